@@ -15,15 +15,18 @@ pub async fn run_admin_server(
     filter_channel: tokio::sync::mpsc::Sender<String>,
     span_channel: tokio::sync::mpsc::Sender<bool>,
     re_handle: Arc<Mutex<regex::Regex>>,
+    spool: Option<Arc<bep::Spool>>,
 ) -> anyhow::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(filter_channel.clone()))
             .app_data(web::Data::new(span_channel.clone()))
             .app_data(web::Data::new(re_handle.clone()))
+            .app_data(web::Data::new(spool.clone()))
             .service(set_filter)
             .service(set_span)
             .service(metrics_handler)
+            .service(spool_handler)
             .service(debug_message_handler)
             .service(debug_mem_stats_handler)
             .service(debug_mem_profile_handler)
@@ -84,6 +87,17 @@ async fn metrics_handler() -> Result<HttpResponse> {
         .body(body))
 }
 
+#[get("/admin/spool")]
+#[instrument(skip(spool))]
+async fn spool_handler(spool: web::Data<Option<Arc<bep::Spool>>>) -> Result<HttpResponse> {
+    let stats = spool
+        .as_ref()
+        .as_ref()
+        .ok_or_else(|| error::ErrorNotFound("spool is disabled, see --spool_dir"))?
+        .stats();
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 #[post("/admin/debug_message")]
 #[instrument(skip(re_handle))]
 async fn debug_message_handler(
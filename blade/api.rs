@@ -0,0 +1,905 @@
+//! Versioned JSON API served under `/api/v1`.
+//!
+//! Unlike the Leptos server functions, the types here are a stable wire
+//! format: timestamps are milliseconds since the Unix epoch and durations are
+//! milliseconds.
+//!
+//! Endpoints:
+//! - `GET  /api/v1/invocations`: list invocations, newest first. Query
+//!   parameters: `status`, `command`, `pattern`, `start_from_ms`,
+//!   `start_to_ms`, `metadata` and `workspace_status` (repeatable `KEY=VALUE`),
+//!   `project_id`, `keyword`, `tool_tag`, `exit_code`, `failure_category`,
+//!   `build_tool_version`, `sort_by` (`start`, `end`, `command`, `status`),
+//!   `ascending`, `offset` and `limit`.
+//! - `GET  /api/v1/invocations/{id}`: a single invocation.
+//! - `GET  /api/v1/invocations/{id}/targets`: targets of an invocation.
+//! - `GET  /api/v1/invocations/{id}/tests`: tests with their runs and
+//!   artifacts. `name` restricts the result to a single test.
+//! - `GET  /api/v1/invocations/{id}/options`: build options, metadata and
+//!   workspace status.
+//! - `GET  /api/v1/invocations/{id}/output`: console output as plain text.
+//! - `GET  /api/v1/tests/history`: history of the test `name`, limited by
+//!   `limit` and `days`.
+//! - `POST /api/v1/tests/history`: same as above with a JSON body
+//!   (`TestHistoryRequest`) that also accepts filters.
+//! - `POST /api/v1/invocations/{id}/events`: ingests a (chunked) body of
+//!   newline delimited `BuildEvent` JSON, as written by
+//!   `--build_event_json_file`, for builds that can't reach the gRPC port.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::*;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+// Mirrors the defaults of the test history page.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+const DEFAULT_HISTORY_DAYS: u32 = 30;
+const MAX_PAGE_SIZE: usize = 1000;
+// Same limit as a single gRPC build event message.
+const MAX_EVENT_SIZE: usize = 10 * 1024 * 1024;
+
+pub fn service() -> Scope {
+    web::scope("/api/v1")
+        .service(list_invocations)
+        .service(get_invocation)
+        .service(get_targets)
+        .service(get_tests)
+        .service(get_options)
+        .service(get_output)
+        .service(get_test_history)
+        .service(post_test_history)
+        .service(post_events)
+}
+
+fn to_ms(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn from_ms(ms: i64) -> SystemTime { UNIX_EPOCH + Duration::from_millis(ms.max(0) as u64) }
+
+// Only a missing row is a 404, a database outage must not look like a deleted
+// invocation.
+fn lookup_error(e: anyhow::Error) -> Error {
+    if db::is_not_found(&e) {
+        return error::ErrorNotFound(format!("{e:#}"));
+    }
+    internal(e)
+}
+
+fn internal(e: anyhow::Error) -> Error { error::ErrorInternalServerError(format!("{e:#}")) }
+
+#[derive(Serialize)]
+struct Invocation {
+    id: String,
+    status: state::Status,
+    command: String,
+    pattern: Vec<String>,
+    start_ms: i64,
+    end_ms: Option<i64>,
+    last_heartbeat_ms: Option<i64>,
+    is_live: bool,
+    profile_uri: Option<String>,
+    build_id: Option<String>,
+    attempt_number: Option<i64>,
+    project_id: Option<String>,
+    notification_keywords: Vec<String>,
+    tool_tag: Option<String>,
+    exit_code: Option<state::ExitCode>,
+    failure_detail: Option<state::FailureDetail>,
+    build_tool_version: Option<String>,
+    workspace_directory: Option<String>,
+    working_directory: Option<String>,
+    server_pid: Option<i64>,
+    options_description: Option<String>,
+    build_user: Option<String>,
+    build_host: Option<String>,
+}
+
+impl From<&state::InvocationResults> for Invocation {
+    fn from(i: &state::InvocationResults) -> Self {
+        Self {
+            id: i.id.clone(),
+            status: i.status,
+            command: i.command.clone(),
+            pattern: i.pattern.clone(),
+            start_ms: to_ms(i.start),
+            end_ms: i.end.map(to_ms),
+            last_heartbeat_ms: i.last_heartbeat.map(to_ms),
+            is_live: i.is_live(),
+            profile_uri: i.profile_uri.clone(),
+            build_id: i.build_id.clone(),
+            attempt_number: i.attempt_number,
+            project_id: i.project_id.clone(),
+            notification_keywords: i.notification_keywords.clone(),
+            tool_tag: i.tool_tag.clone(),
+            exit_code: i.exit_code.clone(),
+            failure_detail: i.failure_detail.clone(),
+            build_tool_version: i.build_tool_version.clone(),
+            workspace_directory: i.workspace_directory.clone(),
+            working_directory: i.working_directory.clone(),
+            server_pid: i.server_pid,
+            options_description: i.options_description.clone(),
+            build_user: i.build_user.clone(),
+            build_host: i.build_host.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct InvocationPage {
+    invocations: Vec<Invocation>,
+    total: usize,
+    offset: usize,
+    limit: usize,
+}
+
+#[derive(Serialize)]
+struct Target {
+    name: String,
+    status: state::Status,
+    kind: String,
+    start_ms: i64,
+    end_ms: Option<i64>,
+    failure: Option<state::TargetFailure>,
+}
+
+impl From<&state::Target> for Target {
+    fn from(t: &state::Target) -> Self {
+        Self {
+            name: t.name.clone(),
+            status: t.status,
+            kind: t.kind.clone(),
+            start_ms: to_ms(t.start),
+            end_ms: t.end.map(to_ms),
+            failure: t.failure.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Artifact {
+    name: String,
+    uri: String,
+    size: usize,
+}
+
+#[derive(Serialize)]
+struct TestRun {
+    run: i32,
+    shard: i32,
+    attempt: i32,
+    status: state::Status,
+    detailed_status: Option<state::TestStatus>,
+    details: String,
+    duration_ms: i64,
+    artifacts: Vec<Artifact>,
+}
+
+impl From<&state::TestRun> for TestRun {
+    fn from(r: &state::TestRun) -> Self {
+        let mut artifacts = r
+            .files
+            .iter()
+            .map(|(name, a)| Artifact {
+                name: name.clone(),
+                uri: a.uri.clone(),
+                size: a.size,
+            })
+            .collect::<Vec<_>>();
+        artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            run: r.run,
+            shard: r.shard,
+            attempt: r.attempt,
+            status: r.status,
+            detailed_status: r.detailed_status,
+            details: r.details.clone(),
+            duration_ms: r.duration.as_millis() as i64,
+            artifacts,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Test {
+    name: String,
+    status: state::Status,
+    detailed_status: Option<state::TestStatus>,
+    duration_ms: i64,
+    end_ms: i64,
+    num_runs: usize,
+    runs: Vec<TestRun>,
+}
+
+impl From<&state::Test> for Test {
+    fn from(t: &state::Test) -> Self {
+        Self {
+            name: t.name.clone(),
+            status: t.status,
+            detailed_status: t.detailed_status,
+            duration_ms: t.duration.as_millis() as i64,
+            end_ms: to_ms(t.end),
+            num_runs: t.num_runs,
+            runs: t.runs.iter().map(TestRun::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Options {
+    unstructured: Vec<String>,
+    structured: HashMap<String, Vec<String>>,
+    startup: Vec<String>,
+    explicit_startup: Vec<String>,
+    cmd_line: Vec<String>,
+    explicit_cmd_line: Vec<String>,
+    build_metadata: HashMap<String, String>,
+    workspace_status: HashMap<String, String>,
+}
+
+impl From<state::BuildOptions> for Options {
+    fn from(o: state::BuildOptions) -> Self {
+        Self {
+            unstructured: o.unstructured,
+            structured: o.structured,
+            startup: o.startup,
+            explicit_startup: o.explicit_startup,
+            cmd_line: o.cmd_line,
+            explicit_cmd_line: o.explicit_cmd_line,
+            build_metadata: o.build_metadata,
+            workspace_status: o.workspace_status,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TestHistoryPoint {
+    invocation_id: String,
+    start_ms: i64,
+    test: Test,
+}
+
+#[derive(Serialize)]
+struct TestHistory {
+    name: String,
+    history: Vec<TestHistoryPoint>,
+    total_found: usize,
+    limit_applied: usize,
+    was_truncated: bool,
+    from_ms: Option<i64>,
+    to_ms: Option<i64>,
+}
+
+impl From<state::TestHistory> for TestHistory {
+    fn from(h: state::TestHistory) -> Self {
+        Self {
+            name: h.name,
+            history: h
+                .history
+                .iter()
+                .map(|p| TestHistoryPoint {
+                    invocation_id: p.invocation_id.clone(),
+                    start_ms: to_ms(p.start),
+                    test: Test::from(&p.test),
+                })
+                .collect(),
+            total_found: h.total_found,
+            limit_applied: h.limit_applied,
+            was_truncated: h.was_truncated,
+            from_ms: h.query_date_range.map(|r| to_ms(r.0)),
+            to_ms: h.query_date_range.map(|r| to_ms(r.1)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum FilterOp {
+    Equals,
+    Contains,
+    GreaterThan,
+    LessThan,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case", tag = "type")]
+enum FilterItem {
+    Start { ms: i64 },
+    Duration { ms: i64 },
+    Metadata { key: String, value: String },
+    BazelFlags { flag: String, value: String },
+    Status { status: state::Status },
+    LogOutput { text: String },
+    DateRange { from_ms: i64, to_ms: i64 },
+    ProjectId { project_id: String },
+    NotificationKeyword { keyword: String },
+    ToolTag { tool_tag: String },
+}
+
+/// A test history filter, e.g.
+/// `{"op": "equals", "type": "metadata", "key": "BRANCH", "value": "main"}`.
+#[derive(Deserialize, Debug, Clone)]
+struct Filter {
+    op: FilterOp,
+    #[serde(default)]
+    invert: bool,
+    #[serde(flatten)]
+    filter: FilterItem,
+}
+
+impl From<Filter> for state::TestFilter {
+    fn from(f: Filter) -> Self {
+        state::TestFilter {
+            op: match f.op {
+                FilterOp::Equals => state::TestFilterOp::Equals,
+                FilterOp::Contains => state::TestFilterOp::Contains,
+                FilterOp::GreaterThan => state::TestFilterOp::GreaterThan,
+                FilterOp::LessThan => state::TestFilterOp::LessThan,
+            },
+            invert: f.invert,
+            filter: match f.filter {
+                FilterItem::Start { ms } => state::TestFilterItem::Start(from_ms(ms)),
+                FilterItem::Duration { ms } => {
+                    state::TestFilterItem::Duration(Duration::from_millis(ms.max(0) as u64))
+                },
+                FilterItem::Metadata { key, value } => {
+                    state::TestFilterItem::Metadata { key, value }
+                },
+                FilterItem::BazelFlags { flag, value } => {
+                    state::TestFilterItem::BazelFlags { flag, value }
+                },
+                FilterItem::Status { status } => state::TestFilterItem::Status(status),
+                FilterItem::LogOutput { text } => state::TestFilterItem::LogOutput(text),
+                FilterItem::DateRange {
+                    from_ms: from,
+                    to_ms: to,
+                } => state::TestFilterItem::DateRange {
+                    from: from_ms(from),
+                    to: from_ms(to),
+                },
+                FilterItem::ProjectId { project_id } => {
+                    state::TestFilterItem::ProjectId(project_id)
+                },
+                FilterItem::NotificationKeyword { keyword } => {
+                    state::TestFilterItem::NotificationKeyword(keyword)
+                },
+                FilterItem::ToolTag { tool_tag } => state::TestFilterItem::ToolTag(tool_tag),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TestHistoryRequest {
+    name: String,
+    #[serde(default)]
+    filters: Vec<Filter>,
+    limit: Option<usize>,
+    days: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestHistoryQuery {
+    name: String,
+    limit: Option<usize>,
+    days: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestsQuery {
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum SortField {
+    Start,
+    End,
+    Command,
+    Status,
+}
+
+#[derive(Deserialize, Debug)]
+struct ListQuery {
+    status: Option<state::Status>,
+    command: Option<String>,
+    pattern: Option<String>,
+    start_from_ms: Option<i64>,
+    start_to_ms: Option<i64>,
+    project_id: Option<String>,
+    keyword: Option<String>,
+    tool_tag: Option<String>,
+    exit_code: Option<String>,
+    failure_category: Option<String>,
+    build_tool_version: Option<String>,
+    sort_by: Option<SortField>,
+    #[serde(default)]
+    ascending: bool,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+// `metadata` and `workspace_status` may be repeated, which serde_urlencoded
+// can't collect into a struct field, so they are pulled out of the query
+// string separately.
+fn key_value_params(query: &str, name: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .filter(|(k, _)| k == name)
+        .filter_map(|(_, v)| {
+            v.split_once('=')
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+        })
+        .collect()
+}
+
+#[get("/invocations")]
+#[instrument(skip(global))]
+async fn list_invocations(
+    global: web::Data<Arc<state::Global>>,
+    req: HttpRequest,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let defaults = state::InvocationQuery::default();
+    let q = state::InvocationQuery {
+        filter: state::InvocationFilter {
+            status: query.status,
+            command: query.command,
+            pattern: query.pattern,
+            start_from: query.start_from_ms.map(from_ms),
+            start_to: query.start_to_ms.map(from_ms),
+            metadata: key_value_params(req.query_string(), "metadata"),
+            project_id: query.project_id,
+            keyword: query.keyword,
+            tool_tag: query.tool_tag,
+            exit_code: query.exit_code,
+            failure_category: query.failure_category,
+            build_tool_version: query.build_tool_version,
+            workspace_status: key_value_params(req.query_string(), "workspace_status"),
+        },
+        sort_by: match query.sort_by {
+            Some(SortField::Start) | None => state::InvocationSortField::Start,
+            Some(SortField::End) => state::InvocationSortField::End,
+            Some(SortField::Command) => state::InvocationSortField::Command,
+            Some(SortField::Status) => state::InvocationSortField::Status,
+        },
+        ascending: query.ascending,
+        offset: query.offset.unwrap_or(defaults.offset),
+        limit: query.limit.unwrap_or(defaults.limit).min(MAX_PAGE_SIZE),
+    };
+    let page = db::run(global.db_manager.clone(), move |db| db.list_invocations(&q))
+        .await
+        .map_err(internal)?;
+    Ok(HttpResponse::Ok().json(InvocationPage {
+        invocations: page.invocations.iter().map(Invocation::from).collect(),
+        total: page.total,
+        offset: page.offset,
+        limit: page.limit,
+    }))
+}
+
+#[get("/invocations/{id}")]
+#[instrument(skip(global))]
+async fn get_invocation(
+    global: web::Data<Arc<state::Global>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let inv = db::run(global.db_manager.clone(), move |db| {
+        db.get_shallow_invocation(&id)
+    })
+    .await
+    .map_err(lookup_error)?;
+    Ok(HttpResponse::Ok().json(Invocation::from(&inv)))
+}
+
+#[get("/invocations/{id}/targets")]
+#[instrument(skip(global))]
+async fn get_targets(
+    global: web::Data<Arc<state::Global>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let inv = db::run_group(global.db_manager.clone(), move |db| db.get_invocation(&id))
+        .await
+        .map_err(lookup_error)?;
+    let mut targets = inv.targets.values().map(Target::from).collect::<Vec<_>>();
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(HttpResponse::Ok().json(targets))
+}
+
+#[get("/invocations/{id}/tests")]
+#[instrument(skip(global))]
+async fn get_tests(
+    global: web::Data<Arc<state::Global>>,
+    id: web::Path<String>,
+    query: web::Query<TestsQuery>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let name = query.into_inner().name;
+    let inv = db::run_group(global.db_manager.clone(), move |db| db.get_invocation(&id))
+        .await
+        .map_err(lookup_error)?;
+    let mut tests = inv
+        .tests
+        .values()
+        .filter(|t| name.as_ref().is_none_or(|n| *n == t.name))
+        .map(Test::from)
+        .collect::<Vec<_>>();
+    tests.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(HttpResponse::Ok().json(tests))
+}
+
+#[get("/invocations/{id}/options")]
+#[instrument(skip(global))]
+async fn get_options(
+    global: web::Data<Arc<state::Global>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let opts = db::run(global.db_manager.clone(), move |db| {
+        db.get_shallow_invocation(&id)?;
+        db.get_options(&id)
+    })
+    .await
+    .map_err(lookup_error)?;
+    Ok(HttpResponse::Ok().json(Options::from(opts)))
+}
+
+#[get("/invocations/{id}/output")]
+#[instrument(skip(global))]
+async fn get_output(
+    global: web::Data<Arc<state::Global>>,
+    id: web::Path<String>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let output = db::run_group(global.db_manager.clone(), move |db| {
+        db.get_shallow_invocation(&id)?;
+        db.get_progress(&id)
+    })
+    .await
+    .map_err(lookup_error)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(output))
+}
+
+async fn test_history(
+    global: &state::Global,
+    name: String,
+    filters: Vec<state::TestFilter>,
+    limit: Option<usize>,
+    days: Option<u32>,
+) -> Result<HttpResponse> {
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT).min(MAX_PAGE_SIZE);
+    let days = days.unwrap_or(DEFAULT_HISTORY_DAYS);
+    let history = db::run(global.db_manager.clone(), move |db| {
+        db.get_test_history(&name, &filters, limit, Some(days))
+    })
+    .await
+    .map_err(internal)?;
+    Ok(HttpResponse::Ok().json(TestHistory::from(history)))
+}
+
+#[get("/tests/history")]
+#[instrument(skip(global))]
+async fn get_test_history(
+    global: web::Data<Arc<state::Global>>,
+    query: web::Query<TestHistoryQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    test_history(&global, query.name, vec![], query.limit, query.days).await
+}
+
+#[post("/tests/history")]
+#[instrument(skip(global))]
+async fn post_test_history(
+    global: web::Data<Arc<state::Global>>,
+    body: web::Json<TestHistoryRequest>,
+) -> Result<HttpResponse> {
+    let body = body.into_inner();
+    let filters = body.filters.into_iter().map(Into::into).collect();
+    test_history(&global, body.name, filters, body.limit, body.days).await
+}
+
+#[derive(Serialize)]
+struct IngestResponse {
+    invocation_id: String,
+    events: i64,
+    build_finished: bool,
+}
+
+#[post("/invocations/{id}/events")]
+#[instrument(skip(global, print_message_re, payload))]
+async fn post_events(
+    global: web::Data<Arc<state::Global>>,
+    print_message_re: web::Data<Arc<Mutex<regex::Regex>>>,
+    id: web::Path<String>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    let mut ingester = bep::Ingester::new(
+        global.get_ref().clone(),
+        &id,
+        print_message_re.get_ref().clone(),
+    );
+    let mut buf: Vec<u8> = vec![];
+    // Events are processed as soon as their line is complete so that progress
+    // and heartbeats show up while the build is still running.
+    loop {
+        let flush_deadline = ingester.flush_deadline();
+        let chunk = tokio::select! {
+            // Store received events even if the client goes quiet.
+            _ = bep::flush_timer(flush_deadline) => {
+                if let Err(e) = ingester.finish().await {
+                    ingester.abort().await;
+                    return Err(internal(e));
+                }
+                continue;
+            },
+            chunk = payload.next() => match chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    ingester.abort().await;
+                    return Err(error::ErrorBadRequest(format!("failed to read body: {e}")));
+                },
+                None => break,
+            },
+        };
+        buf.extend_from_slice(&chunk);
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let line = buf.drain(..=pos).collect::<Vec<_>>();
+            if let Err(e) = ingester.push_json(&line).await {
+                ingester.abort().await;
+                return Err(error::ErrorBadRequest(format!("{e:#}")));
+            }
+        }
+        if buf.len() > MAX_EVENT_SIZE {
+            ingester.abort().await;
+            return Err(error::ErrorPayloadTooLarge("build event too large"));
+        }
+    }
+    if let Err(e) = ingester.push_json(&buf).await {
+        ingester.abort().await;
+        return Err(error::ErrorBadRequest(format!("{e:#}")));
+    }
+    if let Err(e) = ingester.finish().await {
+        ingester.abort().await;
+        return Err(internal(e));
+    }
+    let resp = IngestResponse {
+        invocation_id: id,
+        events: ingester.events(),
+        build_finished: ingester.is_build_over(),
+    };
+    // A body that ends before the build finished is a dropped stream.
+    ingester.abort().await;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{TestRequest, call_and_read_body_json, call_service, init_service};
+    use testonly::{Unavailable, global, sqlite};
+
+    use super::*;
+
+    macro_rules! app {
+        ($global:expr) => {
+            init_service(
+                App::new()
+                    .app_data(web::Data::new($global))
+                    .app_data(web::Data::new(Arc::new(Mutex::new(
+                        regex::Regex::new("").unwrap(),
+                    ))))
+                    .service(service()),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn test_invocations() {
+        let tmp = tempdir::TempDir::new("test_invocations").unwrap();
+        let global = Arc::new(global(sqlite(&tmp)));
+        db::run(global.db_manager.clone(), |db| {
+            for (i, command) in ["build", "test"].into_iter().enumerate() {
+                db.upsert_shallow_invocation(&state::InvocationResults {
+                    id: format!("inv{i}"),
+                    command: command.to_string(),
+                    status: state::Status::Success,
+                    start: from_ms(1_700_000_000_000 + i as i64),
+                    ..Default::default()
+                })?;
+            }
+            db.upsert_target(
+                "inv1",
+                &state::Target {
+                    name: "//:target".to_string(),
+                    status: state::Status::Success,
+                    kind: "rule".to_string(),
+                    start: from_ms(1_700_000_000_000),
+                    end: None,
+                    failure: None,
+                },
+            )
+        })
+        .await
+        .unwrap();
+        let app = app!(global);
+
+        let page: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get().uri("/api/v1/invocations").to_request(),
+        )
+        .await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["invocations"][0]["id"], "inv1");
+        assert_eq!(page["invocations"][1]["id"], "inv0");
+
+        let page: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/api/v1/invocations?command=build")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["invocations"][0]["id"], "inv0");
+
+        let inv: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/api/v1/invocations/inv1")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(inv["command"], "test");
+        assert_eq!(inv["start_ms"], 1_700_000_000_001_i64);
+
+        let targets: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/api/v1/invocations/inv1/targets")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(targets[0]["name"], "//:target");
+
+        for path in ["", "/targets", "/tests", "/options", "/output"] {
+            let resp = call_service(
+                &app,
+                TestRequest::get()
+                    .uri(&format!("/api/v1/invocations/missing{path}"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), http::StatusCode::NOT_FOUND, "{path}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_database_unavailable() {
+        let app = app!(Arc::new(global(Arc::new(Unavailable))));
+        for path in [
+            "/invocations",
+            "/invocations/inv",
+            "/invocations/inv/output",
+        ] {
+            let resp = call_service(
+                &app,
+                TestRequest::get()
+                    .uri(&format!("/api/v1{path}"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(
+                resp.status(),
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                "{path}"
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_post_events() {
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let tmp = tempdir::TempDir::new("test_post_events").unwrap();
+        let global = Arc::new(global(sqlite(&tmp)));
+        let app = app!(global.clone());
+
+        let body = [
+            r#"{"id":{"started":{}},"started":{"uuid":"inv","command":"build"}}"#,
+            r#"{"id":{"progress":{"opaqueCount":1}},"progress":{"stdout":"hello\n"}}"#,
+            r#"{"id":{"buildFinished":{}},"finished":{"exitCode":{"name":"SUCCESS"}},"lastMessage":true}"#,
+        ]
+        .join("\n");
+        let resp: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/invocations/inv/events")
+                .set_payload(body)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp["invocation_id"], "inv");
+        assert_eq!(resp["events"], 3);
+        assert_eq!(resp["build_finished"], true);
+
+        let (inv, output) = db::run(global.db_manager.clone(), |db| {
+            Ok((db.get_shallow_invocation("inv")?, db.get_progress("inv")?))
+        })
+        .await
+        .unwrap();
+        assert_eq!(inv.command, "build");
+        assert!(output.contains("hello"));
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/invocations/bad/events")
+                .set_payload("not json\n")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_post_events_flush() {
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let tmp = tempdir::TempDir::new("test_post_events_flush").unwrap();
+        let global = Arc::new(global(sqlite(&tmp)));
+        let app = app!(global.clone());
+
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let body: std::pin::Pin<
+            Box<dyn futures::Stream<Item = Result<web::Bytes, error::PayloadError>>>,
+        > = Box::pin(rx);
+        let (req, _) = TestRequest::post()
+            .uri("/api/v1/invocations/inv/events")
+            .to_request()
+            .replace_payload(body.into());
+        let send = async {
+            let line = |json: &str| Ok(web::Bytes::from(format!("{json}\n")));
+            tx.unbounded_send(line(
+                r#"{"id":{"started":{}},"started":{"uuid":"inv","command":"build"}}"#,
+            ))
+            .unwrap();
+            tx.unbounded_send(line(
+                r#"{"id":{"progress":{"opaqueCount":1}},"progress":{"stdout":"hello\n"}}"#,
+            ))
+            .unwrap();
+            // The progress shows up while the body is still being sent.
+            let mut output = String::new();
+            for _ in 0..50 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                output = db::run(global.db_manager.clone(), |db| db.get_progress("inv"))
+                    .await
+                    .unwrap_or_default();
+                if !output.is_empty() {
+                    break;
+                }
+            }
+            tx.unbounded_send(line(
+                r#"{"id":{"buildFinished":{}},"finished":{"exitCode":{"name":"SUCCESS"}},"lastMessage":true}"#,
+            ))
+            .unwrap();
+            tx.close_channel();
+            output
+        };
+        let (resp, output): (serde_json::Value, String) =
+            futures::join!(call_and_read_body_json(&app, req), send);
+        assert!(output.contains("hello"));
+        assert_eq!(resp["events"], 3);
+        assert_eq!(resp["build_finished"], true);
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Context;
+use build_event_stream_proto::build_event_stream;
+
+use crate::EventHandler;
+
+pub struct Handler {}
+
+fn file_uri(f: Option<&build_event_stream::File>) -> Option<String> {
+    match f?.file.as_ref()? {
+        build_event_stream::file::File::Uri(uri) => Some(uri.clone()),
+        _ => None,
+    }
+}
+
+impl EventHandler for Handler {
+    fn handle_event(
+        &self,
+        db: &mut dyn state::DB,
+        invocation_id: &str,
+        event: &build_event_stream::BuildEvent,
+    ) -> anyhow::Result<()> {
+        let Some(build_event_stream::build_event::Payload::Action(action)) = &event.payload else {
+            return Ok(());
+        };
+        if action.success {
+            return Ok(());
+        }
+        let id = match event.id.as_ref().and_then(|id| id.id.as_ref()) {
+            Some(build_event_stream::build_event_id::Id::ActionCompleted(id)) => Some(id),
+            _ => None,
+        };
+        #[allow(deprecated)]
+        let label = id
+            .map(|id| id.label.clone())
+            .filter(|l| !l.is_empty())
+            .unwrap_or_else(|| action.label.clone());
+        let primary_output = id
+            .map(|id| id.primary_output.clone())
+            .filter(|p| !p.is_empty())
+            .or_else(|| {
+                action
+                    .primary_output
+                    .as_ref()
+                    .map(|f| f.name.clone())
+                    .filter(|p| !p.is_empty())
+            });
+        let failure = state::ActionFailure {
+            label: label.clone(),
+            mnemonic: action.r#type.clone(),
+            exit_code: action.exit_code,
+            command_line: action.command_line.clone(),
+            primary_output,
+            stdout_uri: file_uri(action.stdout.as_ref()),
+            stderr_uri: file_uri(action.stderr.as_ref()),
+        };
+        db.insert_action_failure(invocation_id, &failure)
+            .context(format!("failed to insert action failure: {label}"))
+    }
+}
@@ -1,36 +1,66 @@
 use anyhow::Context;
 use build_event_stream_proto::build_event_stream;
-use state::DBManager;
 
 pub(crate) struct Handler {}
 
+fn non_empty(s: &str) -> Option<String> { (!s.is_empty()).then(|| s.to_string()) }
+
 impl crate::EventHandler for Handler {
     fn handle_event(
         &self,
-        db_mgr: &dyn DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
         match &event.payload {
             Some(build_event_stream::build_event::Payload::Started(p)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
-                let start = p.start_time.clone();
-                let command = p.command.clone();
+                let p = p.clone();
                 db.update_shallow_invocation(
                     invocation_id,
                     Box::new(move |i: &mut state::InvocationResults| {
-                        i.start = start
+                        i.start = p
+                            .start_time
                             .as_ref()
                             .and_then(|s| prototime::timestamp::from_proto(s).ok())
                             .unwrap_or_else(std::time::SystemTime::now);
-                        i.command = command;
+                        i.command = p.command;
+                        i.build_tool_version = non_empty(&p.build_tool_version);
+                        i.workspace_directory = non_empty(&p.workspace_directory);
+                        i.working_directory = non_empty(&p.working_directory);
+                        i.server_pid = (p.server_pid != 0).then_some(p.server_pid);
+                        i.options_description = non_empty(&p.options_description);
                         Ok(())
                     }),
                 )
                 .context("failed to insert invocation")?;
             },
+            Some(build_event_stream::build_event::Payload::WorkspaceStatus(ws)) => {
+                let status = ws
+                    .item
+                    .iter()
+                    .map(|i| (i.key.clone(), i.value.clone()))
+                    .collect::<std::collections::HashMap<_, _>>();
+                let user = status.get("BUILD_USER").filter(|u| !u.is_empty()).cloned();
+                let host = status.get("BUILD_HOST").filter(|h| !h.is_empty()).cloned();
+                db.insert_options(
+                    invocation_id,
+                    &state::BuildOptions {
+                        workspace_status: status,
+                        ..Default::default()
+                    },
+                )
+                .context("failed to insert workspace status")?;
+                db.update_shallow_invocation(
+                    invocation_id,
+                    Box::new(move |i: &mut state::InvocationResults| {
+                        i.build_user = user;
+                        i.build_host = host;
+                        Ok(())
+                    }),
+                )
+                .context("failed to update build user and host")?;
+            },
             Some(build_event_stream::build_event::Payload::Expanded(_)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let pattern = event
                     .id
                     .as_ref()
@@ -0,0 +1,101 @@
+use anyhow::Context;
+use build_event_stream_proto::build_event_stream;
+
+use crate::EventHandler;
+
+pub struct Handler {}
+
+fn to_duration(d: Option<&duration_proto::google::protobuf::Duration>) -> std::time::Duration {
+    d.and_then(|d| prototime::duration::from_proto(d).ok())
+        .unwrap_or_default()
+}
+
+fn files_metric(
+    f: Option<&build_event_stream::build_metrics::artifact_metrics::FilesMetric>,
+) -> (i64, i64) {
+    f.map(|f| (f.size_in_bytes, f.count as i64))
+        .unwrap_or_default()
+}
+
+fn to_state(m: &build_event_stream::BuildMetrics) -> state::BuildMetrics {
+    let mut ret = state::BuildMetrics::default();
+    if let Some(a) = &m.action_summary {
+        ret.actions_created = a.actions_created;
+        ret.actions_executed = a.actions_executed;
+        if let Some(stats) = &a.action_cache_statistics {
+            ret.action_cache_hits = stats.hits as i64;
+            ret.action_cache_misses = stats.misses as i64;
+        }
+        ret.actions = a
+            .action_data
+            .iter()
+            .map(|d| state::ActionMetrics {
+                mnemonic: d.mnemonic.clone(),
+                actions_executed: d.actions_executed,
+                first_started_ms: d.first_started_ms,
+                last_ended_ms: d.last_ended_ms,
+                system_time: to_duration(d.system_time.as_ref()),
+                user_time: to_duration(d.user_time.as_ref()),
+            })
+            .collect();
+        ret.runners = a
+            .runner_count
+            .iter()
+            .map(|r| state::RunnerMetrics {
+                name: r.name.clone(),
+                exec_kind: r.exec_kind.clone(),
+                count: r.count,
+            })
+            .collect();
+    }
+    if let Some(mem) = &m.memory_metrics {
+        ret.used_heap_size_post_build = mem.used_heap_size_post_build;
+        ret.peak_post_gc_heap_size = mem.peak_post_gc_heap_size;
+    }
+    if let Some(t) = &m.target_metrics {
+        ret.targets_loaded = t.targets_loaded;
+        ret.targets_configured = t.targets_configured;
+    }
+    if let Some(p) = &m.package_metrics {
+        ret.packages_loaded = p.packages_loaded;
+    }
+    if let Some(t) = &m.timing_metrics {
+        ret.cpu_time_ms = t.cpu_time_in_ms;
+        ret.wall_time_ms = t.wall_time_in_ms;
+        ret.analysis_phase_time_ms = t.analysis_phase_time_in_ms;
+        ret.execution_phase_time_ms = t.execution_phase_time_in_ms;
+    }
+    if let Some(a) = &m.artifact_metrics {
+        (
+            ret.source_artifacts_read_bytes,
+            ret.source_artifacts_read_count,
+        ) = files_metric(a.source_artifacts_read.as_ref());
+        (
+            ret.output_artifacts_seen_bytes,
+            ret.output_artifacts_seen_count,
+        ) = files_metric(a.output_artifacts_seen.as_ref());
+        (
+            ret.output_artifacts_from_action_cache_bytes,
+            ret.output_artifacts_from_action_cache_count,
+        ) = files_metric(a.output_artifacts_from_action_cache.as_ref());
+        (ret.top_level_artifacts_bytes, ret.top_level_artifacts_count) =
+            files_metric(a.top_level_artifacts.as_ref());
+    }
+    ret
+}
+
+impl EventHandler for Handler {
+    fn handle_event(
+        &self,
+        db: &mut dyn state::DB,
+        invocation_id: &str,
+        event: &build_event_stream::BuildEvent,
+    ) -> anyhow::Result<()> {
+        let Some(build_event_stream::build_event::Payload::BuildMetrics(metrics)) = &event.payload
+        else {
+            return Ok(());
+        };
+        db.upsert_build_metrics(invocation_id, &to_state(metrics))
+            .context("failed to insert build metrics")
+    }
+}
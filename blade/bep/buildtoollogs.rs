@@ -7,7 +7,7 @@ pub struct Handler {}
 impl EventHandler for Handler {
     fn handle_event(
         &self,
-        db_mgr: &dyn state::DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
@@ -19,7 +19,6 @@ impl EventHandler for Handler {
                     && let Some(build_event_stream::file::File::Uri(uri)) = &log.file
                 {
                     // Update the invocation with the profile URI
-                    let mut db = db_mgr.get()?;
                     let uri2 = uri.clone();
                     db.update_shallow_invocation(
                         invocation_id,
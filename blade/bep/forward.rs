@@ -0,0 +1,467 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use anyhow::Context;
+use build_proto::google::devtools::build::v1::*;
+use lazy_static::lazy_static;
+use prometheus_client::{
+    encoding::EncodeLabelSet,
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+};
+use prost::Message;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{
+    codec::{Codec, EncodeBuf, Encoder, ProstCodec},
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Endpoint},
+};
+
+// Events buffered per upstream and stream before the upstream is dropped from
+// the stream. Forwarding never slows down the local pipeline.
+const MAX_BUFFERED_EVENTS: usize = 10_000;
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+const MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(30);
+
+lazy_static! {
+    static ref FORWARDED_EVENTS: Family::<UpstreamLabels, Counter> = metrics::register_metric(
+        "blade_bep_forwarded_events",
+        "Total number of events acknowledged by an upstream BES",
+        Family::default()
+    );
+    static ref FORWARD_RETRIES: Family::<UpstreamLabels, Counter> = metrics::register_metric(
+        "blade_bep_forward_retries",
+        "Total number of retried streams to an upstream BES",
+        Family::default()
+    );
+    static ref FORWARD_FAILURES: Family::<UpstreamLabels, Counter> = metrics::register_metric(
+        "blade_bep_forward_failures",
+        "Total number of streams that could not be forwarded to an upstream BES",
+        Family::default()
+    );
+    static ref FORWARD_BUFFERED: Family::<UpstreamLabels, Gauge> = metrics::register_metric(
+        "blade_bep_forward_buffered_events",
+        "Number of events waiting to be acknowledged by an upstream BES",
+        Family::default()
+    );
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct UpstreamLabels {
+    upstream: String,
+}
+
+#[derive(Debug)]
+struct Upstream {
+    addr: String,
+    channel: Channel,
+}
+
+impl Upstream {
+    fn labels(&self) -> UpstreamLabels {
+        UpstreamLabels {
+            upstream: self.addr.clone(),
+        }
+    }
+}
+
+/// Forwards every received BES stream to upstream BES backends, in addition
+/// to processing it locally.
+#[derive(Debug)]
+pub struct Forwarder {
+    upstreams: Vec<Arc<Upstream>>,
+}
+
+impl Forwarder {
+    pub fn new(addrs: &[String]) -> anyhow::Result<Self> {
+        let upstreams = addrs
+            .iter()
+            .map(|addr| {
+                let channel = Endpoint::from_shared(addr.clone())
+                    .with_context(|| format!("invalid upstream {addr}"))?
+                    .tcp_keepalive(Some(std::time::Duration::from_secs(20)))
+                    .http2_keep_alive_interval(std::time::Duration::from_secs(20))
+                    .keep_alive_timeout(std::time::Duration::from_secs(30))
+                    .connect_lazy();
+                Ok(Arc::new(Upstream {
+                    addr: addr.clone(),
+                    channel,
+                }))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { upstreams })
+    }
+
+    /// Starts forwarding a new stream. The upstream streams are closed once
+    /// `ForwardStream::finish` was called and everything was sent. They are
+    /// aborted if the `ForwardStream` is dropped without it.
+    pub(crate) fn stream(&self) -> ForwardStream {
+        ForwardStream {
+            upstreams: self
+                .upstreams
+                .iter()
+                .map(|upstream| {
+                    let (tx, rx) = mpsc::channel(MAX_BUFFERED_EVENTS);
+                    let (done_tx, done_rx) = oneshot::channel();
+                    tokio::spawn(forward(upstream.clone(), rx, done_rx));
+                    (upstream.clone(), Some((tx, done_tx)))
+                })
+                .collect(),
+        }
+    }
+}
+
+pub(crate) struct ForwardStream {
+    // Senders are dropped once an upstream falls behind or gives up, which
+    // aborts the upstream stream.
+    upstreams: Vec<(
+        Arc<Upstream>,
+        Option<(
+            mpsc::Sender<PublishBuildToolEventStreamRequest>,
+            oneshot::Sender<()>,
+        )>,
+    )>,
+}
+
+impl ForwardStream {
+    pub fn forward(&mut self, req: &PublishBuildToolEventStreamRequest) {
+        for (upstream, tx) in self.upstreams.iter_mut() {
+            let Some((sender, _)) = tx.as_ref() else {
+                continue;
+            };
+            if let Err(e) = sender.try_send(req.clone()) {
+                if matches!(e, mpsc::error::TrySendError::Full(_)) {
+                    tracing::warn!("{} is falling behind, no longer forwarding", upstream.addr);
+                    FORWARD_FAILURES.get_or_create(&upstream.labels()).inc();
+                }
+                *tx = None;
+            }
+        }
+    }
+
+    /// Marks the local stream as complete, so that the upstream streams are
+    /// closed normally once everything was sent.
+    pub fn finish(self) {
+        for (_, tx) in self.upstreams {
+            if let Some((_, done)) = tx {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+// Encodes requests like the generated client, but fails the request stream on
+// an error instead of ending it. Ending it would make the upstream treat a
+// truncated stream as complete.
+#[derive(Default)]
+struct AbortableCodec {
+    inner: ProstCodec<PublishBuildToolEventStreamRequest, PublishBuildToolEventStreamResponse>,
+}
+
+struct AbortableEncoder;
+
+impl Encoder for AbortableEncoder {
+    type Error = tonic::Status;
+    type Item = Result<PublishBuildToolEventStreamRequest, tonic::Status>;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item?
+            .encode(dst)
+            .map_err(|e| tonic::Status::internal(e.to_string()))
+    }
+}
+
+impl Codec for AbortableCodec {
+    type Decode = PublishBuildToolEventStreamResponse;
+    type Decoder = <ProstCodec<
+        PublishBuildToolEventStreamRequest,
+        PublishBuildToolEventStreamResponse,
+    > as Codec>::Decoder;
+    type Encode = Result<PublishBuildToolEventStreamRequest, tonic::Status>;
+    type Encoder = AbortableEncoder;
+
+    fn encoder(&mut self) -> Self::Encoder { AbortableEncoder }
+
+    fn decoder(&mut self) -> Self::Decoder { self.inner.decoder() }
+}
+
+fn sequence_number(req: &PublishBuildToolEventStreamRequest) -> i64 {
+    req.ordered_build_event
+        .as_ref()
+        .map(|obe| obe.sequence_number)
+        .unwrap_or_default()
+}
+
+// Sends the events of one stream to an upstream. Unacknowledged events are
+// replayed on a new stream after a failure, as Bazel does.
+async fn forward(
+    upstream: Arc<Upstream>,
+    mut rx: mpsc::Receiver<PublishBuildToolEventStreamRequest>,
+    mut done: oneshot::Receiver<()>,
+) {
+    let labels = upstream.labels();
+    let buffered = FORWARD_BUFFERED.get_or_create(&labels).clone();
+    let mut unacked = VecDeque::<PublishBuildToolEventStreamRequest>::new();
+    let mut closed = false;
+    let mut attempts = 0;
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let res = forward_once(
+            &upstream,
+            &mut rx,
+            &mut done,
+            &mut unacked,
+            &mut closed,
+            &buffered,
+        )
+        .await;
+        let progressed = match res {
+            Ok(()) => return,
+            Err((progressed, e)) => {
+                tracing::warn!("failed to forward stream to {}: {e:#}", upstream.addr);
+                progressed
+            },
+        };
+        if progressed {
+            attempts = 0;
+            backoff = INITIAL_BACKOFF;
+        }
+        attempts += 1;
+        if attempts >= MAX_ATTEMPTS {
+            FORWARD_FAILURES.get_or_create(&labels).inc();
+            buffered.dec_by(unacked.len() as i64);
+            return;
+        }
+        FORWARD_RETRIES.get_or_create(&labels).inc();
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+// Returns whether any event was acknowledged along with the error.
+async fn forward_once(
+    upstream: &Upstream,
+    rx: &mut mpsc::Receiver<PublishBuildToolEventStreamRequest>,
+    done: &mut oneshot::Receiver<()>,
+    unacked: &mut VecDeque<PublishBuildToolEventStreamRequest>,
+    closed: &mut bool,
+    buffered: &Gauge,
+) -> Result<(), (bool, anyhow::Error)> {
+    let (tx, out_rx) = mpsc::unbounded_channel();
+    for req in unacked.iter() {
+        let _ = tx.send(Ok(req.clone()));
+    }
+    let mut tx = (!*closed).then_some(tx);
+    let mut client = tonic::client::Grpc::new(upstream.channel.clone());
+    client
+        .ready()
+        .await
+        .context("upstream is not ready")
+        .map_err(|e| (false, e))?;
+    let mut acks = client
+        .streaming(
+            tonic::Request::new(UnboundedReceiverStream::new(out_rx)),
+            PathAndQuery::from_static(
+                "/google.devtools.build.v1.PublishBuildEvent/PublishBuildToolEventStream",
+            ),
+            AbortableCodec::default(),
+        )
+        .await
+        .context("failed to open stream")
+        .map_err(|e| (false, e))?
+        .into_inner();
+    let mut progressed = false;
+    loop {
+        tokio::select! {
+            req = rx.recv(), if tx.is_some() && unacked.len() < MAX_BUFFERED_EVENTS => match req {
+                Some(req) => {
+                    buffered.inc();
+                    unacked.push_back(req.clone());
+                    if let Some(tx) = &tx {
+                        let _ = tx.send(Ok(req));
+                    }
+                },
+                // Closes the upstream stream once everything was sent.
+                None if done.try_recv().is_ok() => {
+                    *closed = true;
+                    tx = None;
+                },
+                None => {
+                    // The local stream failed or the upstream fell behind.
+                    // Failing the request stream resets it, so the upstream
+                    // doesn't mistake the events so far for the whole stream.
+                    tracing::warn!("Aborting stream to {}", upstream.addr);
+                    if let Some(tx) = tx.take() {
+                        let _ = tx.send(Err(tonic::Status::aborted("the forwarded stream failed")));
+                    }
+                    buffered.dec_by(unacked.len() as i64);
+                    return Ok(());
+                },
+            },
+            ack = acks.message() => match ack {
+                Ok(Some(ack)) => {
+                    while unacked.front().is_some_and(|req| sequence_number(req) <= ack.sequence_number) {
+                        unacked.pop_front();
+                        buffered.dec();
+                        FORWARDED_EVENTS.get_or_create(&upstream.labels()).inc();
+                        progressed = true;
+                    }
+                },
+                Ok(None) if *closed && unacked.is_empty() => return Ok(()),
+                Ok(None) => {
+                    return Err((progressed, anyhow::anyhow!("stream closed with {} unacknowledged events", unacked.len())));
+                },
+                Err(e) => return Err((progressed, e.into())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio_stream::wrappers::ReceiverStream;
+
+    use super::*;
+
+    // Acks everything and drops the first connection after `fail_after`
+    // events. Counts the streams that were closed and the ones that failed.
+    #[derive(Default)]
+    struct FakeUpstream {
+        received: Arc<Mutex<Vec<i64>>>,
+        fail_after: Arc<Mutex<Option<usize>>>,
+        closed: Arc<Mutex<(usize, usize)>>,
+    }
+
+    #[tonic::async_trait]
+    impl publish_build_event_server::PublishBuildEvent for FakeUpstream {
+        type PublishBuildToolEventStreamStream =
+            ReceiverStream<Result<PublishBuildToolEventStreamResponse, tonic::Status>>;
+
+        async fn publish_lifecycle_event(
+            &self,
+            _request: tonic::Request<PublishLifecycleEventRequest>,
+        ) -> Result<tonic::Response<empty_proto::google::protobuf::Empty>, tonic::Status> {
+            Ok(tonic::Response::new(
+                empty_proto::google::protobuf::Empty {},
+            ))
+        }
+
+        async fn publish_build_tool_event_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<PublishBuildToolEventStreamRequest>>,
+        ) -> Result<tonic::Response<Self::PublishBuildToolEventStreamStream>, tonic::Status>
+        {
+            let mut in_stream = request.into_inner();
+            let (tx, rx) = mpsc::channel(128);
+            let received = self.received.clone();
+            let fail_after = self.fail_after.lock().unwrap().take();
+            let closed = self.closed.clone();
+            tokio::spawn(async move {
+                let mut count = 0;
+                loop {
+                    let req = match in_stream.message().await {
+                        Ok(Some(req)) => req,
+                        Ok(None) => {
+                            closed.lock().unwrap().0 += 1;
+                            return;
+                        },
+                        Err(_) => {
+                            closed.lock().unwrap().1 += 1;
+                            return;
+                        },
+                    };
+                    if fail_after == Some(count) {
+                        let _ = tx.send(Err(tonic::Status::unavailable("down"))).await;
+                        return;
+                    }
+                    count += 1;
+                    let seq = sequence_number(&req);
+                    received.lock().unwrap().push(seq);
+                    let _ = tx
+                        .send(Ok(PublishBuildToolEventStreamResponse {
+                            stream_id: None,
+                            sequence_number: seq,
+                        }))
+                        .await;
+                }
+            });
+            Ok(tonic::Response::new(ReceiverStream::new(rx)))
+        }
+    }
+
+    fn request(seq: i64) -> PublishBuildToolEventStreamRequest {
+        PublishBuildToolEventStreamRequest {
+            ordered_build_event: Some(OrderedBuildEvent {
+                sequence_number: seq,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn serve(upstream: FakeUpstream) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(publish_build_event_server::PublishBuildEventServer::new(
+                    upstream,
+                ))
+                .serve(addr),
+        );
+        format!("http://{addr}")
+    }
+
+    async fn wait_for<T: PartialEq + std::fmt::Debug>(got: &Mutex<T>, want: T) {
+        for _ in 0..100 {
+            if *got.lock().unwrap() == want {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(*got.lock().unwrap(), want);
+    }
+
+    #[tokio::test]
+    async fn test_forward() {
+        let upstream = FakeUpstream::default();
+        let received = upstream.received.clone();
+        let closed = upstream.closed.clone();
+        *upstream.fail_after.lock().unwrap() = Some(2);
+
+        let forwarder = Forwarder::new(&[
+            serve(upstream),
+            // Nothing listens here, which must not affect the other upstream.
+            "http://127.0.0.1:1".to_string(),
+        ])
+        .unwrap();
+        let mut stream = forwarder.stream();
+        (1..=5).for_each(|seq| stream.forward(&request(seq)));
+        stream.finish();
+
+        // The first connection fails after two events, so the rest is
+        // replayed on a new one, which is closed normally.
+        wait_for(&received, vec![1, 2, 3, 4, 5]).await;
+        wait_for(&closed, (1, 0)).await;
+    }
+
+    #[tokio::test]
+    async fn test_forward_abort() {
+        let upstream = FakeUpstream::default();
+        let received = upstream.received.clone();
+        let closed = upstream.closed.clone();
+
+        let forwarder = Forwarder::new(&[serve(upstream)]).unwrap();
+        let mut stream = forwarder.stream();
+        (1..=2).for_each(|seq| stream.forward(&request(seq)));
+        wait_for(&received, vec![1, 2]).await;
+
+        // The local stream failed, so the upstream must not see a complete
+        // stream.
+        drop(stream);
+        wait_for(&closed, (0, 1)).await;
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, anyhow};
+use build_event_stream_proto::*;
+use build_proto::google::devtools::build::v1 as bes;
+use prost::Message;
+use prost_reflect::{DeserializeOptions, DynamicMessage, ReflectMessage};
+use regex::Regex;
+
+use crate::session::BESSession;
+
+/// Parses a single event of a `--build_event_json_file`.
+pub(crate) fn parse_json_event(line: &[u8]) -> anyhow::Result<build_event_stream::BuildEvent> {
+    let desc = build_event_stream::BuildEvent::default().descriptor();
+    // Newer Bazel versions may report fields we don't know about yet.
+    let opts = DeserializeOptions::new().deny_unknown_fields(false);
+    let mut de = serde_json::Deserializer::from_slice(line);
+    let dm = DynamicMessage::deserialize_with_options(desc, &mut de, &opts)
+        .context("failed to parse event")?;
+    de.end().context("trailing data after event")?;
+    dm.transcode_to::<build_event_stream::BuildEvent>()
+        .context("failed to convert event")
+}
+
+fn is_blank(line: &[u8]) -> bool { line.iter().all(u8::is_ascii_whitespace) }
+
+// Bazel's JSON output is newline delimited JSON objects while the binary
+// output is varint length delimited BuildEvent protos.
+fn parse_json(data: &[u8]) -> anyhow::Result<Vec<build_event_stream::BuildEvent>> {
+    data.split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !is_blank(line))
+        .map(|(i, line)| {
+            parse_json_event(line).with_context(|| format!("invalid event on line {}", i + 1))
+        })
+        .collect()
+}
+
+fn parse_binary(mut data: &[u8]) -> anyhow::Result<Vec<build_event_stream::BuildEvent>> {
+    let mut events = vec![];
+    while !data.is_empty() {
+        let event = build_event_stream::BuildEvent::decode_length_delimited(&mut data)
+            .with_context(|| format!("failed to decode event {}", events.len() + 1))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Parses a `--build_event_json_file` or `--build_event_binary_file`.
+pub(crate) fn parse(data: &[u8]) -> anyhow::Result<Vec<build_event_stream::BuildEvent>> {
+    if data.trim_ascii_start().starts_with(b"{") {
+        // A binary file whose first event happens to be 123 bytes long also
+        // starts with '{'.
+        if let Ok(events) = parse_json(data) {
+            return Ok(events);
+        }
+    }
+    parse_binary(data)
+}
+
+fn invocation_id(events: &[build_event_stream::BuildEvent]) -> anyhow::Result<String> {
+    events
+        .iter()
+        .find_map(|e| match &e.payload {
+            Some(build_event_stream::build_event::Payload::Started(s)) if !s.uuid.is_empty() => {
+                Some(s.uuid.clone())
+            },
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("no BuildStarted event with an invocation id"))
+}
+
+pub(crate) fn to_request(
+    stream_id: &bes::StreamId,
+    sequence_number: i64,
+    event: &build_event_stream::BuildEvent,
+) -> bes::PublishBuildToolEventStreamRequest {
+    bes::PublishBuildToolEventStreamRequest {
+        ordered_build_event: Some(bes::OrderedBuildEvent {
+            stream_id: Some(stream_id.clone()),
+            sequence_number,
+            event: Some(bes::BuildEvent {
+                event_time: None,
+                event: Some(bes::build_event::Event::BazelEvent(
+                    any_proto::google::protobuf::Any {
+                        type_url: "type.googleapis.com/build_event_stream.BuildEvent".to_string(),
+                        value: event.encode_to_vec(),
+                    },
+                )),
+            }),
+        }),
+        ..Default::default()
+    }
+}
+
+/// Feeds the build events of one invocation through a BES session and the
+/// event handlers, exactly as if they had arrived over a gRPC stream.
+pub struct Ingester {
+    session: BESSession,
+    global: Arc<state::Global>,
+    stream_id: bes::StreamId,
+    sequence_number: i64,
+}
+
+impl Ingester {
+    pub fn new(
+        global: Arc<state::Global>,
+        invocation_id: &str,
+        print_message_re: Arc<Mutex<Regex>>,
+    ) -> Self {
+        let handlers = crate::handlers(print_message_re);
+        Self {
+            session: BESSession::new(Arc::new(handlers), global.clone(), None, None),
+            global,
+            stream_id: bes::StreamId {
+                invocation_id: invocation_id.to_string(),
+                ..Default::default()
+            },
+            sequence_number: 0,
+        }
+    }
+
+    pub async fn push(&mut self, event: &build_event_stream::BuildEvent) -> anyhow::Result<()> {
+        self.sequence_number += 1;
+        let req = to_request(&self.stream_id, self.sequence_number, event);
+        self.session
+            .process_message(Some(req))
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow!("event {}: {}", self.sequence_number, e.message()))
+    }
+
+    /// Parses and processes one line of a `--build_event_json_file`. Blank
+    /// lines are ignored.
+    pub async fn push_json(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        if is_blank(line) {
+            return Ok(());
+        }
+        let event = parse_json_event(line)
+            .with_context(|| format!("invalid event {}", self.sequence_number + 1))?;
+        self.push(&event).await
+    }
+
+    /// Stores all events that were pushed so far. Events are otherwise only
+    /// stored in batches.
+    pub async fn finish(&mut self) -> anyhow::Result<()> {
+        self.session
+            .flush()
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow!("failed to store events: {}", e.message()))
+    }
+
+    /// When the pushed events have to be stored if no further events arrive.
+    pub fn flush_deadline(&self) -> Option<tokio::time::Instant> { self.session.flush_deadline() }
+
+    pub fn events(&self) -> i64 { self.sequence_number }
+
+    pub fn is_build_over(&self) -> bool { self.session.is_build_over() }
+
+    /// Marks the invocation as failed, as for a gRPC stream that was dropped
+    /// before the build finished.
+    pub async fn abort(mut self) {
+        if self.sequence_number == 0 || self.is_build_over() {
+            return;
+        }
+        if let Err(e) = self.finish().await {
+            tracing::warn!("{e:#}");
+        }
+        if let Err(e) = crate::unexpected_cleanup_session(
+            self.global.db_manager.clone(),
+            &self.stream_id.invocation_id,
+        )
+        .await
+        {
+            tracing::error!("error closing stream: {e:#?}");
+        }
+    }
+}
+
+/// Imports a recorded build event file by replaying it through the same
+/// session and handlers as a live BES stream. Returns the invocation id.
+pub async fn import_file(state: Arc<state::Global>, path: &str) -> anyhow::Result<String> {
+    proto_registry::init_global_descriptor_pool()?;
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("failed to read {path}"))?;
+    let events = parse(&data).with_context(|| format!("failed to parse {path}"))?;
+    let id = invocation_id(&events)?;
+
+    // Printing events is only useful for live streams.
+    let mut ingester = Ingester::new(state, &id, Arc::new(Mutex::new(Regex::new("")?)));
+    for event in &events {
+        ingester
+            .push(event)
+            .await
+            .with_context(|| format!("failed to import {path}"))?;
+    }
+    ingester
+        .finish()
+        .await
+        .with_context(|| format!("failed to import {path}"))?;
+    if !ingester.is_build_over() {
+        tracing::warn!("{path} ended before the build finished");
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(uuid: &str) -> build_event_stream::BuildEvent {
+        build_event_stream::BuildEvent {
+            id: Some(build_event_stream::BuildEventId {
+                id: Some(build_event_stream::build_event_id::Id::Started(
+                    build_event_stream::build_event_id::BuildStartedId {},
+                )),
+            }),
+            payload: Some(build_event_stream::build_event::Payload::Started(
+                build_event_stream::BuildStarted {
+                    uuid: uuid.to_string(),
+                    command: "test".to_string(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn progress(stdout: &str) -> build_event_stream::BuildEvent {
+        build_event_stream::BuildEvent {
+            id: Some(build_event_stream::BuildEventId {
+                id: Some(build_event_stream::build_event_id::Id::Progress(
+                    build_event_stream::build_event_id::ProgressId { opaque_count: 0 },
+                )),
+            }),
+            payload: Some(build_event_stream::build_event::Payload::Progress(
+                build_event_stream::Progress {
+                    stdout: stdout.to_string(),
+                    stderr: String::new(),
+                },
+            )),
+            last_message: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let events = vec![started("abc"), progress("hello {")];
+
+        let mut binary = vec![];
+        events
+            .iter()
+            .for_each(|e| e.encode_length_delimited(&mut binary).unwrap());
+        assert_eq!(parse(&binary).unwrap(), events);
+
+        let json = events
+            .iter()
+            .map(|e| serde_json::to_string(&e.transcode_to_dynamic()).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(parse(json.as_bytes()).unwrap(), events);
+
+        assert_eq!(invocation_id(&events).unwrap(), "abc");
+        assert!(invocation_id(&events[1..]).is_err());
+    }
+}
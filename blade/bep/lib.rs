@@ -16,20 +16,34 @@ use prometheus_client::{
 };
 use regex::Regex;
 use scopeguard::defer;
-use state::DBManager;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Response, Status, transport::Server};
 use tracing::{Instrument, Level, instrument, span};
 
+mod action;
 mod buildinfo;
+mod buildmetrics;
 mod buildtoollogs;
+mod forward;
+mod import;
+mod lifecycle;
 mod options;
 mod print_event;
 mod progress;
+mod query;
+mod reaper;
+mod recorder;
 mod session;
+mod spool;
 mod target;
 
+pub use forward::Forwarder;
+pub use import::{Ingester, import_file};
+pub use reaper::reap_abandoned;
+pub use recorder::RecordConfig;
+pub use spool::{Spool, SpoolStats};
+
 lazy_static! {
     static ref TOTAL_STREAMS: Counter::<u64> = metrics::register_metric(
         "blade_bep_streams",
@@ -48,19 +62,18 @@ lazy_static! {
     );
 }
 
-enum BuildState {
-    BuildInProgress,
-    BuildFinished,
-}
-
 struct ProccessedEvent {
-    obe: OrderedBuildEvent,
+    // Set once the client closed the stream.
+    end_of_stream: bool,
+    // Sequence numbers of the events that are stored and can be acknowledged,
+    // in order.
+    acks: Vec<i64>,
 }
 
 trait EventHandler {
     fn handle_event(
         &self,
-        db_mgr: &dyn DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()>;
@@ -69,6 +82,9 @@ trait EventHandler {
 pub struct BuildEventService {
     state: Arc<state::Global>,
     handlers: Arc<Vec<Box<dyn EventHandler + Sync + Send>>>,
+    record_config: Option<Arc<RecordConfig>>,
+    spool: Option<Arc<Spool>>,
+    forwarder: Option<Arc<Forwarder>>,
 }
 
 async fn unexpected_cleanup_session(
@@ -102,11 +118,21 @@ impl publish_build_event_server::PublishBuildEvent for BuildEventService {
     #[instrument(skip_all)]
     async fn publish_lifecycle_event(
         &self,
-        _request: tonic::Request<
+        request: tonic::Request<
             build_proto::google::devtools::build::v1::PublishLifecycleEventRequest,
         >,
     ) -> std::result::Result<tonic::Response<empty_proto::google::protobuf::Empty>, tonic::Status>
     {
+        let req = request.into_inner();
+        // Lifecycle events only add build attempt details. Failing them makes
+        // Bazel retry and eventually fail the upload of the build events too.
+        if let Err(e) = db::transaction(self.state.db_manager.clone(), move |db| {
+            lifecycle::apply(db, &req)
+        })
+        .await
+        {
+            tracing::warn!("failed to store lifecycle event: {e:#}");
+        }
         return Ok(Response::new(empty_proto::google::protobuf::Empty {}));
     }
 
@@ -120,6 +146,9 @@ impl publish_build_event_server::PublishBuildEvent for BuildEventService {
         let (tx, rx) = mpsc::channel(128);
         let global = self.state.clone();
         let handlers = self.handlers.clone();
+        let record_config = self.record_config.clone();
+        let spool = self.spool.clone();
+        let mut forward = self.forwarder.as_ref().map(|f| f.stream());
         tokio::spawn(async move {
             TOTAL_STREAMS.inc();
             ACTIVE_STREAMS.inc();
@@ -127,36 +156,56 @@ impl publish_build_event_server::PublishBuildEvent for BuildEventService {
                 ACTIVE_STREAMS.dec();
                 tracing::info!("Stream ended.");
             }
-            let mut session = session::BESSession::new(handlers, global.clone());
+            let mut session = session::BESSession::new(handlers, global.clone(), record_config, spool);
             loop {
-                let Ok(msg) = tokio::time::timeout(global.session_lock_time, in_stream.message()).await else {
-                    tracing::warn!("Timeout waiting for message for {}, skipping.", session.invocation_id());
-                    let _ = tx.send(Err(tonic::Status::deadline_exceeded("failed to wait for timeout"))).await;
-                    return;
-                };
-
-                let msg = match msg {
-                    Ok(msg) => msg,
-                    Err(err) => {
-                        tracing::error!("Error receiving message: {:#?}", err);
-                        return;
+                let flush_deadline = session.flush_deadline();
+                let res = tokio::select! {
+                    // Store received events even if the client goes quiet.
+                    _ = flush_timer(flush_deadline) => session.flush().await.map(|acks| ProccessedEvent { end_of_stream: false, acks }),
+                    msg = tokio::time::timeout(global.session_lock_time, in_stream.message()) => {
+                        let Ok(msg) = msg else {
+                            tracing::warn!("Timeout waiting for message for {}, skipping.", session.invocation_id());
+                            let _ = tx.send(Err(tonic::Status::deadline_exceeded("failed to wait for timeout"))).await;
+                            return;
+                        };
+                        match msg {
+                            Ok(msg) => {
+                                match msg.as_ref() {
+                                    Some(msg) => if let Some(forward) = forward.as_mut() {
+                                        forward.forward(msg);
+                                    },
+                                    // Upstream streams are only closed normally once
+                                    // the client closed its stream, and aborted otherwise.
+                                    None => if let Some(forward) = forward.take() {
+                                        forward.finish();
+                                    },
+                                }
+                                session.process_message(msg).await
+                            },
+                            Err(err) => {
+                                tracing::error!("Error receiving message: {:#?}", err);
+                                return;
+                            }
+                        }
                     }
                 };
 
-                match session.process_message(msg).await {
+                match res {
                     Ok(out) => {
-                        if out.obe.event.is_none() {
+                        for sequence_number in out.acks {
+                            if let Err(e) = tx.send(Ok(PublishBuildToolEventStreamResponse { stream_id: session.stream_id(), sequence_number })).await {
+                                tracing::error!("Error sending response, aborting: {:#?}", e);
+                                TOTAL_STREAMS_ERRORS.get_or_create(&ErrorLabels { code: tonic::Code::Aborted.into() }).inc();
+                                return;
+                            }
+                        }
+                        if out.end_of_stream {
                             if !session.is_build_over() {
                                 tracing::warn!("Received empty event for {}, skipping.", session.invocation_id());
                                 let _ = tx.send(Err(tonic::Status::invalid_argument("empty event"))).await;
                             }
                             return;
                         }
-                        if let Err(e) = tx.send(Ok(PublishBuildToolEventStreamResponse { stream_id: out.obe.stream_id.clone(), sequence_number: out.obe.sequence_number })).await {
-                            tracing::error!("Error sending response, aborting: {:#?}", e);
-                                    TOTAL_STREAMS_ERRORS.get_or_create(&ErrorLabels { code: tonic::Code::Aborted.into() }).inc();
-                            return;
-                        }
                     }
                     Err(err) => {
                         // Tonic gives us this scary message for disconnects. It's really just a disconnect.
@@ -184,29 +233,54 @@ impl publish_build_event_server::PublishBuildEvent for BuildEventService {
     }
 }
 
+/// Fires when the received events have to be stored, see
+/// `BESSession::flush_deadline`.
+pub async fn flush_timer(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn handlers(print_message_re: Arc<Mutex<Regex>>) -> Vec<Box<dyn EventHandler + Sync + Send>> {
+    vec![
+        Box::new(progress::Handler {}),
+        Box::new(target::Handler {}),
+        Box::new(buildinfo::Handler {}),
+        Box::new(buildtoollogs::Handler {}),
+        Box::new(action::Handler {}),
+        Box::new(buildmetrics::Handler {}),
+        Box::new(options::Handler {}),
+        Box::new(print_event::Handler {
+            message_re: print_message_re,
+        }),
+    ]
+}
+
 #[instrument(skip_all)]
 pub async fn run_bes_grpc(
     host: SocketAddr,
     state: Arc<state::Global>,
     print_message_re: Arc<Mutex<Regex>>,
+    record_config: Option<Arc<RecordConfig>>,
+    spool: Option<Arc<Spool>>,
+    forwarder: Option<Arc<Forwarder>>,
 ) -> Result<()> {
     let reflect = tonic_reflection::server::Builder::configure()
         .register_file_descriptor_set(*proto_registry::DESCRIPTORS.clone())
         .build()?;
-    let handlers: Vec<Box<dyn EventHandler + Sync + Send>> = vec![
-        Box::new(progress::Handler {}),
-        Box::new(target::Handler {}),
-        Box::new(buildinfo::Handler {}),
-        Box::new(buildtoollogs::Handler {}),
-        Box::new(options::Handler {}),
-        Box::new(print_event::Handler {
-            message_re: print_message_re,
-        }),
-    ];
+    let handlers = Arc::new(handlers(print_message_re));
     proto_registry::init_global_descriptor_pool()?;
+    let query = query::QueryService::new(state.clone());
+    if let Some(spool) = spool.clone() {
+        tokio::spawn(spool::replay(spool, state.clone(), handlers.clone()));
+    }
     let server = BuildEventService {
         state,
-        handlers: Arc::new(handlers),
+        handlers,
+        record_config,
+        spool,
+        forwarder,
     };
     Server::builder()
         .tcp_keepalive(Some(std::time::Duration::from_secs(20)))
@@ -216,6 +290,9 @@ pub async fn run_bes_grpc(
             publish_build_event_server::PublishBuildEventServer::new(server)
                 .max_decoding_message_size(10 * 1024 * 1024),
         )
+        .add_service(
+            query_proto::blade::query::v1::query_service_server::QueryServiceServer::new(query),
+        )
         .add_service(reflect)
         .serve(host)
         .await
@@ -0,0 +1,242 @@
+use anyhow::anyhow;
+use build_proto::google::devtools::build::v1::*;
+
+fn result_name(status: Option<&BuildStatus>) -> String {
+    status
+        .and_then(|s| build_status::Result::try_from(s.result).ok())
+        .unwrap_or(build_status::Result::UnknownStatus)
+        .as_str_name()
+        .to_string()
+}
+
+fn update_build(
+    db: &mut dyn state::DB,
+    stream_id: &StreamId,
+    project_id: &str,
+    f: impl FnOnce(&mut state::Build),
+) -> anyhow::Result<()> {
+    if stream_id.build_id.is_empty() {
+        return Ok(());
+    }
+    let mut build = db
+        .get_build(&stream_id.build_id)?
+        .unwrap_or_else(|| state::Build {
+            id: stream_id.build_id.clone(),
+            ..Default::default()
+        });
+    if !project_id.is_empty() {
+        build.project_id = project_id.to_string();
+    }
+    f(&mut build);
+    db.upsert_build(&build)
+}
+
+fn attempt_started(
+    db: &mut dyn state::DB,
+    stream_id: &StreamId,
+    attempt_number: i64,
+    time: std::time::SystemTime,
+) -> anyhow::Result<()> {
+    let build_id = Some(stream_id.build_id.clone()).filter(|id| !id.is_empty());
+    if db.get_shallow_invocation(&stream_id.invocation_id).is_ok() {
+        return db.update_shallow_invocation(
+            &stream_id.invocation_id,
+            Box::new(move |i: &mut state::InvocationResults| {
+                i.build_id = build_id;
+                i.attempt_number = Some(attempt_number);
+                Ok(())
+            }),
+        );
+    }
+    // Bazel announces the attempt before it opens the event stream, which then
+    // continues with this invocation.
+    db.upsert_shallow_invocation(&state::InvocationResults {
+        id: stream_id.invocation_id.clone(),
+        status: state::Status::InProgress,
+        start: time,
+        build_id,
+        attempt_number: Some(attempt_number),
+        ..Default::default()
+    })
+}
+
+fn attempt_finished(
+    db: &mut dyn state::DB,
+    stream_id: &StreamId,
+    status: Option<&BuildStatus>,
+    time: std::time::SystemTime,
+) -> anyhow::Result<()> {
+    if db.get_shallow_invocation(&stream_id.invocation_id).is_err() {
+        return Ok(());
+    }
+    let success = status.is_some_and(|s| s.result == build_status::Result::CommandSucceeded as i32);
+    db.update_shallow_invocation(
+        &stream_id.invocation_id,
+        Box::new(move |i: &mut state::InvocationResults| {
+            // The tool event stream may have ended without its last message.
+            if matches!(i.status, state::Status::InProgress | state::Status::Unknown) {
+                i.status = if success {
+                    state::Status::Success
+                } else {
+                    state::Status::Fail
+                };
+            }
+            i.end.get_or_insert(time);
+            Ok(())
+        }),
+    )
+}
+
+/// Stores what a BES lifecycle event says about a build and its invocation
+/// attempts.
+pub(crate) fn apply(
+    db: &mut dyn state::DB,
+    req: &PublishLifecycleEventRequest,
+) -> anyhow::Result<()> {
+    let obe = req
+        .build_event
+        .as_ref()
+        .ok_or_else(|| anyhow!("missing build event"))?;
+    let stream_id = obe
+        .stream_id
+        .as_ref()
+        .ok_or_else(|| anyhow!("missing stream id"))?;
+    let time = obe
+        .event
+        .as_ref()
+        .and_then(|e| e.event_time.as_ref())
+        .and_then(|t| prototime::timestamp::from_proto(t).ok())
+        .unwrap_or_else(std::time::SystemTime::now);
+    match obe.event.as_ref().and_then(|e| e.event.as_ref()) {
+        Some(build_event::Event::BuildEnqueued(_)) => {
+            update_build(db, stream_id, &req.project_id, |b| b.enqueued = Some(time))
+        },
+        Some(build_event::Event::BuildFinished(f)) => {
+            update_build(db, stream_id, &req.project_id, |b| {
+                b.finished = Some(time);
+                b.result = Some(result_name(f.status.as_ref()));
+            })
+        },
+        Some(build_event::Event::InvocationAttemptStarted(s))
+            if !stream_id.invocation_id.is_empty() =>
+        {
+            update_build(db, stream_id, &req.project_id, |_| {})?;
+            attempt_started(db, stream_id, s.attempt_number, time)
+        },
+        Some(build_event::Event::InvocationAttemptFinished(f))
+            if !stream_id.invocation_id.is_empty() =>
+        {
+            attempt_finished(db, stream_id, f.invocation_status.as_ref(), time)
+        },
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        invocation_id: &str,
+        event: build_event::Event,
+        secs: u64,
+    ) -> PublishLifecycleEventRequest {
+        let t = std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+        PublishLifecycleEventRequest {
+            project_id: "project".to_string(),
+            build_event: Some(OrderedBuildEvent {
+                stream_id: Some(StreamId {
+                    build_id: "build".to_string(),
+                    invocation_id: invocation_id.to_string(),
+                    ..Default::default()
+                }),
+                event: Some(BuildEvent {
+                    event_time: prototime::timestamp::to_proto(&t).ok(),
+                    event: Some(event),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn status(result: build_status::Result) -> Option<BuildStatus> {
+        Some(BuildStatus {
+            result: result as i32,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let tmp = tempdir::TempDir::new("test_lifecycle").unwrap();
+        let mut db = testonly::sqlite(&tmp).get().unwrap();
+
+        let started = |n| {
+            build_event::Event::InvocationAttemptStarted(build_event::InvocationAttemptStarted {
+                attempt_number: n,
+                ..Default::default()
+            })
+        };
+        let finished = |r| {
+            build_event::Event::InvocationAttemptFinished(build_event::InvocationAttemptFinished {
+                invocation_status: status(r),
+                ..Default::default()
+            })
+        };
+        for req in [
+            request(
+                "",
+                build_event::Event::BuildEnqueued(build_event::BuildEnqueued::default()),
+                10,
+            ),
+            request("first", started(1), 20),
+            request("first", finished(build_status::Result::SystemError), 30),
+            request("second", started(2), 40),
+            request(
+                "second",
+                finished(build_status::Result::CommandSucceeded),
+                50,
+            ),
+            request(
+                "",
+                build_event::Event::BuildFinished(build_event::BuildFinished {
+                    status: status(build_status::Result::CommandSucceeded),
+                    ..Default::default()
+                }),
+                60,
+            ),
+        ] {
+            apply(db.as_mut(), &req).unwrap();
+        }
+
+        let at = |secs| Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        assert_eq!(
+            db.get_build("build").unwrap(),
+            Some(state::Build {
+                id: "build".to_string(),
+                project_id: "project".to_string(),
+                enqueued: at(10),
+                finished: at(60),
+                result: Some("COMMAND_SUCCEEDED".to_string()),
+            })
+        );
+        let attempts = db.get_build_invocations("build").unwrap();
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|i| (
+                    i.id.as_str(),
+                    i.attempt_number,
+                    i.status,
+                    Some(i.start),
+                    i.end
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("first", Some(1), state::Status::Fail, at(20), at(30)),
+                ("second", Some(2), state::Status::Success, at(40), at(50)),
+            ]
+        );
+    }
+}
@@ -1,19 +1,17 @@
 use anyhow::Context;
 use build_event_stream_proto::build_event_stream;
-use state::DBManager;
 
 pub(crate) struct Handler {}
 
 impl crate::EventHandler for Handler {
     fn handle_event(
         &self,
-        db_mgr: &dyn DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
         match &event.payload {
             Some(build_event_stream::build_event::Payload::UnstructuredCommandLine(opts)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let o = state::BuildOptions {
                     unstructured: opts.args.clone(),
                     ..Default::default()
@@ -22,7 +20,6 @@ impl crate::EventHandler for Handler {
                     .context("failed to insert unstructured command line")?;
             },
             Some(build_event_stream::build_event::Payload::OptionsParsed(opts)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let o = state::BuildOptions {
                     startup: opts.startup_options.clone(),
                     explicit_startup: opts.explicit_startup_options.clone(),
@@ -32,9 +29,19 @@ impl crate::EventHandler for Handler {
                 };
                 db.insert_options(invocation_id, &o)
                     .context("failed to insert parsed options")?;
+                if !opts.tool_tag.is_empty() {
+                    let tool_tag = opts.tool_tag.clone();
+                    db.update_shallow_invocation(
+                        invocation_id,
+                        Box::new(move |i: &mut state::InvocationResults| {
+                            i.tool_tag = Some(tool_tag);
+                            Ok(())
+                        }),
+                    )
+                    .context("failed to update tool tag")?;
+                }
             },
             Some(build_event_stream::build_event::Payload::BuildMetadata(md)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let o = state::BuildOptions {
                     build_metadata: md.metadata.clone(),
                     ..Default::default()
@@ -3,7 +3,6 @@ use std::sync::{Arc, Mutex};
 use build_event_stream_proto::build_event_stream;
 use prost_reflect::ReflectMessage;
 use regex::Regex;
-use state::DBManager;
 pub(crate) struct Handler {
     pub message_re: Arc<Mutex<Regex>>,
 }
@@ -11,7 +10,7 @@ pub(crate) struct Handler {
 impl crate::EventHandler for Handler {
     fn handle_event(
         &self,
-        _db_mgr: &dyn DBManager,
+        _db: &mut dyn state::DB,
         _invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
@@ -1,6 +1,5 @@
 use anyhow::Context;
 use build_event_stream_proto::build_event_stream;
-use state::DBManager;
 
 const DELETE_LINE_SEQ: &str = "\x1b[1A\x1b[K";
 
@@ -54,7 +53,7 @@ fn cleanup(stdout: &str, stderr: &str) -> (u32, Vec<String>) {
 impl crate::EventHandler for Handler {
     fn handle_event(
         &self,
-        db_mgr: &dyn DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
@@ -63,7 +62,6 @@ impl crate::EventHandler for Handler {
             if p.stderr.is_empty() && p.stdout.is_empty() {
                 return Ok(());
             }
-            let mut db = db_mgr.get().context("failed to get db handle")?;
             let progress = cleanup(&p.stdout, &p.stderr);
             if progress.0 > 0 {
                 // Try our best. If it doesn't work out, just log and continue.
@@ -0,0 +1,573 @@
+use std::sync::Arc;
+
+use query_proto::blade::query::v1 as pb;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::instrument;
+
+// How often StreamInvocationUpdates checks the database for changes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+const DEFAULT_LIST_LIMIT: usize = 50;
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+const DEFAULT_HISTORY_DAYS: u32 = 30;
+const MAX_RESULTS: usize = 1000;
+
+pub struct QueryService {
+    state: Arc<state::Global>,
+}
+
+impl QueryService {
+    pub fn new(state: Arc<state::Global>) -> Self { Self { state } }
+}
+
+fn status(s: state::Status) -> i32 {
+    match s {
+        state::Status::Unknown => pb::Status::Unknown,
+        state::Status::InProgress => pb::Status::InProgress,
+        state::Status::Success => pb::Status::Success,
+        state::Status::Fail => pb::Status::Fail,
+        state::Status::Skip => pb::Status::Skip,
+        state::Status::Incomplete => pb::Status::Incomplete,
+        state::Status::Interrupted => pb::Status::Interrupted,
+    }
+    .into()
+}
+
+fn from_status(s: i32) -> state::Status {
+    match pb::Status::try_from(s).unwrap_or(pb::Status::Unknown) {
+        pb::Status::Unknown => state::Status::Unknown,
+        pb::Status::InProgress => state::Status::InProgress,
+        pb::Status::Success => state::Status::Success,
+        pb::Status::Fail => state::Status::Fail,
+        pb::Status::Skip => state::Status::Skip,
+        pb::Status::Incomplete => state::Status::Incomplete,
+        pb::Status::Interrupted => state::Status::Interrupted,
+    }
+}
+
+fn timestamp(t: &std::time::SystemTime) -> Option<timestamp_proto::google::protobuf::Timestamp> {
+    prototime::timestamp::to_proto(t).ok()
+}
+
+fn from_timestamp(
+    t: &timestamp_proto::google::protobuf::Timestamp,
+) -> Result<std::time::SystemTime, Status> {
+    prototime::timestamp::from_proto(t)
+        .map_err(|e| Status::invalid_argument(format!("invalid timestamp: {e:#}")))
+}
+
+fn detailed_status(s: Option<state::TestStatus>) -> String {
+    s.map(|s| s.to_string()).unwrap_or_default()
+}
+
+fn test(t: &state::Test) -> pb::Test {
+    pb::Test {
+        name: t.name.clone(),
+        status: status(t.status),
+        detailed_status: detailed_status(t.detailed_status),
+        duration: Some(prototime::duration::to_proto(&t.duration)),
+        end: timestamp(&t.end),
+        num_runs: t.num_runs as i64,
+        runs: t
+            .runs
+            .iter()
+            .map(|r| {
+                let mut artifacts = r
+                    .files
+                    .iter()
+                    .map(|(name, a)| pb::Artifact {
+                        name: name.clone(),
+                        uri: a.uri.clone(),
+                        size: a.size as i64,
+                    })
+                    .collect::<Vec<_>>();
+                artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+                pb::TestRun {
+                    run: r.run,
+                    shard: r.shard,
+                    attempt: r.attempt,
+                    status: status(r.status),
+                    detailed_status: detailed_status(r.detailed_status),
+                    details: r.details.clone(),
+                    duration: Some(prototime::duration::to_proto(&r.duration)),
+                    artifacts,
+                }
+            })
+            .collect(),
+    }
+}
+
+fn invocation(i: &state::InvocationResults) -> pb::Invocation {
+    let mut targets = i
+        .targets
+        .values()
+        .map(|t| pb::Target {
+            name: t.name.clone(),
+            status: status(t.status),
+            kind: t.kind.clone(),
+            start: timestamp(&t.start),
+            end: t.end.as_ref().and_then(timestamp),
+            failure: t.failure.as_ref().map(|f| pb::TargetFailure {
+                message: f.message.clone(),
+                category: f.category.clone(),
+                root_causes: f.root_causes.clone(),
+            }),
+        })
+        .collect::<Vec<_>>();
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut tests = i.tests.values().map(test).collect::<Vec<_>>();
+    tests.sort_by(|a, b| a.name.cmp(&b.name));
+    pb::Invocation {
+        id: i.id.clone(),
+        status: status(i.status),
+        command: i.command.clone(),
+        pattern: i.pattern.clone(),
+        start: timestamp(&i.start),
+        end: i.end.as_ref().and_then(timestamp),
+        last_heartbeat: i.last_heartbeat.as_ref().and_then(timestamp),
+        is_live: i.is_live(),
+        profile_uri: i.profile_uri.clone().unwrap_or_default(),
+        targets,
+        tests,
+        project_id: i.project_id.clone().unwrap_or_default(),
+        notification_keywords: i.notification_keywords.clone(),
+        tool_tag: i.tool_tag.clone().unwrap_or_default(),
+        exit_code: i.exit_code.as_ref().map(|e| pb::ExitCode {
+            name: e.name.clone(),
+            code: e.code,
+        }),
+        failure_detail: i.failure_detail.as_ref().map(|f| pb::FailureDetail {
+            message: f.message.clone(),
+            category: f.category.clone(),
+            code: f.code.clone(),
+        }),
+        build_tool_version: i.build_tool_version.clone().unwrap_or_default(),
+        workspace_directory: i.workspace_directory.clone().unwrap_or_default(),
+        working_directory: i.working_directory.clone().unwrap_or_default(),
+        server_pid: i.server_pid.unwrap_or_default(),
+        options_description: i.options_description.clone().unwrap_or_default(),
+        build_user: i.build_user.clone().unwrap_or_default(),
+        build_host: i.build_host.clone().unwrap_or_default(),
+    }
+}
+
+fn filter(f: pb::TestFilter) -> Result<state::TestFilter, Status> {
+    use pb::test_filter::{Filter, Op};
+
+    let op = match Op::try_from(f.op).unwrap_or(Op::Equals) {
+        Op::Equals => state::TestFilterOp::Equals,
+        Op::Contains => state::TestFilterOp::Contains,
+        Op::GreaterThan => state::TestFilterOp::GreaterThan,
+        Op::LessThan => state::TestFilterOp::LessThan,
+    };
+    let filter = match f
+        .filter
+        .ok_or_else(|| Status::invalid_argument("empty test filter"))?
+    {
+        Filter::Start(t) => state::TestFilterItem::Start(from_timestamp(&t)?),
+        Filter::Duration(d) => state::TestFilterItem::Duration(
+            prototime::duration::from_proto(&d)
+                .map_err(|e| Status::invalid_argument(format!("invalid duration: {e:#}")))?,
+        ),
+        Filter::Metadata(kv) => state::TestFilterItem::Metadata {
+            key: kv.key,
+            value: kv.value,
+        },
+        Filter::BazelFlag(kv) => state::TestFilterItem::BazelFlags {
+            flag: kv.key,
+            value: kv.value,
+        },
+        Filter::Status(s) => state::TestFilterItem::Status(from_status(s)),
+        Filter::LogOutput(s) => state::TestFilterItem::LogOutput(s),
+        Filter::DateRange(r) => {
+            let (Some(from), Some(to)) = (r.from, r.to) else {
+                return Err(Status::invalid_argument("date range needs from and to"));
+            };
+            state::TestFilterItem::DateRange {
+                from: from_timestamp(&from)?,
+                to: from_timestamp(&to)?,
+            }
+        },
+        Filter::ProjectId(p) => state::TestFilterItem::ProjectId(p),
+        Filter::NotificationKeyword(k) => state::TestFilterItem::NotificationKeyword(k),
+        Filter::ToolTag(t) => state::TestFilterItem::ToolTag(t),
+    };
+    Ok(state::TestFilter {
+        op,
+        invert: f.invert,
+        filter,
+    })
+}
+
+// Only a missing row is NOT_FOUND, a database outage must not look like a
+// deleted invocation.
+fn db_status(e: anyhow::Error) -> Status {
+    if db::is_not_found(&e) {
+        return Status::not_found(format!("{e:#}"));
+    }
+    Status::internal(format!("{e:#}"))
+}
+
+/// Turns the lines of successive `DB::get_output_since` calls into the
+/// output deltas of `InvocationUpdate`s.
+#[derive(Default)]
+struct OutputCursor {
+    // Id of the last line sent.
+    last_id: i64,
+    // Number of lines sent that were not deleted since.
+    lines: usize,
+}
+
+impl OutputCursor {
+    /// Returns how many lines the client has to delete from the end of its
+    /// output and the text to append after that.
+    fn advance(&mut self, since: state::OutputSince) -> (usize, String) {
+        let deleted = since
+            .kept
+            .map(|kept| self.lines.saturating_sub(kept))
+            .unwrap_or_default();
+        self.lines -= deleted;
+        let mut output = String::new();
+        for (id, line) in since.lines {
+            if self.lines > 0 {
+                output.push('\n');
+            }
+            output.push_str(&line);
+            self.lines += 1;
+            self.last_id = id;
+        }
+        (deleted, output)
+    }
+}
+
+fn limit(l: i64, default: usize) -> usize {
+    if l <= 0 {
+        return default;
+    }
+    (l as usize).min(MAX_RESULTS)
+}
+
+#[tonic::async_trait]
+impl pb::query_service_server::QueryService for QueryService {
+    type StreamInvocationUpdatesStream = ReceiverStream<Result<pb::InvocationUpdate, Status>>;
+
+    #[instrument(skip_all)]
+    async fn get_invocation(
+        &self,
+        request: Request<pb::GetInvocationRequest>,
+    ) -> Result<Response<pb::Invocation>, Status> {
+        let id = request.into_inner().invocation_id;
+        let inv = db::run_group(self.state.db_manager.clone(), move |db| {
+            db.get_invocation(&id)
+        })
+        .await
+        .map_err(db_status)?;
+        Ok(Response::new(invocation(&inv)))
+    }
+
+    #[instrument(skip_all)]
+    async fn list_invocations(
+        &self,
+        request: Request<pb::ListInvocationsRequest>,
+    ) -> Result<Response<pb::ListInvocationsResponse>, Status> {
+        let req = request.into_inner();
+        let mut metadata = req.metadata.into_iter().collect::<Vec<_>>();
+        metadata.sort();
+        let mut workspace_status = req.workspace_status.into_iter().collect::<Vec<_>>();
+        workspace_status.sort();
+        let query = state::InvocationQuery {
+            filter: state::InvocationFilter {
+                status: req.status.map(from_status),
+                command: Some(req.command).filter(|c| !c.is_empty()),
+                pattern: Some(req.pattern).filter(|p| !p.is_empty()),
+                start_from: req.start_from.as_ref().map(from_timestamp).transpose()?,
+                start_to: req.start_to.as_ref().map(from_timestamp).transpose()?,
+                metadata,
+                project_id: Some(req.project_id).filter(|p| !p.is_empty()),
+                keyword: Some(req.keyword).filter(|k| !k.is_empty()),
+                tool_tag: Some(req.tool_tag).filter(|t| !t.is_empty()),
+                exit_code: Some(req.exit_code).filter(|e| !e.is_empty()),
+                failure_category: Some(req.failure_category).filter(|c| !c.is_empty()),
+                build_tool_version: Some(req.build_tool_version).filter(|v| !v.is_empty()),
+                workspace_status,
+            },
+            sort_by: state::InvocationSortField::Start,
+            ascending: req.ascending,
+            offset: req.offset.max(0) as usize,
+            limit: limit(req.limit, DEFAULT_LIST_LIMIT),
+        };
+        let page = db::run(self.state.db_manager.clone(), move |db| {
+            db.list_invocations(&query)
+        })
+        .await
+        .map_err(|e| Status::internal(format!("{e:#}")))?;
+        Ok(Response::new(pb::ListInvocationsResponse {
+            invocations: page.invocations.iter().map(invocation).collect(),
+            total: page.total as i64,
+        }))
+    }
+
+    #[instrument(skip_all)]
+    async fn get_test_history(
+        &self,
+        request: Request<pb::GetTestHistoryRequest>,
+    ) -> Result<Response<pb::TestHistory>, Status> {
+        let req = request.into_inner();
+        let filters = req
+            .filters
+            .into_iter()
+            .map(filter)
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_results = limit(req.limit, DEFAULT_HISTORY_LIMIT);
+        let days = match req.days {
+            d if d > 0 => d as u32,
+            _ => DEFAULT_HISTORY_DAYS,
+        };
+        let name = req.test_name;
+        let history = db::run(self.state.db_manager.clone(), move |db| {
+            db.get_test_history(&name, &filters, max_results, Some(days))
+        })
+        .await
+        .map_err(|e| Status::internal(format!("{e:#}")))?;
+        Ok(Response::new(pb::TestHistory {
+            test_name: history.name,
+            history: history
+                .history
+                .iter()
+                .map(|p| pb::TestHistoryPoint {
+                    invocation_id: p.invocation_id.clone(),
+                    start: timestamp(&p.start),
+                    test: Some(test(&p.test)),
+                })
+                .collect(),
+            total_found: history.total_found as i64,
+            was_truncated: history.was_truncated,
+        }))
+    }
+
+    #[instrument(skip_all)]
+    async fn stream_invocation_updates(
+        &self,
+        request: Request<pb::StreamInvocationUpdatesRequest>,
+    ) -> Result<Response<Self::StreamInvocationUpdatesStream>, Status> {
+        let id = request.into_inner().invocation_id;
+        let db_mgr = self.state.db_manager.clone();
+        // Fail fast on unknown invocations instead of streaming errors.
+        let inv_id = id.clone();
+        db::run(db_mgr.clone(), move |db| db.get_shallow_invocation(&inv_id))
+            .await
+            .map_err(db_status)?;
+
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut last: Option<pb::Invocation> = None;
+            let mut cursor = OutputCursor::default();
+            loop {
+                let inv_id = id.clone();
+                let after_id = cursor.last_id;
+                let res = db::run_group(db_mgr.clone(), move |db| {
+                    let inv = db.get_shallow_invocation(&inv_id)?;
+                    let output = db.get_output_since(&inv_id, after_id)?;
+                    Ok((inv, output))
+                })
+                .await;
+                let (inv, output) = match res {
+                    Ok(res) => res,
+                    Err(e) => {
+                        let _ = tx.send(Err(db_status(e))).await;
+                        return;
+                    },
+                };
+                let done = !matches!(
+                    inv.status,
+                    state::Status::InProgress | state::Status::Unknown
+                );
+                let current = invocation(&inv);
+                let (deleted_lines, output) = cursor.advance(output);
+                if last.as_ref() != Some(&current) || !output.is_empty() || deleted_lines > 0 {
+                    let update = pb::InvocationUpdate {
+                        invocation: Some(current.clone()),
+                        output,
+                        deleted_lines: deleted_lines as i64,
+                    };
+                    if tx.send(Ok(update)).await.is_err() {
+                        // Client went away.
+                        return;
+                    }
+                    last = Some(current);
+                }
+                if done {
+                    return;
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pb::query_service_server::QueryService as _;
+    use testonly::{Unavailable, global, sqlite};
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    fn service(db_manager: Arc<dyn state::DBManager>) -> QueryService {
+        QueryService::new(Arc::new(global(db_manager)))
+    }
+
+    fn since(kept: Option<usize>, lines: &[(i64, &str)]) -> state::OutputSince {
+        state::OutputSince {
+            kept,
+            lines: lines.iter().map(|(id, l)| (*id, l.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_output_cursor() {
+        let mut cursor = OutputCursor::default();
+        assert_eq!(
+            cursor.advance(since(None, &[(1, "a"), (2, "b")])),
+            (0, "a\nb".to_string())
+        );
+        assert_eq!(cursor.advance(since(None, &[])), (0, String::new()));
+        assert_eq!(
+            cursor.advance(since(None, &[(3, "c")])),
+            (0, "\nc".to_string())
+        );
+        // "c" was redrawn as "d".
+        assert_eq!(
+            cursor.advance(since(Some(2), &[(4, "d")])),
+            (1, "\nd".to_string())
+        );
+        assert_eq!(cursor.last_id, 4);
+        // Everything was deleted.
+        assert_eq!(
+            cursor.advance(since(Some(0), &[(5, "e")])),
+            (3, "e".to_string())
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let f = filter(pb::TestFilter {
+            op: pb::test_filter::Op::Contains.into(),
+            invert: true,
+            filter: Some(pb::test_filter::Filter::LogOutput("panic".to_string())),
+        })
+        .unwrap();
+        assert_eq!(f.op, state::TestFilterOp::Contains);
+        assert!(f.invert);
+        assert_eq!(
+            f.filter,
+            state::TestFilterItem::LogOutput("panic".to_string())
+        );
+
+        let err = filter(pb::TestFilter::default()).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        assert_eq!(limit(0, 50), 50);
+        assert_eq!(limit(-1, 50), 50);
+        assert_eq!(limit(10, 50), 10);
+        assert_eq!(limit(1_000_000, 50), MAX_RESULTS);
+    }
+
+    #[tokio::test]
+    async fn test_get_invocation() {
+        let tmp = tempdir::TempDir::new("test_get_invocation").unwrap();
+        let svc = service(sqlite(&tmp));
+        db::run(svc.state.db_manager.clone(), |db| {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: "inv".to_string(),
+                command: "build".to_string(),
+                status: state::Status::Fail,
+                ..Default::default()
+            })
+        })
+        .await
+        .unwrap();
+
+        let get = |id: &str| {
+            svc.get_invocation(Request::new(pb::GetInvocationRequest {
+                invocation_id: id.to_string(),
+            }))
+        };
+        let inv = get("inv").await.unwrap().into_inner();
+        assert_eq!(inv.command, "build");
+        assert_eq!(inv.status, i32::from(pb::Status::Fail));
+        assert_eq!(
+            get("missing").await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+
+        let err = service(Arc::new(Unavailable))
+            .get_invocation(Request::new(pb::GetInvocationRequest {
+                invocation_id: "inv".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_stream_invocation_updates() {
+        let tmp = tempdir::TempDir::new("test_stream_invocation_updates").unwrap();
+        let svc = service(sqlite(&tmp));
+        let db_mgr = svc.state.db_manager.clone();
+        db::run(db_mgr.clone(), |db| {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: "inv".to_string(),
+                status: state::Status::InProgress,
+                ..Default::default()
+            })?;
+            db.insert_output_lines("inv", vec!["a".to_string(), "b".to_string()])
+        })
+        .await
+        .unwrap();
+
+        let mut stream = svc
+            .stream_invocation_updates(Request::new(pb::StreamInvocationUpdatesRequest {
+                invocation_id: "inv".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let update = stream.next().await.unwrap().unwrap();
+        assert_eq!(update.output, "a\nb");
+        assert_eq!(update.deleted_lines, 0);
+
+        db::run(db_mgr, |db| {
+            db.delete_last_output_lines("inv", 1)?;
+            db.insert_output_lines("inv", vec!["c".to_string()])?;
+            db.update_shallow_invocation(
+                "inv",
+                Box::new(|i: &mut state::InvocationResults| {
+                    i.status = state::Status::Success;
+                    Ok(())
+                }),
+            )
+        })
+        .await
+        .unwrap();
+        let update = stream.next().await.unwrap().unwrap();
+        assert_eq!(update.output, "\nc");
+        assert_eq!(update.deleted_lines, 1);
+        assert_eq!(
+            update.invocation.unwrap().status,
+            i32::from(pb::Status::Success)
+        );
+        // The stream ends with the invocation.
+        assert!(stream.next().await.is_none());
+
+        let err = svc
+            .stream_invocation_updates(Request::new(pb::StreamInvocationUpdatesRequest {
+                invocation_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{sync::Arc, time::SystemTime};
+
+use lazy_static::lazy_static;
+use prometheus_client::metrics::counter::Counter;
+
+lazy_static! {
+    static ref REAPED_INVOCATIONS: Counter::<u64> = metrics::register_metric(
+        "blade_bep_reaped_invocations",
+        "Total number of in progress invocations marked as interrupted",
+        Counter::default()
+    );
+}
+
+// Marks the invocation as interrupted unless a new heartbeat arrived in the
+// meantime. Returns the time of the last heartbeat if it was.
+fn interrupt(
+    db: &mut dyn state::DB,
+    invocation_id: &str,
+    heartbeat_before: SystemTime,
+) -> anyhow::Result<Option<SystemTime>> {
+    let inv = db.get_shallow_invocation(invocation_id)?;
+    let last_seen = inv.last_heartbeat.unwrap_or(inv.start);
+    if inv.status != state::Status::InProgress || inv.end.is_some() || last_seen >= heartbeat_before
+    {
+        return Ok(None);
+    }
+    db.update_shallow_invocation(
+        invocation_id,
+        Box::new(move |i: &mut state::InvocationResults| {
+            i.status = state::Status::Interrupted;
+            i.end = Some(last_seen);
+            Ok(())
+        }),
+    )?;
+    db.mark_incomplete(invocation_id, last_seen)?;
+    Ok(Some(last_seen))
+}
+
+/// Marks in progress invocations without a heartbeat for `threshold`, and
+/// their unfinished targets and tests, as interrupted. Returns how many
+/// invocations were marked.
+pub async fn reap_abandoned(
+    global: Arc<state::Global>,
+    threshold: std::time::Duration,
+) -> anyhow::Result<usize> {
+    let Some(heartbeat_before) = SystemTime::now().checked_sub(threshold) else {
+        return Ok(0);
+    };
+    let abandoned = db::run(global.db_manager.clone(), move |db| {
+        db.get_abandoned_invocations(&heartbeat_before)
+    })
+    .await?;
+    let mut reaped = 0;
+    for inv in abandoned {
+        let id = inv.id.clone();
+        let res = db::transaction(global.db_manager.clone(), move |db| {
+            interrupt(db, &id, heartbeat_before)
+        })
+        .await;
+        match res {
+            Ok(Some(last_seen)) => {
+                let idle = SystemTime::now()
+                    .duration_since(last_seen)
+                    .unwrap_or_default();
+                tracing::info!(
+                    "Marked invocation {} as interrupted, no heartbeat for {}s",
+                    inv.id,
+                    idle.as_secs()
+                );
+                REAPED_INVOCATIONS.inc();
+                reaped += 1;
+            },
+            Ok(None) => {},
+            Err(e) => tracing::warn!("failed to mark invocation {} as interrupted: {e:#}", inv.id),
+        }
+    }
+    Ok(reaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reap_abandoned() {
+        let tmp = tempdir::TempDir::new("test_reap_abandoned").unwrap();
+        let global = Arc::new(testonly::global(testonly::sqlite(&tmp)));
+        let now = SystemTime::now();
+        let old = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        db::run(global.db_manager.clone(), move |db| {
+            for (id, status, heartbeat) in [
+                ("abandoned", state::Status::InProgress, Some(old)),
+                ("never_started", state::Status::InProgress, None),
+                ("live", state::Status::InProgress, Some(now)),
+                ("finished", state::Status::Success, Some(old)),
+            ] {
+                db.upsert_shallow_invocation(&state::InvocationResults {
+                    id: id.to_string(),
+                    status,
+                    start: old,
+                    last_heartbeat: heartbeat,
+                    ..Default::default()
+                })?;
+                db.upsert_target(
+                    id,
+                    &state::Target {
+                        name: "//:target".to_string(),
+                        status: state::Status::InProgress,
+                        kind: "rule".to_string(),
+                        start: old,
+                        end: None,
+                        failure: None,
+                    },
+                )?;
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let reaped = reap_abandoned(global.clone(), Duration::from_secs(600))
+            .await
+            .unwrap();
+        assert_eq!(reaped, 2);
+        assert_eq!(
+            reap_abandoned(global.clone(), Duration::from_secs(600))
+                .await
+                .unwrap(),
+            0
+        );
+
+        let get =
+            |id: &'static str| db::run(global.db_manager.clone(), move |db| db.get_invocation(id));
+        for id in ["abandoned", "never_started"] {
+            let inv = get(id).await.unwrap();
+            assert_eq!(inv.status, state::Status::Interrupted);
+            assert_eq!(inv.end, Some(old));
+            assert_eq!(inv.targets["//:target"].status, state::Status::Incomplete);
+        }
+        let inv = get("live").await.unwrap();
+        assert_eq!(inv.status, state::Status::InProgress);
+        assert_eq!(inv.targets["//:target"].status, state::Status::InProgress);
+        assert_eq!(
+            get("finished").await.unwrap().status,
+            state::Status::Success
+        );
+    }
+}
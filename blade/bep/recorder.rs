@@ -0,0 +1,289 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use build_event_stream_proto::*;
+use build_proto::google::devtools::build::v1::*;
+use prost::Message;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+// Events held back while waiting for the build metadata of an invocation.
+// Bazel sends it early, so this only guards against builds without metadata.
+const MAX_PENDING_EVENTS: usize = 10_000;
+
+/// Selects the BES streams to record and where to write them.
+#[derive(Debug, Clone, Default)]
+pub struct RecordConfig {
+    pub dir: PathBuf,
+    pub invocation_ids: HashSet<String>,
+    // Build metadata that must all match for an invocation to be recorded.
+    pub metadata: Vec<(String, String)>,
+}
+
+impl RecordConfig {
+    pub fn path(&self, invocation_id: &str) -> PathBuf {
+        self.dir.join(format!("{invocation_id}.bes"))
+    }
+
+    fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        !self.metadata.is_empty()
+            && self
+                .metadata
+                .iter()
+                .all(|(k, v)| metadata.get(k).is_some_and(|m| m == v))
+    }
+}
+
+enum Mode {
+    Pending(Vec<OrderedBuildEvent>),
+    // Events are written by a separate task, so that the stream never waits
+    // for the disk.
+    Recording(mpsc::UnboundedSender<OrderedBuildEvent>),
+    Skip,
+}
+
+/// Writes the raw `OrderedBuildEvent`s of a single BES stream to a length
+/// delimited file that `streamstress --replay` can play back.
+pub(crate) struct Recorder {
+    config: Arc<RecordConfig>,
+    invocation_id: String,
+    mode: Mode,
+}
+
+impl Recorder {
+    pub fn new(config: Arc<RecordConfig>, invocation_id: &str) -> Self {
+        let mut r = Self {
+            config,
+            invocation_id: invocation_id.to_string(),
+            mode: Mode::Pending(vec![]),
+        };
+        // A retried stream is recorded if the original one was, even if it
+        // doesn't resend the build metadata.
+        if r.config.invocation_ids.contains(invocation_id) || r.config.path(invocation_id).exists()
+        {
+            r.start(vec![]);
+        } else if r.config.metadata.is_empty() {
+            r.mode = Mode::Skip;
+        }
+        r
+    }
+
+    fn start(&mut self, pending: Vec<OrderedBuildEvent>) {
+        // The id comes from the client and ends up in a file name.
+        if !self
+            .invocation_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            tracing::warn!(
+                "not recording invalid invocation id {:?}",
+                self.invocation_id
+            );
+            self.mode = Mode::Skip;
+            return;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_recording(
+            self.config.clone(),
+            self.invocation_id.clone(),
+            rx,
+        ));
+        self.mode = Mode::Recording(tx);
+        pending.into_iter().for_each(|obe| self.write(obe));
+    }
+
+    fn write(&mut self, obe: OrderedBuildEvent) {
+        let Mode::Recording(tx) = &self.mode else {
+            return;
+        };
+        // Fails once the writer gave up.
+        if tx.send(obe).is_err() {
+            self.mode = Mode::Skip;
+        }
+    }
+
+    pub fn record(&mut self, obe: &OrderedBuildEvent) {
+        let mut obe = obe.clone();
+        // Replays use the event times for pacing.
+        if let Some(event) = obe.event.as_mut()
+            && event.event_time.is_none()
+        {
+            event.event_time = prototime::timestamp::to_proto(&std::time::SystemTime::now()).ok();
+        }
+        match &mut self.mode {
+            Mode::Skip => {},
+            Mode::Recording(_) => self.write(obe),
+            Mode::Pending(pending) => {
+                let metadata = build_metadata(&obe);
+                pending.push(obe);
+                if metadata.is_some_and(|m| self.config.matches(&m)) {
+                    let pending = std::mem::take(pending);
+                    self.start(pending);
+                } else if pending.len() > MAX_PENDING_EVENTS {
+                    self.mode = Mode::Skip;
+                }
+            },
+        }
+    }
+}
+
+// Writes the events of a stream as they come in. Everything that queued up is
+// written and flushed at once, so a crashing server still leaves a usable
+// recording behind.
+async fn write_recording(
+    config: Arc<RecordConfig>,
+    invocation_id: String,
+    mut rx: mpsc::UnboundedReceiver<OrderedBuildEvent>,
+) {
+    let path = config.path(&invocation_id);
+    let res = async {
+        tokio::fs::create_dir_all(&config.dir).await?;
+        // Appended to, so that retries of the stream end up in the same
+        // recording after the original.
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        tracing::info!("Recording stream to {}", path.display());
+        let mut buf = vec![];
+        while let Some(obe) = rx.recv().await {
+            buf.clear();
+            obe.encode_length_delimited(&mut buf)?;
+            while let Ok(obe) = rx.try_recv() {
+                obe.encode_length_delimited(&mut buf)?;
+            }
+            file.write_all(&buf).await?;
+            file.flush().await.context("failed to flush")?;
+        }
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = res {
+        tracing::warn!("failed to record stream {invocation_id}: {e:#?}");
+    }
+}
+
+fn build_metadata(obe: &OrderedBuildEvent) -> Option<HashMap<String, String>> {
+    let Some(build_event::Event::BazelEvent(any)) = obe.event.as_ref()?.event.as_ref() else {
+        return None;
+    };
+    let be = build_event_stream::BuildEvent::decode(&any.value[..]).ok()?;
+    match be.payload {
+        Some(build_event_stream::build_event::Payload::BuildMetadata(md)) => Some(md.metadata),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obe(seq: i64, payload: build_event_stream::build_event::Payload) -> OrderedBuildEvent {
+        let be = build_event_stream::BuildEvent {
+            payload: Some(payload),
+            ..Default::default()
+        };
+        OrderedBuildEvent {
+            sequence_number: seq,
+            event: Some(BuildEvent {
+                event_time: None,
+                event: Some(build_event::Event::BazelEvent(
+                    any_proto::google::protobuf::Any {
+                        type_url: "type.googleapis.com/build_event_stream.BuildEvent".to_string(),
+                        value: be.encode_to_vec(),
+                    },
+                )),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn metadata(kv: &[(&str, &str)]) -> build_event_stream::build_event::Payload {
+        build_event_stream::build_event::Payload::BuildMetadata(build_event_stream::BuildMetadata {
+            metadata: kv
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    // Waits for the writer to catch up with `n` recorded events.
+    async fn read(path: &std::path::Path, n: usize) -> Vec<OrderedBuildEvent> {
+        let mut ret = vec![];
+        for _ in 0..100 {
+            let data = std::fs::read(path).unwrap_or_default();
+            let mut buf = &data[..];
+            ret.clear();
+            while let Ok(obe) = OrderedBuildEvent::decode_length_delimited(&mut buf) {
+                ret.push(obe);
+            }
+            if ret.len() >= n {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        ret
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let tmp = tempdir::TempDir::new("test_record").unwrap();
+        let config = Arc::new(RecordConfig {
+            dir: tmp.path().join("recordings"),
+            invocation_ids: HashSet::from(["by_id".to_string()]),
+            metadata: vec![("BRANCH".to_string(), "main".to_string())],
+        });
+        let started = || {
+            build_event_stream::build_event::Payload::Started(
+                build_event_stream::BuildStarted::default(),
+            )
+        };
+
+        // Selected by id: everything is recorded.
+        let mut r = Recorder::new(config.clone(), "by_id");
+        r.record(&obe(1, started()));
+        r.record(&obe(2, metadata(&[("BRANCH", "dev")])));
+        let got = read(&config.path("by_id"), 2).await;
+        assert_eq!(got.len(), 2);
+        assert!(
+            got.iter()
+                .all(|e| e.event.as_ref().unwrap().event_time.is_some())
+        );
+
+        // Selected by metadata: events before the metadata are kept too.
+        let mut r = Recorder::new(config.clone(), "by_metadata");
+        r.record(&obe(1, started()));
+        r.record(&obe(
+            2,
+            metadata(&[("BRANCH", "main"), ("COMMIT_SHA", "abc")]),
+        ));
+        r.record(&obe(3, started()));
+        let got = read(&config.path("by_metadata"), 3).await;
+        assert_eq!(
+            got.iter().map(|e| e.sequence_number).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        // A retry of the stream is appended to the same recording, even
+        // without build metadata.
+        let mut r = Recorder::new(config.clone(), "by_metadata");
+        r.record(&obe(3, started()));
+        r.record(&obe(4, started()));
+        let got = read(&config.path("by_metadata"), 5).await;
+        assert_eq!(
+            got.iter().map(|e| e.sequence_number).collect::<Vec<_>>(),
+            vec![1, 2, 3, 3, 4]
+        );
+
+        // Not selected.
+        let mut r = Recorder::new(config.clone(), "other");
+        r.record(&obe(1, started()));
+        r.record(&obe(2, metadata(&[("BRANCH", "dev")])));
+        assert!(!config.path("other").exists());
+    }
+}
@@ -3,10 +3,24 @@ use std::sync::Arc;
 use build_event_stream_proto::*;
 use build_proto::google::devtools::build::v1::*;
 use lazy_static::lazy_static;
-use prometheus_client::metrics::counter::Counter;
-use prost_reflect::prost::Message;
+use prometheus_client::metrics::{counter::Counter, histogram::Histogram};
+use prost_reflect::{ReflectMessage, prost::Message};
+use serde::{Deserialize, Serialize};
 
-use crate::{BuildState, EventHandler, ProccessedEvent};
+use crate::{
+    EventHandler,
+    ProccessedEvent,
+    recorder::{RecordConfig, Recorder},
+    spool::Spool,
+};
+
+// Received events are stored in batches, each in a single transaction, and
+// only acknowledged once stored. A batch is stored once it is large or old
+// enough, and when the build ends.
+const MAX_BATCH_EVENTS: usize = 500;
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+// Raw events held back while waiting for the build metadata of an invocation.
+const MAX_PENDING_RAW_EVENTS: usize = 10_000;
 
 lazy_static! {
     static ref MESSAGE_HANDLER_ERRORS: Counter::<u64> = metrics::register_metric(
@@ -14,6 +28,61 @@ lazy_static! {
         "Total number of errors returned by the message handlers",
         Counter::default()
     );
+    static ref DUPLICATE_EVENTS: Counter::<u64> = metrics::register_metric(
+        "blade_bep_duplicate_events",
+        "Total number of replayed build events that were already processed",
+        Counter::default()
+    );
+    static ref FLUSH_DURATION: Histogram = {
+        let buckets = [
+            0.001, 0.005, 0.010, 0.025, 0.050, 0.100, 0.250, 0.500, 1.0, 2.5, 5.0, 10.0,
+        ];
+        metrics::register_metric(
+            "blade_bep_flush_duration_seconds",
+            "Duration of storing a batch of build events",
+            Histogram::new(buckets.into_iter()),
+        )
+    };
+    static ref FLUSH_BATCH_SIZE: Histogram = {
+        let buckets = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0];
+        metrics::register_metric(
+            "blade_bep_flush_batch_size",
+            "Number of build events stored per batch",
+            Histogram::new(buckets.into_iter()),
+        )
+    };
+}
+
+// Invocation details that are only sent with the stream, not in the events.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StreamInfo {
+    pub project_id: String,
+    pub notification_keywords: Vec<String>,
+}
+
+// Everything needed to store a batch of events in a blocking task.
+pub(crate) struct Batch {
+    pub invocation_id: String,
+    pub stream: String,
+    pub sequence_number: i64,
+    pub reopen: bool,
+    // Set when the invocation could not be created when the stream started.
+    pub create: Option<std::time::SystemTime>,
+    // Set until it was stored with a batch.
+    pub stream_info: Option<StreamInfo>,
+    // Build events to keep for the events page, including ones that were
+    // held back from earlier batches.
+    pub raw_events: Vec<state::RawEvent>,
+    pub events: Vec<(i64, build_event_stream::BuildEvent)>,
+}
+
+// Whether the build events of a stream are stored for the events page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RawEventMode {
+    // Held back until the build metadata shows whether they are selected.
+    Pending,
+    Store,
+    Skip,
 }
 
 pub(crate) struct BESSession {
@@ -21,149 +90,466 @@ pub(crate) struct BESSession {
     global: Arc<state::Global>,
     invocation_id: String,
     build_over: bool,
+    stream_id: Option<StreamId>,
+    // Last event received on this stream, including earlier connections.
+    sequence_number: i64,
+    // Last event stored. Later events are waiting in `events`.
+    stored_sequence_number: i64,
+    // Set when resuming an invocation that was already closed, e.g. because
+    // the previous connection dropped.
+    reopen: bool,
+    // Start of an invocation that still has to be created.
+    create: Option<std::time::SystemTime>,
+    stream_info: Option<StreamInfo>,
+    events: Vec<(i64, build_event_stream::BuildEvent)>,
+    raw_event_mode: RawEventMode,
+    raw_events: Vec<state::RawEvent>,
+    // Received events that were not acknowledged yet, in order.
+    unacked: Vec<i64>,
+    flush_deadline: Option<tokio::time::Instant>,
+    record_config: Option<Arc<RecordConfig>>,
+    recorder: Option<Recorder>,
+    spool: Option<Arc<Spool>>,
 }
 
 impl BESSession {
     pub fn new(
         handlers: Arc<Vec<Box<dyn EventHandler + Sync + Send>>>,
         global: Arc<state::Global>,
+        record_config: Option<Arc<RecordConfig>>,
+        spool: Option<Arc<Spool>>,
     ) -> Self {
         BESSession {
             handlers,
             global,
             invocation_id: "".to_string(),
             build_over: false,
+            stream_id: None,
+            sequence_number: 0,
+            stored_sequence_number: 0,
+            reopen: false,
+            create: None,
+            stream_info: None,
+            events: vec![],
+            raw_event_mode: RawEventMode::Skip,
+            raw_events: vec![],
+            unacked: vec![],
+            flush_deadline: None,
+            record_config,
+            recorder: None,
+            spool,
         }
     }
 
     pub fn invocation_id(&self) -> &str { &self.invocation_id }
 
+    pub fn stream_id(&self) -> Option<StreamId> { self.stream_id.clone() }
+
+    /// When the received events have to be stored, if there are any.
+    pub fn flush_deadline(&self) -> Option<tokio::time::Instant> { self.flush_deadline }
+
     pub async fn process_message(
         &mut self,
         msg: Option<PublishBuildToolEventStreamRequest>,
     ) -> Result<crate::ProccessedEvent, tonic::Status> {
         let Some(msg) = msg else {
             return Ok(ProccessedEvent {
-                obe: OrderedBuildEvent::default(),
+                end_of_stream: true,
+                acks: self.flush().await?,
             });
         };
         if self.invocation_id.is_empty() {
-            self.invocation_id = extract_session_id(&msg)?;
+            let (invocation_id, stream_info) = extract_session_id(&msg)?;
+            self.invocation_id = invocation_id;
+            self.stream_info = Some(stream_info).filter(|i| *i != StreamInfo::default());
             let span = tracing::span::Span::current();
             span.record("session_uuid", &self.invocation_id);
             tracing::info!("Stream started");
-            validate_stream(self.global.clone(), &self.invocation_id).await?;
-            create_invocation(self.global.db_manager.clone(), &self.invocation_id)
-                .await
-                .map_err(|e| tonic::Status::internal(format!("{e:#?}")))?;
+            let existing = validate_stream(self.global.clone(), &self.invocation_id).await?;
+            self.stream_id = msg
+                .ordered_build_event
+                .as_ref()
+                .and_then(|obe| obe.stream_id.clone());
+            let selection = &self.global.raw_events;
+            self.raw_event_mode = if selection.selects(&self.invocation_id) {
+                RawEventMode::Store
+            } else if selection.metadata.is_empty() {
+                RawEventMode::Skip
+            } else {
+                RawEventMode::Pending
+            };
+            match existing {
+                Some(inv) => {
+                    // Bazel retries uploads on a new stream and replays every
+                    // event that was not acknowledged yet.
+                    let inv_id = self.invocation_id.clone();
+                    let stream = self.stream_key();
+                    let pending = self.raw_event_mode == RawEventMode::Pending;
+                    // Spooled events were acknowledged too. Looked up before
+                    // the database, so that a batch that is replayed in
+                    // between is seen in one or the other.
+                    let spooled = self
+                        .spool
+                        .as_ref()
+                        .and_then(|spool| spool.sequence_number(&inv_id, &stream));
+                    let (sequence_number, stored_raw_events) =
+                        db::run(self.global.db_manager.clone(), move |db| {
+                            let sequence_number =
+                                db.get_stream_sequence_number(&inv_id, &stream)?;
+                            // A retried stream is stored if the original one
+                            // was, even if it doesn't resend the build metadata.
+                            let stored =
+                                pending && !db.get_raw_events(&inv_id, None, 0, 1)?.is_empty();
+                            Ok((sequence_number, stored))
+                        })
+                        .await
+                        .map_err(|e| tonic::Status::internal(format!("{e:#?}")))?;
+                    self.sequence_number = sequence_number.max(spooled).unwrap_or_default();
+                    if stored_raw_events {
+                        self.raw_event_mode = RawEventMode::Store;
+                    }
+                    self.stored_sequence_number = self.sequence_number;
+                    self.reopen = inv.end.is_some();
+                    tracing::info!("Resuming stream after event {}", self.sequence_number);
+                },
+                None => {
+                    let start = std::time::SystemTime::now();
+                    match create_invocation(
+                        self.global.db_manager.clone(),
+                        &self.invocation_id,
+                        start,
+                    )
+                    .await
+                    {
+                        Ok(()) => {},
+                        // Created along with the first spooled batch instead.
+                        Err(e) if self.spool.is_some() => {
+                            tracing::warn!("failed to create invocation: {e:#}");
+                            self.create = Some(start);
+                        },
+                        Err(e) => return Err(tonic::Status::internal(format!("{e:#?}"))),
+                    }
+                },
+            }
+            self.recorder = self
+                .record_config
+                .clone()
+                .map(|c| Recorder::new(c, &self.invocation_id));
         }
 
-        // Update heartbeat for liveness tracking
-        let mgr = self.global.db_manager.clone();
-        let inv_id = self.invocation_id.to_string();
-        let _ = db::run(mgr, move |db| db.update_invocation_heartbeat(&inv_id))
-            .await
-            .inspect_err(|e| {
-                tracing::warn!(
-                    "Failed to update heartbeat for {}: {:#?}",
-                    self.invocation_id,
-                    e
-                );
-            });
-
         let Some(obe) = msg.ordered_build_event else {
             return Err(tonic::Status::invalid_argument("Empty OBE"));
         };
-        let state = self.handle_ordered_build_event(&obe).await?;
-        matches!(state, BuildState::BuildFinished).then(|| {
-            self.build_over = true;
-        });
-        Ok(crate::ProccessedEvent { obe })
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(&obe);
+        }
+        let expected = self.sequence_number + 1;
+        if obe.sequence_number > expected {
+            // Nothing after the last processed event was acked, so a retried
+            // upload fills the gap.
+            return Err(tonic::Status::failed_precondition(format!(
+                "expected event {expected}, got {}",
+                obe.sequence_number
+            )));
+        }
+        self.unacked.push(obe.sequence_number);
+        if obe.sequence_number < expected {
+            // Already processed, but the ack may have been lost. Ack it again
+            // so the client moves on.
+            tracing::debug!("Ignoring duplicate event {}", obe.sequence_number);
+            DUPLICATE_EVENTS.inc();
+        } else {
+            self.receive(&obe)?;
+        }
+
+        // Clients close the stream with an empty event.
+        let end_of_stream = obe.event.is_none();
+        let acks = if end_of_stream
+            || self.build_over
+            || self.events.len() >= MAX_BATCH_EVENTS
+            || self
+                .flush_deadline
+                .is_some_and(|d| d <= tokio::time::Instant::now())
+        {
+            self.flush().await?
+        } else if self.stored_sequence_number == self.sequence_number {
+            // Nothing waiting to be stored, e.g. only duplicates were received.
+            std::mem::take(&mut self.unacked)
+        } else {
+            vec![]
+        };
+        Ok(crate::ProccessedEvent {
+            end_of_stream,
+            acks,
+        })
     }
 
-    async fn handle_ordered_build_event(
-        &self,
-        obe: &OrderedBuildEvent,
-    ) -> Result<BuildState, tonic::Status> {
-        let mut build_state = BuildState::BuildInProgress;
+    fn receive(&mut self, obe: &OrderedBuildEvent) -> Result<(), tonic::Status> {
+        self.sequence_number = obe.sequence_number;
+        self.flush_deadline
+            .get_or_insert_with(|| tokio::time::Instant::now() + FLUSH_INTERVAL);
         let Some(event) = obe.event.as_ref().and_then(|event| event.event.as_ref()) else {
-            // If there is no event for some reason, just read the next event.
-            return Ok(BuildState::BuildInProgress);
+            return Ok(());
         };
         match event {
             build_event::Event::BazelEvent(any) => {
                 let be = build_event_stream::BuildEvent::decode(&any.value[..]).map_err(|e| {
                     tonic::Status::invalid_argument(format!("badly formatted BuildEvent: {e:#?}"))
                 })?;
-                be.last_message.then(|| {
-                    build_state = BuildState::BuildFinished;
-                });
-                match be.payload.as_ref() {
-                    Some(build_event_stream::build_event::Payload::Finished(f)) => {
-                        let success = f
-                            .exit_code
-                            .as_ref()
-                            .unwrap_or(&build_event_stream::build_finished::ExitCode {
-                                name: "idk".into(),
-                                code: 1,
-                            })
-                            .code
-                            == 0;
-                        write_session_result(
-                            self.global.db_manager.clone(),
-                            &self.invocation_id,
-                            success,
-                        )
-                        .await
-                        .map_err(|e| tonic::Status::internal(format!("{e:#?}")))?;
-                    },
-                    Some(_) => {
-                        for v in &*self.handlers {
-                            if let Err(e) =
-                                v.handle_event(&*self.global.db_manager, &self.invocation_id, &be)
-                            {
-                                tracing::warn!("{:#?}", e);
-                                MESSAGE_HANDLER_ERRORS.inc();
-                            }
-                        }
-                    },
-                    _ => {},
+                if be.last_message {
+                    self.build_over = true;
                 }
+                self.collect_raw_event(obe.sequence_number, &be);
+                self.events.push((obe.sequence_number, be));
             },
             build_event::Event::ComponentStreamFinished(_) => {
-                build_state = BuildState::BuildFinished;
+                self.build_over = true;
             },
             _ => {},
         }
-        Ok(build_state)
+        Ok(())
+    }
+
+    fn collect_raw_event(&mut self, sequence: i64, be: &build_event_stream::BuildEvent) {
+        if self.raw_event_mode == RawEventMode::Skip {
+            return;
+        }
+        match raw_event(sequence, be) {
+            Ok(event) => self.raw_events.push(event),
+            Err(e) => tracing::warn!("failed to convert raw event {sequence}: {e:#?}"),
+        }
+        if self.raw_event_mode != RawEventMode::Pending {
+            return;
+        }
+        if let Some(build_event_stream::build_event::Payload::BuildMetadata(md)) = &be.payload
+            && self.global.raw_events.matches(&md.metadata)
+        {
+            self.raw_event_mode = RawEventMode::Store;
+        } else if self.raw_events.len() > MAX_PENDING_RAW_EVENTS {
+            self.raw_event_mode = RawEventMode::Skip;
+            self.raw_events = vec![];
+        }
+    }
+
+    /// Stores the received events in a single transaction and returns the
+    /// sequence numbers that can be acknowledged now. With a spool, events
+    /// that can't be stored are spooled and acknowledged anyway.
+    pub async fn flush(&mut self) -> Result<Vec<i64>, tonic::Status> {
+        self.flush_deadline = None;
+        if let Some(spool) = &self.spool
+            && spool.has_failed(&self.invocation_id, &self.stream_key())
+        {
+            return Err(tonic::Status::data_loss(
+                "acknowledged events of the stream could not be stored",
+            ));
+        }
+        if self.stored_sequence_number == self.sequence_number {
+            return Ok(std::mem::take(&mut self.unacked));
+        }
+        let batch = Arc::new(Batch {
+            invocation_id: self.invocation_id.clone(),
+            stream: self.stream_key(),
+            sequence_number: self.sequence_number,
+            reopen: self.reopen,
+            create: self.create,
+            stream_info: self.stream_info.clone(),
+            raw_events: match self.raw_event_mode {
+                RawEventMode::Store => std::mem::take(&mut self.raw_events),
+                _ => vec![],
+            },
+            events: std::mem::take(&mut self.events),
+        });
+        let size = batch.events.len();
+        let handlers = self.handlers.clone();
+        let start = std::time::Instant::now();
+        let res = match &self.spool {
+            // Spooled batches of the stream have to be stored first to keep
+            // the order. Other streams don't wait for them.
+            Some(spool) if spool.has_stream(&batch.invocation_id, &batch.stream) => {
+                spool.push(&batch).await
+            },
+            _ => {
+                let b = batch.clone();
+                let res = db::transaction(self.global.db_manager.clone(), move |db| {
+                    apply(db, &handlers, &b)
+                })
+                .await;
+                match (res, &self.spool) {
+                    (Err(e), Some(spool)) => {
+                        tracing::warn!("failed to store events, spooling them: {e:#}");
+                        spool.push(&batch).await
+                    },
+                    (res, _) => res,
+                }
+            },
+        };
+        if let Err(e) = res {
+            // Nothing was stored, so the events have to be sent again.
+            self.sequence_number = self.stored_sequence_number;
+            self.unacked.clear();
+            return Err(tonic::Status::internal(format!("{e:#?}")));
+        }
+        FLUSH_DURATION.observe(start.elapsed().as_secs_f64());
+        FLUSH_BATCH_SIZE.observe(size as f64);
+        self.reopen = false;
+        self.create = None;
+        self.stream_info = None;
+        self.stored_sequence_number = self.sequence_number;
+        Ok(std::mem::take(&mut self.unacked))
+    }
+
+    // Bazel only uses a single stream per invocation, but BES allows one per
+    // build and component.
+    fn stream_key(&self) -> String {
+        self.stream_id
+            .as_ref()
+            .map(|sid| format!("{}/{}", sid.build_id, sid.component))
+            .unwrap_or_default()
     }
 
     pub fn is_build_over(&self) -> bool { self.build_over }
 }
 
+pub(crate) fn apply(
+    db: &mut dyn state::DB,
+    handlers: &[Box<dyn EventHandler + Sync + Send>],
+    batch: &Batch,
+) -> anyhow::Result<()> {
+    // Written first so that the transaction holds the write lock from the
+    // start on SQLite.
+    if let Some(start) = batch.create
+        && db.get_shallow_invocation(&batch.invocation_id).is_err()
+    {
+        db.upsert_shallow_invocation(&new_invocation(&batch.invocation_id, start))?;
+    }
+    db.update_invocation_heartbeat(&batch.invocation_id)?;
+    if batch.reopen {
+        // A finished build sends no further events, so the previous stream
+        // was cut off and the build is still running.
+        reopen_invocation(db, &batch.invocation_id)?;
+    }
+    if let Some(info) = &batch.stream_info {
+        write_stream_info(db, &batch.invocation_id, info)?;
+    }
+    for event in &batch.raw_events {
+        store_raw_event(db, &batch.invocation_id, event);
+    }
+    for (sequence, be) in &batch.events {
+        apply_event(db, handlers, &batch.invocation_id, *sequence, be)?;
+    }
+    db.update_stream_sequence_number(&batch.invocation_id, &batch.stream, batch.sequence_number)
+}
+
+fn apply_event(
+    db: &mut dyn state::DB,
+    handlers: &[Box<dyn EventHandler + Sync + Send>],
+    invocation_id: &str,
+    sequence: i64,
+    be: &build_event_stream::BuildEvent,
+) -> anyhow::Result<()> {
+    match be.payload.as_ref() {
+        Some(build_event_stream::build_event::Payload::Finished(f)) => {
+            let exit_code = f.exit_code.as_ref().map(|e| state::ExitCode {
+                name: e.name.clone(),
+                code: e.code,
+            });
+            let failure_detail = f.failure_detail.as_ref().map(failure_detail);
+            // Imported builds finished long before they are processed.
+            let end = f
+                .finish_time
+                .as_ref()
+                .and_then(|t| prototime::timestamp::from_proto(t).ok());
+            // Nothing else is reported once the build finished.
+            let n = db.mark_incomplete(
+                invocation_id,
+                end.unwrap_or_else(std::time::SystemTime::now),
+            )?;
+            if n > 0 {
+                tracing::info!("Marked {n} unfinished targets and tests as incomplete");
+            }
+            write_session_result(db, invocation_id, exit_code, failure_detail, end)
+        },
+        Some(_) => {
+            // An event is applied by all handlers or not at all, so that a
+            // failing handler does not leave partial results behind.
+            let res = db.transaction(Box::new(|db: &mut dyn state::DB| {
+                handlers
+                    .iter()
+                    .try_for_each(|h| h.handle_event(db, invocation_id, be))
+            }));
+            if let Err(e) = res {
+                tracing::warn!("failed to handle event {sequence}: {e:#?}");
+                MESSAGE_HANDLER_ERRORS.inc();
+            }
+            Ok(())
+        },
+        None => Ok(()),
+    }
+}
+
+// Failing to store the raw event does not affect the build results, so it is
+// not an error for the stream.
+fn store_raw_event(db: &mut dyn state::DB, invocation_id: &str, event: &state::RawEvent) {
+    let res = db.transaction(Box::new(|db: &mut dyn state::DB| {
+        db.insert_raw_event(invocation_id, event)
+    }));
+    if let Err(e) = res {
+        tracing::warn!("failed to store raw event {}: {e:#?}", event.sequence);
+    }
+}
+
+fn raw_event(
+    sequence: i64,
+    be: &build_event_stream::BuildEvent,
+) -> anyhow::Result<state::RawEvent> {
+    // The kind is the name of the BuildEventId oneof field that is set.
+    let kind = be
+        .id
+        .as_ref()
+        .and_then(|id| {
+            id.transcode_to_dynamic()
+                .fields()
+                .next()
+                .map(|(f, _)| f.name().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    Ok(state::RawEvent {
+        sequence,
+        kind,
+        json: serde_json::to_string(&be.transcode_to_dynamic())?,
+    })
+}
+
 fn extract_session_id(
     msg: &build_proto::google::devtools::build::v1::PublishBuildToolEventStreamRequest,
-) -> Result<String, tonic::Status> {
+) -> Result<(String, StreamInfo), tonic::Status> {
     let id = msg
         .ordered_build_event
         .as_ref()
         .and_then(|obe| obe.stream_id.as_ref())
         .map(|sid| sid.invocation_id.clone())
         .ok_or_else(|| tonic::Status::invalid_argument("Missing stream id"))?;
-    Ok(id)
+    Ok((
+        id,
+        StreamInfo {
+            project_id: msg.project_id.clone(),
+            notification_keywords: msg.notification_keywords.clone(),
+        },
+    ))
 }
 
+/// Returns the invocation if it already exists.
 async fn validate_stream(
     global: Arc<state::Global>,
     session_uuid: &str,
-) -> Result<(), tonic::Status> {
+) -> Result<Option<state::InvocationResults>, tonic::Status> {
     let mgr = global.db_manager.clone();
     let session_id = session_uuid.to_string();
     let session_lock_time = global.session_lock_time;
 
     let inv = match db::run(mgr, move |db| db.get_shallow_invocation(&session_id)).await {
         Ok(inv) => inv,
-        Err(_) => return Ok(()), // Invocation doesn't exist yet, allow creation
+        Err(_) => return Ok(None), // Invocation doesn't exist yet, allow creation
     };
 
     if let Some(end) = inv.end
@@ -174,43 +560,788 @@ async fn validate_stream(
     {
         return Err(tonic::Status::failed_precondition("session already ended"));
     }
-    Ok(())
+    Ok(Some(inv))
 }
 
-async fn write_session_result(
-    db_mgr: std::sync::Arc<dyn state::DBManager>,
+fn write_session_result(
+    db: &mut dyn state::DB,
     invocation_id: &str,
-    success: bool,
+    exit_code: Option<state::ExitCode>,
+    failure_detail: Option<state::FailureDetail>,
+    end: Option<std::time::SystemTime>,
 ) -> anyhow::Result<()> {
-    let inv_id = invocation_id.to_string();
-    db::run_group(db_mgr, move |db| {
-        db.update_shallow_invocation(
-            &inv_id,
-            Box::new(move |i: &mut state::InvocationResults| {
-                match success {
-                    true => i.status = state::Status::Success,
-                    false => i.status = state::Status::Fail,
-                }
-                i.end = Some(std::time::SystemTime::now());
-                Ok(())
-            }),
-        )
-    })
-    .await
+    db.update_shallow_invocation(
+        invocation_id,
+        Box::new(move |i: &mut state::InvocationResults| {
+            // A build without an exit code did not succeed.
+            match exit_code.as_ref().is_some_and(|e| e.code == 0) {
+                true => i.status = state::Status::Success,
+                false => i.status = state::Status::Fail,
+            }
+            i.exit_code = exit_code;
+            i.failure_detail = failure_detail;
+            i.end = Some(end.unwrap_or_else(std::time::SystemTime::now));
+            Ok(())
+        }),
+    )
+}
+
+// The category is the name of the FailureDetail oneof field that is set, and
+// the code the name of the `code` enum value of that category.
+pub(crate) fn failure_detail(fd: &failure_details::FailureDetail) -> state::FailureDetail {
+    let mut detail = state::FailureDetail {
+        message: fd.message.clone(),
+        ..Default::default()
+    };
+    let dm = fd.transcode_to_dynamic();
+    let Some((field, value)) = dm.fields().find(|(f, _)| f.containing_oneof().is_some()) else {
+        return detail;
+    };
+    detail.category = field.name().to_string();
+    detail.code = value
+        .as_message()
+        .and_then(|m| {
+            let code = m.descriptor().get_field_by_name("code")?;
+            let n = m.get_field(&code).as_enum_number()?;
+            code.kind()
+                .as_enum()?
+                .get_value(n)
+                .map(|v| v.name().to_string())
+        })
+        .unwrap_or_default();
+    detail
+}
+
+fn write_stream_info(
+    db: &mut dyn state::DB,
+    invocation_id: &str,
+    info: &StreamInfo,
+) -> anyhow::Result<()> {
+    let info = info.clone();
+    db.update_shallow_invocation(
+        invocation_id,
+        Box::new(move |i: &mut state::InvocationResults| {
+            if !info.project_id.is_empty() {
+                i.project_id = Some(info.project_id);
+            }
+            if !info.notification_keywords.is_empty() {
+                i.notification_keywords = info.notification_keywords;
+            }
+            Ok(())
+        }),
+    )
+}
+
+fn reopen_invocation(db: &mut dyn state::DB, invocation_id: &str) -> anyhow::Result<()> {
+    db.update_shallow_invocation(
+        invocation_id,
+        Box::new(move |i: &mut state::InvocationResults| {
+            i.status = state::Status::InProgress;
+            i.end = None;
+            Ok(())
+        }),
+    )
+}
+
+fn new_invocation(invocation_id: &str, start: std::time::SystemTime) -> state::InvocationResults {
+    state::InvocationResults {
+        id: invocation_id.to_string(),
+        status: state::Status::InProgress,
+        start,
+        ..Default::default()
+    }
 }
 
 async fn create_invocation(
     db_mgr: std::sync::Arc<dyn state::DBManager>,
     invocation_id: &str,
+    start: std::time::SystemTime,
 ) -> anyhow::Result<()> {
-    let inv_id = invocation_id.to_string();
-    db::run_group(db_mgr, move |db| {
-        db.upsert_shallow_invocation(&state::InvocationResults {
-            id: inv_id,
-            status: state::Status::InProgress,
-            start: std::time::SystemTime::now(),
+    let inv = new_invocation(invocation_id, start);
+    db::run_group(db_mgr, move |db| db.upsert_shallow_invocation(&inv)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use regex::Regex;
+    use testonly::sqlite;
+
+    use super::*;
+
+    fn global_with(
+        db_manager: Arc<dyn state::DBManager>,
+        raw_events: state::RawEventSelection,
+    ) -> Arc<state::Global> {
+        Arc::new(state::Global {
+            raw_events,
+            ..testonly::global(db_manager)
+        })
+    }
+
+    fn global(tmp: &tempdir::TempDir) -> Arc<state::Global> {
+        global_with(sqlite(tmp), Default::default())
+    }
+
+    // A database that can be taken down.
+    struct FlakyManager {
+        inner: Arc<dyn state::DBManager>,
+        up: AtomicBool,
+    }
+
+    impl state::DBManager for FlakyManager {
+        fn get(&self) -> anyhow::Result<Box<dyn state::DB>> {
+            if !self.up.load(Ordering::SeqCst) {
+                return Err(anyhow::anyhow!("database is down"));
+            }
+            self.inner.get()
+        }
+    }
+
+    fn session(global: &Arc<state::Global>) -> BESSession {
+        let handlers = crate::handlers(Arc::new(Mutex::new(Regex::new("").unwrap())));
+        BESSession::new(Arc::new(handlers), global.clone(), None, None)
+    }
+
+    fn progress(stdout: &str) -> build_event_stream::BuildEvent {
+        build_event_stream::BuildEvent {
+            payload: Some(build_event_stream::build_event::Payload::Progress(
+                build_event_stream::Progress {
+                    stdout: stdout.to_string(),
+                    stderr: String::new(),
+                },
+            )),
             ..Default::default()
+        }
+    }
+
+    fn finished() -> build_event_stream::BuildEvent {
+        build_event_stream::BuildEvent {
+            payload: Some(build_event_stream::build_event::Payload::Finished(
+                build_event_stream::BuildFinished {
+                    exit_code: Some(build_event_stream::build_finished::ExitCode {
+                        name: "SUCCESS".to_string(),
+                        code: 0,
+                    }),
+                    ..Default::default()
+                },
+            )),
+            last_message: true,
+            ..Default::default()
+        }
+    }
+
+    fn events() -> Vec<build_event_stream::BuildEvent> {
+        vec![
+            progress("one"),
+            progress("two"),
+            progress("three"),
+            progress("four"),
+            finished(),
+        ]
+    }
+
+    async fn send(
+        session: &mut BESSession,
+        invocation_id: &str,
+        events: &[build_event_stream::BuildEvent],
+        sequence_numbers: impl IntoIterator<Item = i64>,
+    ) -> Result<Vec<i64>, tonic::Status> {
+        let stream_id = StreamId {
+            invocation_id: invocation_id.to_string(),
+            ..Default::default()
+        };
+        let mut acks = vec![];
+        for seq in sequence_numbers {
+            let req = crate::import::to_request(&stream_id, seq, &events[seq as usize - 1]);
+            acks.extend(session.process_message(Some(req)).await?.acks);
+        }
+        Ok(acks)
+    }
+
+    async fn stored(global: &Arc<state::Global>, id: &str) -> (state::Status, String) {
+        let id = id.to_string();
+        db::run_group(global.db_manager.clone(), move |db| {
+            Ok((
+                db.get_shallow_invocation(&id)?.status,
+                db.get_progress(&id)?,
+            ))
         })
-    })
-    .await
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let tmp = tempdir::TempDir::new("test_reconnect").unwrap();
+        let global = global(&tmp);
+        let events = events();
+
+        let mut s = session(&global);
+        let acks = send(&mut s, "clean", &events, 1..=5).await.unwrap();
+        assert_eq!(acks, vec![1, 2, 3, 4, 5]);
+        assert!(s.is_build_over());
+        let want = stored(&global, "clean").await;
+        assert_eq!(want.0, state::Status::Success);
+
+        // Events are only acked once they are stored.
+        let mut s = session(&global);
+        assert!(
+            send(&mut s, "retried", &events, 1..=3)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(stored(&global, "retried").await.1, "");
+        assert_eq!(s.flush().await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(stored(&global, "retried").await.1, "one\ntwo\nthree");
+
+        // The connection drops before the ack of event 3 reached the client.
+        crate::unexpected_cleanup_session(global.db_manager.clone(), "retried")
+            .await
+            .unwrap();
+        assert_eq!(stored(&global, "retried").await.0, state::Status::Fail);
+
+        // The retry replays everything after the last ack.
+        let mut s = session(&global);
+        let acks = send(&mut s, "retried", &events, 3..=5).await.unwrap();
+        assert_eq!(acks, vec![3, 4, 5]);
+        assert!(s.is_build_over());
+        assert_eq!(stored(&global, "retried").await, want);
+
+        // Retrying a finished upload changes nothing.
+        let mut s = session(&global);
+        let acks = send(&mut s, "retried", &events, 4..=5).await.unwrap();
+        assert_eq!(acks, vec![4, 5]);
+        assert_eq!(stored(&global, "retried").await, want);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order() {
+        let tmp = tempdir::TempDir::new("test_out_of_order").unwrap();
+        let global = global(&tmp);
+        let events = events();
+
+        let mut s = session(&global);
+        assert!(
+            send(&mut s, "gap", &events, [1, 2])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let err = send(&mut s, "gap", &events, [4]).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+        let acks = send(&mut s, "gap", &events, [3, 4, 5]).await.unwrap();
+        assert_eq!(acks, vec![1, 2, 3, 4, 5]);
+        // Duplicates are acked again, but not processed.
+        let acks = send(&mut s, "gap", &events, [2, 1]).await.unwrap();
+        assert_eq!(acks, vec![2, 1]);
+        assert_eq!(
+            stored(&global, "gap").await,
+            (state::Status::Success, "one\ntwo\nthree\nfour".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_info() {
+        let tmp = tempdir::TempDir::new("test_stream_info").unwrap();
+        let global = global(&tmp);
+        let stream_id = StreamId {
+            invocation_id: "info".to_string(),
+            ..Default::default()
+        };
+        let options = build_event_stream::BuildEvent {
+            payload: Some(build_event_stream::build_event::Payload::OptionsParsed(
+                build_event_stream::OptionsParsed {
+                    tool_tag: "presubmit".to_string(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        let mut s = session(&global);
+        for (seq, be) in [(1, &options), (2, &finished())] {
+            let mut req = crate::import::to_request(&stream_id, seq, be);
+            req.project_id = "project".to_string();
+            req.notification_keywords = vec!["ci".to_string(), "nightly".to_string()];
+            s.process_message(Some(req)).await.unwrap();
+        }
+
+        let inv = db::run(global.db_manager.clone(), |db| {
+            db.get_shallow_invocation("info")
+        })
+        .await
+        .unwrap();
+        assert_eq!(inv.project_id.as_deref(), Some("project"));
+        assert_eq!(inv.notification_keywords, vec!["ci", "nightly"]);
+        assert_eq!(inv.tool_tag.as_deref(), Some("presubmit"));
+    }
+
+    #[tokio::test]
+    async fn test_incomplete() {
+        use build_event_stream::{build_event::Payload, build_event_id::Id};
+
+        let tmp = tempdir::TempDir::new("test_incomplete").unwrap();
+        let global = global(&tmp);
+        let id = |id| build_event_stream::BuildEventId { id: Some(id) };
+        let configured = |label: &str| {
+            Id::TargetConfigured(build_event_stream::build_event_id::TargetConfiguredId {
+                label: label.to_string(),
+                ..Default::default()
+            })
+        };
+        let test_summary = |label: &str| {
+            Id::TestSummary(build_event_stream::build_event_id::TestSummaryId {
+                label: label.to_string(),
+                ..Default::default()
+            })
+        };
+        let events = vec![
+            build_event_stream::BuildEvent {
+                id: Some(id(Id::Pattern(
+                    build_event_stream::build_event_id::PatternExpandedId {
+                        pattern: vec!["//...".to_string()],
+                    },
+                ))),
+                children: ["//:built", "//:test", "//:never"]
+                    .map(|l| id(configured(l)))
+                    .to_vec(),
+                payload: Some(Payload::Expanded(Default::default())),
+                ..Default::default()
+            },
+            build_event_stream::BuildEvent {
+                id: Some(id(configured("//:built"))),
+                payload: Some(Payload::Configured(Default::default())),
+                ..Default::default()
+            },
+            build_event_stream::BuildEvent {
+                id: Some(id(Id::TargetCompleted(
+                    build_event_stream::build_event_id::TargetCompletedId {
+                        label: "//:built".to_string(),
+                        ..Default::default()
+                    },
+                ))),
+                payload: Some(Payload::Completed(build_event_stream::TargetComplete {
+                    success: true,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            build_event_stream::BuildEvent {
+                id: Some(id(configured("//:test"))),
+                children: vec![id(test_summary("//:test"))],
+                payload: Some(Payload::Configured(Default::default())),
+                ..Default::default()
+            },
+            finished(),
+        ];
+
+        let mut s = session(&global);
+        send(&mut s, "incomplete", &events, 1..=5).await.unwrap();
+
+        let inv = db::run(global.db_manager.clone(), |db| {
+            db.get_invocation("incomplete")
+        })
+        .await
+        .unwrap();
+        let status = |name: &str| inv.targets.get(name).map(|t| t.status);
+        assert_eq!(status("//:built"), Some(state::Status::Success));
+        assert_eq!(status("//:test"), Some(state::Status::Incomplete));
+        assert_eq!(status("//:never"), Some(state::Status::Incomplete));
+        assert_eq!(
+            inv.tests.get("//:test").map(|t| t.status),
+            Some(state::Status::Incomplete)
+        );
+        assert_eq!(inv.status, state::Status::Success);
+    }
+
+    #[tokio::test]
+    async fn test_target_failure() {
+        use build_event_stream::{build_event::Payload, build_event_id::Id};
+
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let tmp = tempdir::TempDir::new("test_target_failure").unwrap();
+        let global = global(&tmp);
+        let id = |id| build_event_stream::BuildEventId { id: Some(id) };
+        let configured = |label: &str| {
+            id(Id::TargetConfigured(
+                build_event_stream::build_event_id::TargetConfiguredId {
+                    label: label.to_string(),
+                    ..Default::default()
+                },
+            ))
+        };
+        let action = |label: &str| {
+            id(Id::ActionCompleted(
+                build_event_stream::build_event_id::ActionCompletedId {
+                    label: label.to_string(),
+                    ..Default::default()
+                },
+            ))
+        };
+        let completed = |label: &str, cause: &str| build_event_stream::BuildEvent {
+            id: Some(id(Id::TargetCompleted(
+                build_event_stream::build_event_id::TargetCompletedId {
+                    label: label.to_string(),
+                    ..Default::default()
+                },
+            ))),
+            children: vec![action(cause)],
+            payload: Some(Payload::Completed(build_event_stream::TargetComplete {
+                success: false,
+                failure_detail: Some(failure_details::FailureDetail {
+                    message: "compile failed".to_string(),
+                    category: Some(failure_details::failure_detail::Category::Spawn(
+                        failure_details::Spawn {
+                            code: failure_details::spawn::Code::NonZeroExit.into(),
+                            ..Default::default()
+                        },
+                    )),
+                }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let events = vec![
+            build_event_stream::BuildEvent {
+                id: Some(configured("//:lib")),
+                payload: Some(Payload::Configured(Default::default())),
+                ..Default::default()
+            },
+            build_event_stream::BuildEvent {
+                id: Some(configured("//:bin")),
+                payload: Some(Payload::Configured(Default::default())),
+                ..Default::default()
+            },
+            completed("//:lib", "//:lib"),
+            completed("//:bin", "//:lib"),
+            build_event_stream::BuildEvent {
+                id: Some(id(Id::UnconfiguredLabel(
+                    build_event_stream::build_event_id::UnconfiguredLabelId {
+                        label: "//:missing.txt".to_string(),
+                    },
+                ))),
+                payload: Some(Payload::Aborted(build_event_stream::Aborted {
+                    reason: build_event_stream::aborted::AbortReason::LoadingFailure.into(),
+                    description: "missing input file".to_string(),
+                })),
+                ..Default::default()
+            },
+            finished(),
+        ];
+
+        let mut s = session(&global);
+        send(&mut s, "failure", &events, 1..=6).await.unwrap();
+
+        let inv = db::run(global.db_manager.clone(), |db| db.get_invocation("failure"))
+            .await
+            .unwrap();
+        let failure = |name: &str| inv.targets.get(name).and_then(|t| t.failure.clone());
+        assert_eq!(
+            failure("//:lib"),
+            Some(state::TargetFailure {
+                message: "compile failed".to_string(),
+                category: "spawn".to_string(),
+                root_causes: vec![],
+            })
+        );
+        assert_eq!(
+            failure("//:bin").map(|f| f.root_causes),
+            Some(vec!["//:lib".to_string()])
+        );
+        assert_eq!(
+            failure("//:missing.txt"),
+            Some(state::TargetFailure {
+                message: "missing input file".to_string(),
+                category: "loading_failure".to_string(),
+                root_causes: vec![],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_info() {
+        use build_event_stream::build_event::Payload;
+
+        let tmp = tempdir::TempDir::new("test_build_info").unwrap();
+        let global = global(&tmp);
+        let events = vec![
+            build_event_stream::BuildEvent {
+                payload: Some(Payload::Started(build_event_stream::BuildStarted {
+                    command: "build".to_string(),
+                    build_tool_version: "7.4.1".to_string(),
+                    workspace_directory: "/src/ws".to_string(),
+                    working_directory: "/src/ws/pkg".to_string(),
+                    server_pid: 42,
+                    options_description: "--config=ci".to_string(),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            build_event_stream::BuildEvent {
+                payload: Some(Payload::WorkspaceStatus(
+                    build_event_stream::WorkspaceStatus {
+                        item: [
+                            ("BUILD_USER", "alice"),
+                            ("BUILD_HOST", "ci-42"),
+                            ("BUILD_SCM_REVISION", "abc123"),
+                        ]
+                        .map(|(key, value)| build_event_stream::workspace_status::Item {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                        })
+                        .to_vec(),
+                    },
+                )),
+                ..Default::default()
+            },
+            finished(),
+        ];
+
+        let mut s = session(&global);
+        send(&mut s, "info", &events, 1..=3).await.unwrap();
+
+        let (inv, opts) = db::run(global.db_manager.clone(), |db| {
+            Ok((db.get_shallow_invocation("info")?, db.get_options("info")?))
+        })
+        .await
+        .unwrap();
+        assert_eq!(inv.command, "build");
+        assert_eq!(inv.build_tool_version.as_deref(), Some("7.4.1"));
+        assert_eq!(inv.workspace_directory.as_deref(), Some("/src/ws"));
+        assert_eq!(inv.working_directory.as_deref(), Some("/src/ws/pkg"));
+        assert_eq!(inv.server_pid, Some(42));
+        assert_eq!(inv.options_description.as_deref(), Some("--config=ci"));
+        assert_eq!(inv.build_user.as_deref(), Some("alice"));
+        assert_eq!(inv.build_host.as_deref(), Some("ci-42"));
+        assert_eq!(
+            opts.workspace_status
+                .get("BUILD_SCM_REVISION")
+                .map(String::as_str),
+            Some("abc123")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_raw_events() {
+        use build_event_stream::build_event::Payload;
+
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let tmp = tempdir::TempDir::new("test_raw_events").unwrap();
+        let global = global_with(
+            sqlite(&tmp),
+            state::RawEventSelection {
+                invocation_ids: ["by_id".to_string()].into(),
+                metadata: vec![("BRANCH".to_string(), "main".to_string())],
+                ..Default::default()
+            },
+        );
+        let metadata = |branch: &str| build_event_stream::BuildEvent {
+            payload: Some(Payload::BuildMetadata(build_event_stream::BuildMetadata {
+                metadata: [("BRANCH".to_string(), branch.to_string())].into(),
+            })),
+            ..Default::default()
+        };
+        let stored = async |id: &str| {
+            let id = id.to_string();
+            db::run(global.db_manager.clone(), move |db| {
+                db.get_raw_events(&id, None, 0, 100)
+            })
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.sequence)
+            .collect::<Vec<_>>()
+        };
+
+        let events = vec![
+            progress("one"),
+            metadata("dev"),
+            progress("two"),
+            finished(),
+        ];
+        let mut s = session(&global);
+        send(&mut s, "by_id", &events, 1..=4).await.unwrap();
+        assert_eq!(stored("by_id").await, vec![1, 2, 3, 4]);
+        let mut s = session(&global);
+        send(&mut s, "other", &events, 1..=4).await.unwrap();
+        assert!(stored("other").await.is_empty());
+
+        // Events before the build metadata are kept too, also when they were
+        // stored in an earlier batch.
+        let events = vec![
+            progress("one"),
+            metadata("main"),
+            progress("two"),
+            finished(),
+        ];
+        let mut s = session(&global);
+        send(&mut s, "by_metadata", &events, 1..=1).await.unwrap();
+        assert_eq!(s.flush().await.unwrap(), vec![1]);
+        assert!(stored("by_metadata").await.is_empty());
+        send(&mut s, "by_metadata", &events, 2..=2).await.unwrap();
+        assert_eq!(s.flush().await.unwrap(), vec![2]);
+        assert_eq!(stored("by_metadata").await, vec![1, 2]);
+
+        // A retry without the build metadata is stored as well.
+        crate::unexpected_cleanup_session(global.db_manager.clone(), "by_metadata")
+            .await
+            .unwrap();
+        let mut s = session(&global);
+        send(&mut s, "by_metadata", &events, 3..=4).await.unwrap();
+        assert_eq!(stored("by_metadata").await, vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_exit_code() {
+        proto_registry::init_global_descriptor_pool().unwrap();
+        let tmp = tempdir::TempDir::new("test_exit_code").unwrap();
+        let global = global(&tmp);
+        let failed = build_event_stream::BuildEvent {
+            payload: Some(build_event_stream::build_event::Payload::Finished(
+                build_event_stream::BuildFinished {
+                    exit_code: Some(build_event_stream::build_finished::ExitCode {
+                        name: "TESTS_FAILED".to_string(),
+                        code: 3,
+                    }),
+                    failure_detail: Some(failure_details::FailureDetail {
+                        message: "1 test failed".to_string(),
+                        category: Some(failure_details::failure_detail::Category::TestCommand(
+                            failure_details::TestCommand {
+                                code: failure_details::test_command::Code::TestsFailed.into(),
+                            },
+                        )),
+                    }),
+                    ..Default::default()
+                },
+            )),
+            last_message: true,
+            ..Default::default()
+        };
+
+        let mut s = session(&global);
+        send(&mut s, "failed", &[progress("one"), failed], 1..=2)
+            .await
+            .unwrap();
+        let mut s = session(&global);
+        send(&mut s, "passed", &events(), 1..=5).await.unwrap();
+
+        let get = |id: &'static str| {
+            db::run(global.db_manager.clone(), move |db| {
+                db.get_shallow_invocation(id)
+            })
+        };
+        let inv = get("failed").await.unwrap();
+        assert_eq!(inv.status, state::Status::Fail);
+        assert_eq!(
+            inv.exit_code,
+            Some(state::ExitCode {
+                name: "TESTS_FAILED".to_string(),
+                code: 3
+            })
+        );
+        assert_eq!(
+            inv.failure_detail,
+            Some(state::FailureDetail {
+                message: "1 test failed".to_string(),
+                category: "test_command".to_string(),
+                code: "TESTS_FAILED".to_string(),
+            })
+        );
+        let inv = get("passed").await.unwrap();
+        assert_eq!(inv.status, state::Status::Success);
+        assert_eq!(inv.exit_code.map(|e| e.name).as_deref(), Some("SUCCESS"));
+        assert_eq!(inv.failure_detail, None);
+    }
+
+    #[tokio::test]
+    async fn test_spool() {
+        let tmp = tempdir::TempDir::new("test_spool").unwrap();
+        let mgr = Arc::new(FlakyManager {
+            inner: sqlite(&tmp),
+            up: AtomicBool::new(false),
+        });
+        let global = global_with(mgr.clone(), Default::default());
+        let spool = Arc::new(Spool::open(&tmp.path().join("spool")).unwrap());
+        let handlers = Arc::new(crate::handlers(Arc::new(Mutex::new(
+            Regex::new("").unwrap(),
+        ))));
+        let events = events();
+
+        // The build is not blocked while the database is down.
+        let mut s = BESSession::new(handlers.clone(), global.clone(), None, Some(spool.clone()));
+        let acks = send(&mut s, "first", &events, 1..=5).await.unwrap();
+        assert_eq!(acks, vec![1, 2, 3, 4, 5]);
+        assert_eq!(spool.stats().depth, 1);
+        assert!(
+            crate::spool::replay_next(&spool, &global, &handlers)
+                .await
+                .is_err()
+        );
+        assert_eq!(spool.stats().depth, 1);
+
+        // Other builds don't wait for the spooled ones.
+        mgr.up.store(true, Ordering::SeqCst);
+        let mut s = BESSession::new(handlers.clone(), global.clone(), None, Some(spool.clone()));
+        let acks = send(&mut s, "second", &events, 1..=5).await.unwrap();
+        assert_eq!(acks, vec![1, 2, 3, 4, 5]);
+        assert_eq!(spool.stats().depth, 1);
+        let want = (state::Status::Success, "one\ntwo\nthree\nfour".to_string());
+        assert_eq!(stored(&global, "second").await, want);
+
+        while crate::spool::replay_next(&spool, &global, &handlers)
+            .await
+            .unwrap()
+        {}
+        assert_eq!(spool.stats().depth, 0);
+        assert_eq!(stored(&global, "first").await, want);
+    }
+
+    #[tokio::test]
+    async fn test_spool_reconnect() {
+        let tmp = tempdir::TempDir::new("test_spool_reconnect").unwrap();
+        let mgr = Arc::new(FlakyManager {
+            inner: sqlite(&tmp),
+            up: AtomicBool::new(true),
+        });
+        let global = global_with(mgr.clone(), Default::default());
+        let spool = Arc::new(Spool::open(&tmp.path().join("spool")).unwrap());
+        let handlers = Arc::new(crate::handlers(Arc::new(Mutex::new(
+            Regex::new("").unwrap(),
+        ))));
+        let events = events();
+
+        let mut s = BESSession::new(handlers.clone(), global.clone(), None, Some(spool.clone()));
+        send(&mut s, "retried", &events, 1..=2).await.unwrap();
+        assert_eq!(s.flush().await.unwrap(), vec![1, 2]);
+        mgr.up.store(false, Ordering::SeqCst);
+        send(&mut s, "retried", &events, 3..=3).await.unwrap();
+        assert_eq!(s.flush().await.unwrap(), vec![3]);
+        assert_eq!(spool.stats().depth, 1);
+
+        // The connection drops before the spool drained. The retry continues
+        // after the spooled events instead of after the stored ones.
+        mgr.up.store(true, Ordering::SeqCst);
+        crate::unexpected_cleanup_session(global.db_manager.clone(), "retried")
+            .await
+            .unwrap();
+        let mut s = BESSession::new(handlers.clone(), global.clone(), None, Some(spool.clone()));
+        let acks = send(&mut s, "retried", &events, 4..=5).await.unwrap();
+        assert_eq!(acks, vec![4, 5]);
+        assert_eq!(spool.stats().depth, 2);
+
+        while crate::spool::replay_next(&spool, &global, &handlers)
+            .await
+            .unwrap()
+        {}
+        assert_eq!(
+            stored(&global, "retried").await,
+            (state::Status::Success, "one\ntwo\nthree\nfour".to_string())
+        );
+    }
 }
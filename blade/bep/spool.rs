@@ -0,0 +1,539 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use anyhow::{Context, anyhow};
+use build_event_stream_proto::*;
+use lazy_static::lazy_static;
+use prometheus_client::metrics::{counter::Counter, gauge::Gauge};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    EventHandler,
+    session::{Batch, StreamInfo, apply},
+};
+
+// How long to wait before retrying while the database is unavailable.
+const REPLAY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+// A batch that keeps failing while the database is reachable is moved out of
+// the way so that it doesn't hold up everything behind it.
+const MAX_REPLAY_ATTEMPTS: u32 = 5;
+const FAILED_DIR: &str = "failed";
+
+lazy_static! {
+    static ref SPOOL_DEPTH: Gauge = metrics::register_metric(
+        "blade_bep_spool_depth",
+        "Number of event batches waiting in the spool",
+        Gauge::default()
+    );
+    static ref SPOOL_AGE: Gauge = metrics::register_metric(
+        "blade_bep_spool_age_seconds",
+        "Age of the oldest event batch waiting in the spool",
+        Gauge::default()
+    );
+    static ref SPOOLED_BATCHES: Counter::<u64> = metrics::register_metric(
+        "blade_bep_spooled_batches",
+        "Total number of event batches written to the spool",
+        Counter::default()
+    );
+    static ref SPOOL_FAILED_BATCHES: Counter::<u64> = metrics::register_metric(
+        "blade_bep_spool_failed_batches",
+        "Total number of spooled event batches that could not be replayed",
+        Counter::default()
+    );
+}
+
+// Everything about a batch except for the events themselves.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    invocation_id: String,
+    stream: String,
+    sequence_number: i64,
+    reopen: bool,
+    create: Option<SystemTime>,
+    #[serde(default)]
+    stream_info: Option<StreamInfo>,
+    #[serde(default)]
+    raw_events: Vec<state::RawEvent>,
+    sequences: Vec<i64>,
+}
+
+// A spooled batch is a varint length delimited JSON header followed by the
+// length delimited events.
+fn encode(batch: &Batch) -> anyhow::Result<Vec<u8>> {
+    let header = serde_json::to_vec(&Header {
+        invocation_id: batch.invocation_id.clone(),
+        stream: batch.stream.clone(),
+        sequence_number: batch.sequence_number,
+        reopen: batch.reopen,
+        create: batch.create,
+        stream_info: batch.stream_info.clone(),
+        raw_events: batch.raw_events.clone(),
+        sequences: batch.events.iter().map(|(seq, _)| *seq).collect(),
+    })?;
+    let mut buf = vec![];
+    prost::encoding::encode_varint(header.len() as u64, &mut buf);
+    buf.extend(header);
+    for (_, be) in &batch.events {
+        be.encode_length_delimited(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+fn decode_header(data: &mut &[u8]) -> anyhow::Result<Header> {
+    let len = prost::encoding::decode_varint(data).context("invalid header length")? as usize;
+    if len > data.len() {
+        return Err(anyhow!("truncated header"));
+    }
+    let header = serde_json::from_slice(&data[..len]).context("invalid header")?;
+    *data = &data[len..];
+    Ok(header)
+}
+
+fn read_header(path: &Path) -> anyhow::Result<Header> {
+    let data = std::fs::read(path)?;
+    decode_header(&mut &data[..])
+}
+
+// File names are zero padded counters.
+fn counter(path: &Path) -> Option<u64> { path.file_stem()?.to_str()?.parse().ok() }
+
+fn decode(mut data: &[u8]) -> anyhow::Result<Batch> {
+    let header = decode_header(&mut data)?;
+    let events = header
+        .sequences
+        .iter()
+        .map(|seq| {
+            build_event_stream::BuildEvent::decode_length_delimited(&mut data)
+                .map(|be| (*seq, be))
+                .with_context(|| format!("failed to decode event {seq}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Batch {
+        invocation_id: header.invocation_id,
+        stream: header.stream,
+        sequence_number: header.sequence_number,
+        reopen: header.reopen,
+        create: header.create,
+        stream_info: header.stream_info,
+        raw_events: header.raw_events,
+        events,
+    })
+}
+
+// Identifies a stream by its invocation id and stream key.
+type StreamKey = (String, String);
+
+#[derive(Debug)]
+struct Entry {
+    path: PathBuf,
+    created: SystemTime,
+    attempts: u32,
+    // Unknown if the header can't be read, in which case the batch is
+    // quarantined once it is replayed.
+    stream: Option<StreamKey>,
+    sequence_number: i64,
+}
+
+#[derive(Debug)]
+struct State {
+    next: u64,
+    entries: VecDeque<Entry>,
+    // Streams with a batch that was given up on. Their events were already
+    // acknowledged, so the live stream can't resend them.
+    failed: HashSet<StreamKey>,
+}
+
+/// The current size of the spool, as shown on the admin server.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpoolStats {
+    pub depth: usize,
+    pub oldest_age_seconds: u64,
+}
+
+/// A directory of event batches that could not be stored in the database.
+/// They are replayed in order once the database is available again, and
+/// survive restarts.
+#[derive(Debug)]
+pub struct Spool {
+    dir: PathBuf,
+    state: Arc<Mutex<State>>,
+}
+
+impl Spool {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create spool dir {}", dir.display()))?;
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(dir).context("failed to read spool dir")? {
+            let path = entry?.path();
+            match path.extension().and_then(|e| e.to_str()) {
+                Some("spool") => {
+                    let created = std::fs::metadata(&path)?.modified()?;
+                    let header = read_header(&path)
+                        .inspect_err(|e| {
+                            tracing::warn!("failed to read spooled batch {}: {e:#}", path.display())
+                        })
+                        .ok();
+                    entries.push(Entry {
+                        path,
+                        created,
+                        attempts: 0,
+                        stream: header
+                            .as_ref()
+                            .map(|h| (h.invocation_id.clone(), h.stream.clone())),
+                        sequence_number: header.map_or(0, |h| h.sequence_number),
+                    });
+                },
+                // Left behind by a crash while writing.
+                Some("tmp") => std::fs::remove_file(&path)?,
+                _ => {},
+            }
+        }
+        // Zero padded counters sort in spool order.
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut next = entries
+            .last()
+            .and_then(|e| counter(&e.path))
+            .map_or(0, |n| n + 1);
+        // Streams stay failed across restarts, so that their later batches
+        // aren't stored on top of the gap.
+        let mut failed = HashSet::new();
+        let failed_dir = dir.join(FAILED_DIR);
+        if failed_dir.exists() {
+            for entry in
+                std::fs::read_dir(&failed_dir).context("failed to read failed spool dir")?
+            {
+                let path = entry?.path();
+                match read_header(&path) {
+                    Ok(h) => {
+                        failed.insert((h.invocation_id, h.stream));
+                    },
+                    Err(e) => {
+                        tracing::warn!("failed to read failed batch {}: {e:#}", path.display())
+                    },
+                }
+                // New batches must not overwrite failed ones once they fail
+                // too.
+                next = next.max(counter(&path).map_or(0, |n| n + 1));
+            }
+        }
+        if !entries.is_empty() {
+            tracing::info!("Replaying {} spooled batches", entries.len());
+        }
+        let spool = Self {
+            dir: dir.to_path_buf(),
+            state: Arc::new(Mutex::new(State {
+                next,
+                entries: entries.into(),
+                failed,
+            })),
+        };
+        spool.stats();
+        Ok(spool)
+    }
+
+    /// Returns the spool depth and age and updates the metrics with them.
+    pub fn stats(&self) -> SpoolStats {
+        let state = self.state.lock().unwrap();
+        let stats = SpoolStats {
+            depth: state.entries.len(),
+            oldest_age_seconds: state
+                .entries
+                .front()
+                .and_then(|e| SystemTime::now().duration_since(e.created).ok())
+                .map_or(0, |d| d.as_secs()),
+        };
+        SPOOL_DEPTH.set(stats.depth as i64);
+        SPOOL_AGE.set(stats.oldest_age_seconds as i64);
+        stats
+    }
+
+    /// The last event of the stream that is waiting in the spool. It was
+    /// acknowledged, so a retry of the stream continues after it.
+    pub(crate) fn sequence_number(&self, invocation_id: &str, stream: &str) -> Option<i64> {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|e| {
+                e.stream
+                    .as_ref()
+                    .is_some_and(|(i, s)| i == invocation_id && s == stream)
+            })
+            .map(|e| e.sequence_number)
+            .max()
+    }
+
+    /// Whether batches of the stream are waiting in the spool. Later batches
+    /// of the stream have to be stored after them.
+    pub(crate) fn has_stream(&self, invocation_id: &str, stream: &str) -> bool {
+        self.sequence_number(invocation_id, stream).is_some()
+    }
+
+    /// Whether acknowledged events of the stream were given up on.
+    pub(crate) fn has_failed(&self, invocation_id: &str, stream: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .failed
+            .contains(&(invocation_id.to_string(), stream.to_string()))
+    }
+
+    pub(crate) async fn push(&self, batch: &Batch) -> anyhow::Result<()> {
+        let data = encode(batch)?;
+        // Only the file name is reserved under the lock, so that other
+        // streams don't wait for the write. A stream pushes one batch at a
+        // time, so its batches still end up in order.
+        let path = {
+            let mut state = self.state.lock().unwrap();
+            state.next += 1;
+            self.dir.join(format!("{:020}.spool", state.next - 1))
+        };
+        let entry = Entry {
+            path: path.clone(),
+            created: SystemTime::now(),
+            attempts: 0,
+            stream: Some((batch.invocation_id.clone(), batch.stream.clone())),
+            sequence_number: batch.sequence_number,
+        };
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            // Written to a temporary file first so that a crash never leaves
+            // a partial batch behind.
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, data)
+                .and_then(|_| std::fs::rename(&tmp, &path))
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            // Added here so that a written batch is replayed even if the
+            // stream goes away before the write finished.
+            state.lock().unwrap().entries.push_back(entry);
+            anyhow::Ok(())
+        })
+        .await??;
+        SPOOLED_BATCHES.inc();
+        self.stats();
+        Ok(())
+    }
+
+    fn front(&self) -> Option<PathBuf> {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .front()
+            .map(|e| e.path.clone())
+    }
+
+    async fn pop(&self) -> anyhow::Result<()> {
+        let entry = self.state.lock().unwrap().entries.pop_front();
+        if let Some(entry) = entry {
+            tokio::fs::remove_file(&entry.path)
+                .await
+                .with_context(|| format!("failed to remove {}", entry.path.display()))?;
+        }
+        self.stats();
+        Ok(())
+    }
+
+    // Moves the oldest batch to the failed directory, where it is kept for
+    // manual inspection. The stream is marked as failed so that the live
+    // stream learns about the lost events.
+    async fn quarantine(&self) -> anyhow::Result<()> {
+        let entry = {
+            let mut state = self.state.lock().unwrap();
+            let entry = state.entries.pop_front();
+            if let Some(stream) = entry.as_ref().and_then(|e| e.stream.clone()) {
+                state.failed.insert(stream);
+            }
+            entry
+        };
+        self.stats();
+        let Some(entry) = entry else {
+            return Ok(());
+        };
+        SPOOL_FAILED_BATCHES.inc();
+        let failed = self.dir.join(FAILED_DIR);
+        tokio::fs::create_dir_all(&failed).await?;
+        let dest = failed.join(entry.path.file_name().unwrap_or_default());
+        tracing::error!(
+            "Giving up on spooled batch, moving it to {}",
+            dest.display()
+        );
+        tokio::fs::rename(&entry.path, &dest)
+            .await
+            .with_context(|| format!("failed to move {}", entry.path.display()))
+    }
+
+    fn failed_attempt(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        state.entries.front_mut().map_or(0, |e| {
+            e.attempts += 1;
+            e.attempts
+        })
+    }
+}
+
+/// Stores the oldest spooled batch. Returns false if the spool is empty.
+pub(crate) async fn replay_next(
+    spool: &Spool,
+    global: &Arc<state::Global>,
+    handlers: &Arc<Vec<Box<dyn EventHandler + Sync + Send>>>,
+) -> anyhow::Result<bool> {
+    let Some(path) = spool.front() else {
+        return Ok(false);
+    };
+    let batch = match tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("failed to read {}", path.display()))
+        .and_then(|data| decode(&data))
+    {
+        Ok(batch) => batch,
+        Err(e) => {
+            spool.quarantine().await?;
+            return Err(e);
+        },
+    };
+    // Storing later events of the stream would leave a gap in it.
+    if spool.has_failed(&batch.invocation_id, &batch.stream) {
+        spool.quarantine().await?;
+        return Err(anyhow!(
+            "an earlier batch of stream {} of {} failed",
+            batch.stream,
+            batch.invocation_id
+        ));
+    }
+    // Attempts only count while the database is up.
+    db::run(global.db_manager.clone(), |_| Ok(()))
+        .await
+        .context("database is unavailable")?;
+    let handlers = handlers.clone();
+    let res = db::transaction(global.db_manager.clone(), move |db| {
+        apply(db, &handlers, &batch)
+    })
+    .await;
+    match res {
+        Ok(()) => spool.pop().await.map(|_| true),
+        Err(e) => {
+            if spool.failed_attempt() >= MAX_REPLAY_ATTEMPTS {
+                spool.quarantine().await?;
+            }
+            Err(e)
+        },
+    }
+}
+
+/// Replays spooled batches forever.
+pub(crate) async fn replay(
+    spool: Arc<Spool>,
+    global: Arc<state::Global>,
+    handlers: Arc<Vec<Box<dyn EventHandler + Sync + Send>>>,
+) {
+    loop {
+        match replay_next(&spool, &global, &handlers).await {
+            // Keep going while the database keeps up.
+            Ok(true) => continue,
+            Ok(false) => {},
+            Err(e) => tracing::warn!("failed to replay spooled events: {e:#}"),
+        }
+        // Keeps the age metric current.
+        spool.stats();
+        tokio::time::sleep(REPLAY_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(id: &str, sequences: &[i64]) -> Batch {
+        Batch {
+            invocation_id: id.to_string(),
+            stream: "build/tool".to_string(),
+            sequence_number: sequences.last().copied().unwrap_or_default() + 1,
+            reopen: true,
+            create: Some(SystemTime::UNIX_EPOCH),
+            stream_info: Some(StreamInfo {
+                project_id: "project".to_string(),
+                notification_keywords: vec!["ci".to_string()],
+            }),
+            raw_events: vec![state::RawEvent {
+                sequence: 1,
+                kind: "started".to_string(),
+                json: "{}".to_string(),
+            }],
+            events: sequences
+                .iter()
+                .map(|seq| {
+                    (
+                        *seq,
+                        build_event_stream::BuildEvent {
+                            last_message: *seq == 3,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let want = batch("inv", &[1, 2, 3]);
+        let got = decode(&encode(&want).unwrap()).unwrap();
+        assert_eq!(got.invocation_id, want.invocation_id);
+        assert_eq!(got.stream, want.stream);
+        assert_eq!(got.sequence_number, 4);
+        assert!(got.reopen);
+        assert_eq!(got.create, want.create);
+        assert_eq!(got.stream_info, want.stream_info);
+        assert_eq!(got.raw_events, want.raw_events);
+        assert_eq!(got.events, want.events);
+
+        let data = encode(&want).unwrap();
+        assert!(decode(&data[..data.len() - 1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spool() {
+        let tmp = tempdir::TempDir::new("test_spool").unwrap();
+        let spool = Spool::open(tmp.path()).unwrap();
+        assert_eq!(spool.stats().depth, 0);
+        for id in ["a", "b", "c"] {
+            spool.push(&batch(id, &[1])).await.unwrap();
+        }
+        spool.pop().await.unwrap();
+        assert_eq!(spool.stats().depth, 2);
+
+        // Batches survive a restart in order.
+        let spool = Spool::open(tmp.path()).unwrap();
+        let read = |spool: &Spool| decode(&std::fs::read(spool.front().unwrap()).unwrap()).unwrap();
+        assert_eq!(read(&spool).invocation_id, "b");
+        assert_eq!(spool.sequence_number("b", "build/tool"), Some(2));
+        assert_eq!(spool.sequence_number("b", "other"), None);
+        assert!(spool.has_stream("b", "build/tool"));
+        assert!(!spool.has_stream("a", "build/tool"));
+        assert!(!spool.has_failed("b", "build/tool"));
+        spool.quarantine().await.unwrap();
+        assert_eq!(read(&spool).invocation_id, "c");
+        assert!(tmp.path().join(FAILED_DIR).read_dir().unwrap().count() == 1);
+        assert_eq!(spool.sequence_number("b", "build/tool"), None);
+        assert!(spool.has_failed("b", "build/tool"));
+        assert!(!spool.has_failed("c", "build/tool"));
+
+        // So do failed streams.
+        let spool = Spool::open(tmp.path()).unwrap();
+        assert!(spool.has_failed("b", "build/tool"));
+        assert!(!spool.has_failed("c", "build/tool"));
+
+        // New batches go after the existing ones.
+        spool.push(&batch("d", &[1])).await.unwrap();
+        spool.pop().await.unwrap();
+        assert_eq!(read(&spool).invocation_id, "d");
+    }
+}
@@ -1,6 +1,5 @@
 use anyhow::Context;
 use build_event_stream_proto::build_event_stream;
-use state::DBManager;
 
 pub(crate) struct Handler {}
 
@@ -12,9 +11,11 @@ fn target_label(
     let label = match id {
         Some(build_event_stream::build_event_id::Id::TargetConfigured(t)) => &t.label,
         Some(build_event_stream::build_event_id::Id::UnconfiguredLabel(t)) => &t.label,
+        Some(build_event_stream::build_event_id::Id::ConfiguredLabel(t)) => &t.label,
         Some(build_event_stream::build_event_id::Id::TargetCompleted(t)) => &t.label,
         Some(build_event_stream::build_event_id::Id::TestSummary(t)) => &t.label,
         Some(build_event_stream::build_event_id::Id::TestResult(t)) => &t.label,
+        Some(build_event_stream::build_event_id::Id::TargetSummary(t)) => &t.label,
         _ => {
             return None;
         },
@@ -38,6 +39,7 @@ fn test_run_info(
                 files: Default::default(),
                 details: Default::default(),
                 status: state::Status::Unknown,
+                detailed_status: None,
             },
         ),
         _ => {
@@ -47,6 +49,35 @@ fn test_run_info(
     Some(label)
 }
 
+fn to_test_status(status: i32) -> anyhow::Result<state::TestStatus> {
+    Ok(match build_event_stream::TestStatus::try_from(status)? {
+        build_event_stream::TestStatus::NoStatus => state::TestStatus::NoStatus,
+        build_event_stream::TestStatus::Passed => state::TestStatus::Passed,
+        build_event_stream::TestStatus::Flaky => state::TestStatus::Flaky,
+        build_event_stream::TestStatus::Timeout => state::TestStatus::Timeout,
+        build_event_stream::TestStatus::Failed => state::TestStatus::Failed,
+        build_event_stream::TestStatus::Incomplete => state::TestStatus::Incomplete,
+        build_event_stream::TestStatus::RemoteFailure => state::TestStatus::RemoteFailure,
+        build_event_stream::TestStatus::FailedToBuild => state::TestStatus::FailedToBuild,
+        build_event_stream::TestStatus::ToolHaltedBeforeTesting => {
+            state::TestStatus::ToolHaltedBeforeTesting
+        },
+    })
+}
+
+// How the test attempt was executed, in spawn runner terms so it can be
+// classified alongside BuildMetrics runner counts.
+fn test_strategy(r: &build_event_stream::TestResult) -> Option<String> {
+    let exec = r.execution_info.as_ref();
+    if r.cached_locally {
+        Some("cached locally".to_string())
+    } else if exec.is_some_and(|e| e.cached_remotely) {
+        Some("remote cache hit".to_string())
+    } else {
+        exec.map(|e| e.strategy.clone()).filter(|s| !s.is_empty())
+    }
+}
+
 fn to_duration(
     start: Option<&::timestamp_proto::google::protobuf::Timestamp>,
     end: Option<&::timestamp_proto::google::protobuf::Timestamp>,
@@ -72,16 +103,71 @@ fn proto_to_rust_duration(
     d.and_then(convert).unwrap_or_default()
 }
 
+// Targets and tests are stored as soon as they are announced, so that the ones
+// the build never gets to are marked as incomplete once it finishes.
+fn announced_targets(event: &build_event_stream::BuildEvent) -> impl Iterator<Item = &str> {
+    event.children.iter().filter_map(|c| match &c.id {
+        Some(build_event_stream::build_event_id::Id::TargetConfigured(t))
+            if t.aspect.is_empty() =>
+        {
+            Some(t.label.as_str())
+        },
+        _ => None,
+    })
+}
+
+fn announced_tests(event: &build_event_stream::BuildEvent) -> impl Iterator<Item = &str> {
+    event.children.iter().filter_map(|c| match &c.id {
+        Some(build_event_stream::build_event_id::Id::TestSummary(t)) => Some(t.label.as_str()),
+        _ => None,
+    })
+}
+
+// A failed target announces what made it fail as children: failed actions,
+// missing files or broken labels.
+fn root_causes(event: &build_event_stream::BuildEvent, label: &str) -> Vec<String> {
+    let mut causes: Vec<String> = vec![];
+    for c in &event.children {
+        let cause = match &c.id {
+            Some(build_event_stream::build_event_id::Id::ActionCompleted(a)) => &a.label,
+            Some(build_event_stream::build_event_id::Id::ConfiguredLabel(l)) => &l.label,
+            Some(build_event_stream::build_event_id::Id::UnconfiguredLabel(l)) => &l.label,
+            _ => continue,
+        };
+        if !cause.is_empty() && cause != label && !causes.contains(cause) {
+            causes.push(cause.clone());
+        }
+    }
+    causes
+}
+
 impl crate::EventHandler for Handler {
     fn handle_event(
         &self,
-        db_mgr: &dyn DBManager,
+        db: &mut dyn state::DB,
         invocation_id: &str,
         event: &build_event_stream::BuildEvent,
     ) -> anyhow::Result<()> {
         match event.payload.as_ref() {
+            Some(build_event_stream::build_event::Payload::Expanded(_)) => {
+                // Patterns are expanded before any of their targets is
+                // configured.
+                for label in announced_targets(event) {
+                    db.upsert_target(
+                        invocation_id,
+                        &state::Target {
+                            name: label.to_string(),
+                            status: state::Status::InProgress,
+                            kind: String::new(),
+                            start: std::time::SystemTime::now(),
+                            end: None,
+                            failure: None,
+                        },
+                    )
+                    .context(format!("failed to insert announced target: {label}"))?;
+                }
+            },
             Some(build_event_stream::build_event::Payload::Configured(target)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let label =
                     target_label(event).ok_or(anyhow::anyhow!("target not found: {event:#?}"))?;
                 db.upsert_target(
@@ -92,12 +178,30 @@ impl crate::EventHandler for Handler {
                         kind: target.target_kind.to_string(),
                         start: std::time::SystemTime::now(),
                         end: None,
+                        failure: None,
                     },
                 )
                 .context(format!("failed to insert target:{label}"))?;
+                for label in announced_tests(event) {
+                    if db.get_test(invocation_id, label).is_ok() {
+                        continue;
+                    }
+                    db.upsert_test(
+                        invocation_id,
+                        &state::Test {
+                            name: label.to_string(),
+                            status: state::Status::InProgress,
+                            detailed_status: None,
+                            duration: Default::default(),
+                            end: std::time::SystemTime::now(),
+                            num_runs: 0,
+                            runs: vec![],
+                        },
+                    )
+                    .context(format!("failed to insert announced test: {label}"))?;
+                }
             },
             Some(build_event_stream::build_event::Payload::Completed(t)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let label = target_label(event).ok_or(anyhow::anyhow!("target not found"))?;
                 db.update_target_result(
                     invocation_id,
@@ -110,21 +214,36 @@ impl crate::EventHandler for Handler {
                     std::time::SystemTime::now(),
                 )
                 .context(format!("failed to update target result: {label}"))?;
+                if !t.success {
+                    let detail = t
+                        .failure_detail
+                        .as_ref()
+                        .map(crate::session::failure_detail)
+                        .unwrap_or_default();
+                    let failure = state::TargetFailure {
+                        message: detail.message,
+                        category: detail.category,
+                        root_causes: root_causes(event, &label),
+                    };
+                    db.update_target_failure(invocation_id, &label, &failure)
+                        .context(format!("failed to update target failure: {label}"))?;
+                }
             },
             Some(build_event_stream::build_event::Payload::Aborted(a)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let label =
                     target_label(event).ok_or(anyhow::anyhow!("target not found: {event:#?}"))?;
+                let reason = build_event_stream::aborted::AbortReason::try_from(a.reason);
+                let status = match reason {
+                    Ok(
+                        build_event_stream::aborted::AbortReason::Skipped
+                        | build_event_stream::aborted::AbortReason::UserInterrupted,
+                    ) => state::Status::Skip,
+                    _ => state::Status::Fail,
+                };
                 db.update_target_result(
                     invocation_id,
                     &label,
-                    match build_event_stream::aborted::AbortReason::try_from(a.reason) {
-                        Ok(
-                            build_event_stream::aborted::AbortReason::Skipped
-                            | build_event_stream::aborted::AbortReason::UserInterrupted,
-                        ) => state::Status::Skip,
-                        _ => state::Status::Fail,
-                    },
+                    status,
                     std::time::SystemTime::now(),
                 )
                 .or_else(|_| {
@@ -137,22 +256,31 @@ impl crate::EventHandler for Handler {
                             kind: "unknown".to_string(),
                             start: std::time::SystemTime::now(),
                             end: Some(std::time::SystemTime::now()),
+                            failure: None,
                         },
                     )
                 })
                 .context(format!("failed to update target result: {label}"))?;
+                if status == state::Status::Fail {
+                    let failure = state::TargetFailure {
+                        message: a.description.clone(),
+                        category: reason
+                            .map(|r| r.as_str_name().to_ascii_lowercase())
+                            .unwrap_or_default(),
+                        root_causes: root_causes(event, &label),
+                    };
+                    db.update_target_failure(invocation_id, &label, &failure)
+                        .context(format!("failed to update target failure: {label}"))?;
+                }
             },
             Some(build_event_stream::build_event::Payload::TestSummary(summary)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let label =
                     target_label(event).ok_or(anyhow::anyhow!("target not found: {event:#?}"))?;
+                let detailed_status = to_test_status(summary.overall_status)?;
                 let test = state::Test {
                     name: label.clone(),
-                    status: match build_event_stream::TestStatus::try_from(summary.overall_status)?
-                    {
-                        build_event_stream::TestStatus::Passed => state::Status::Success,
-                        _ => state::Status::Fail,
-                    },
+                    status: detailed_status.status(),
+                    detailed_status: Some(detailed_status),
                     duration: to_duration(
                         summary.first_start_time.as_ref(),
                         summary.last_stop_time.as_ref(),
@@ -164,8 +292,23 @@ impl crate::EventHandler for Handler {
                 db.upsert_test(invocation_id, &test)
                     .context(format!("failed to insert test: {label}"))?;
             },
+            Some(build_event_stream::build_event::Payload::TargetSummary(summary)) => {
+                // Only needed for tests that never got a TestSummary.
+                let label =
+                    target_label(event).ok_or(anyhow::anyhow!("target not found: {event:#?}"))?;
+                let detailed_status = to_test_status(summary.overall_test_status)?;
+                if let Ok(mut test) = db.get_test(invocation_id, &label)
+                    && test.status == state::Status::InProgress
+                    && detailed_status != state::TestStatus::NoStatus
+                {
+                    test.status = detailed_status.status();
+                    test.detailed_status = Some(detailed_status);
+                    test.end = std::time::SystemTime::now();
+                    db.upsert_test(invocation_id, &test)
+                        .context(format!("failed to update test: {label}"))?;
+                }
+            },
             Some(build_event_stream::build_event::Payload::TestResult(r)) => {
-                let mut db = db_mgr.get().context("failed to get db handle")?;
                 let mut info =
                     test_run_info(event).ok_or(anyhow::anyhow!("failed to find test id"))?;
                 let mut test =
@@ -177,6 +320,7 @@ impl crate::EventHandler for Handler {
                             runs: vec![],
                             end: std::time::SystemTime::now(),
                             status: state::Status::InProgress,
+                            detailed_status: None,
                         });
                 r.test_action_output.iter().for_each(|f| {
                     if let Some(build_event_stream_proto::build_event_stream::file::File::Uri(
@@ -193,10 +337,9 @@ impl crate::EventHandler for Handler {
                     }
                 });
                 info.1.duration = proto_to_rust_duration(r.test_attempt_duration.as_ref());
-                info.1.status = match build_event_stream::TestStatus::try_from(r.status)? {
-                    build_event_stream::TestStatus::Passed => state::Status::Success,
-                    _ => state::Status::Fail,
-                };
+                let detailed_status = to_test_status(r.status)?;
+                info.1.status = detailed_status.status();
+                info.1.detailed_status = Some(detailed_status);
                 info.1.details = r.status_details.clone();
 
                 test.num_runs = std::cmp::max(test.num_runs, info.1.run as usize);
@@ -205,6 +348,14 @@ impl crate::EventHandler for Handler {
                     .context(format!("failed to update test: {}", info.0))?;
                 db.upsert_test_run(invocation_id, &test_id, &info.1)
                     .context(format!("error inserting test run: {}", info.0))?;
+                if let Some(strategy) = test_strategy(r) {
+                    let key = format!(
+                        "{}|{}|{}|{}",
+                        info.0, info.1.run, info.1.shard, info.1.attempt
+                    );
+                    db.insert_action_strategy(invocation_id, &key, "TestRunner", &strategy)
+                        .context(format!("error inserting test strategy: {}", info.0))?;
+                }
             },
             _ => {},
         }
@@ -0,0 +1,94 @@
+use leptos::{either::Either, prelude::*};
+
+use crate::{accordion::*, shellout::ShellOut};
+
+#[allow(non_snake_case)]
+#[component]
+fn ActionLog(name: &'static str, uri: String) -> impl IntoView {
+    let log = LocalResource::new(move || {
+        let uri = uri.clone();
+        async move {
+            match shared::get_artifact(uri).await {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+                Err(e) => format!("failed to fetch {name}: {e:#?}"),
+            }
+        }
+    });
+    view! {
+        <div class="mt-2">
+            <h4 class="font-semibold text-sm">{name}</h4>
+            <Suspense fallback=move || view! { <div>Loading...</div> }>
+                {move || {
+                    log.get()
+                        .map(|text| {
+                            view! {
+                                <div class="max-h-96 overflow-auto">
+                                    <ShellOut text=text />
+                                </div>
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn ActionFailureList(failures: Vec<state::ActionFailure>) -> impl IntoView {
+    view! {
+        <Accordion>
+            {failures
+                .into_iter()
+                .map(|f| {
+                    let header = format!("{} {} (exit code {})", f.mnemonic, f.label, f.exit_code);
+                    view! {
+                        <AccordionItem
+                            header=move || view! { <h3 class="text-red-500">{header.clone()}</h3> }
+                            hide=true
+                        >
+                            <div class="p-2 text-sm">
+                                {f
+                                    .primary_output
+                                    .clone()
+                                    .map(|o| {
+                                        view! {
+                                            <div>
+                                                <span class="font-semibold">"Output: "</span>
+                                                <span class="font-mono break-all">{o}</span>
+                                            </div>
+                                        }
+                                    })}
+                                <details class="mt-2">
+                                    <summary class="cursor-pointer font-semibold">
+                                        "Command Line"
+                                    </summary>
+                                    <pre class="whitespace-pre-wrap break-all font-mono text-xs p-2 bg-gray-100 dark:bg-gray-900 rounded-md">
+                                        {f.command_line.join(" \\\n    ")}
+                                    </pre>
+                                </details>
+                                {match (f.stderr_uri.clone(), f.stdout_uri.clone()) {
+                                    (None, None) => {
+                                        Either::Left(
+                                            view! {
+                                                <div class="mt-2 text-gray-400">"No logs captured."</div>
+                                            },
+                                        )
+                                    }
+                                    (stderr, stdout) => {
+                                        Either::Right(
+                                            view! {
+                                                {stderr.map(|uri| view! { <ActionLog name="stderr" uri /> })}
+                                                {stdout.map(|uri| view! { <ActionLog name="stdout" uri /> })}
+                                            },
+                                        )
+                                    }
+                                }}
+                            </div>
+                        </AccordionItem>
+                    }
+                })
+                .collect_view()}
+        </Accordion>
+    }
+}
@@ -0,0 +1,173 @@
+use leptos::prelude::*;
+use state::BuildMetrics;
+
+use crate::card::Card;
+
+pub fn format_bytes(b: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut v = b as f64;
+    let mut unit = 0;
+    while v.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{b} B")
+    } else {
+        format!("{v:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn format_ms(ms: i64) -> String {
+    humantime::format_duration(std::time::Duration::from_millis(ms.max(0) as u64)).to_string()
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn MetricGroup(title: &'static str, items: Vec<(&'static str, String)>) -> impl IntoView {
+    view! {
+        <Card class="p-4 m-2 max-w-full">
+            <h3 class="font-semibold text-lg mb-2">{title}</h3>
+            <dl class="grid grid-cols-2 gap-x-4 gap-y-1 text-sm">
+                {items
+                    .into_iter()
+                    .map(|(k, v)| {
+                        view! {
+                            <dt class="text-gray-500 dark:text-gray-400">{k}</dt>
+                            <dd class="text-right">{v}</dd>
+                        }
+                    })
+                    .collect_view()}
+            </dl>
+        </Card>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn BuildMetricsView(metrics: BuildMetrics) -> impl IntoView {
+    let m = &metrics;
+    let hit_rate = m
+        .action_cache_hit_rate()
+        .map(|r| format!("{:.1}%", r * 100.0))
+        .unwrap_or_else(|| "N/A".to_string());
+    let files = |bytes: i64, count: i64| format!("{count} ({})", format_bytes(bytes));
+    let actions = vec![
+        ("Created", m.actions_created.to_string()),
+        ("Executed", m.actions_executed.to_string()),
+        ("Action Cache Hits", m.action_cache_hits.to_string()),
+        ("Action Cache Misses", m.action_cache_misses.to_string()),
+        ("Action Cache Hit Rate", hit_rate),
+    ];
+    let timing = vec![
+        ("Wall Time", format_ms(m.wall_time_ms)),
+        ("CPU Time", format_ms(m.cpu_time_ms)),
+        ("Analysis Phase", format_ms(m.analysis_phase_time_ms)),
+        ("Execution Phase", format_ms(m.execution_phase_time_ms)),
+    ];
+    let graph = vec![
+        ("Packages Loaded", m.packages_loaded.to_string()),
+        ("Targets Loaded", m.targets_loaded.to_string()),
+        ("Targets Configured", m.targets_configured.to_string()),
+        (
+            "Used Heap Post Build",
+            format_bytes(m.used_heap_size_post_build),
+        ),
+        ("Peak Post GC Heap", format_bytes(m.peak_post_gc_heap_size)),
+    ];
+    let artifacts = vec![
+        (
+            "Source Artifacts Read",
+            files(m.source_artifacts_read_bytes, m.source_artifacts_read_count),
+        ),
+        (
+            "Output Artifacts Seen",
+            files(m.output_artifacts_seen_bytes, m.output_artifacts_seen_count),
+        ),
+        (
+            "From Action Cache",
+            files(
+                m.output_artifacts_from_action_cache_bytes,
+                m.output_artifacts_from_action_cache_count,
+            ),
+        ),
+        (
+            "Top Level Artifacts",
+            files(m.top_level_artifacts_bytes, m.top_level_artifacts_count),
+        ),
+    ];
+
+    view! {
+        <div class="flex flex-col">
+            <div class="grid grid-cols-1 md:grid-cols-2 xl:grid-cols-4">
+                <MetricGroup title="Actions" items=actions />
+                <MetricGroup title="Timing" items=timing />
+                <MetricGroup title="Loading & Memory" items=graph />
+                <MetricGroup title="Artifacts" items=artifacts />
+            </div>
+            <div class="grid grid-cols-1 xl:grid-cols-2">
+                <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                    <h3 class="font-semibold text-lg mb-2">"Actions by Mnemonic"</h3>
+                    <table class="min-w-full text-sm">
+                        <thead>
+                            <tr class="text-left text-gray-500 dark:text-gray-400">
+                                <th class="py-1 pr-4">"Mnemonic"</th>
+                                <th class="py-1 pr-4 text-right">"Executed"</th>
+                                <th class="py-1 pr-4 text-right">"User Time"</th>
+                                <th class="py-1 text-right">"System Time"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {metrics
+                                .actions
+                                .iter()
+                                .map(|a| {
+                                    view! {
+                                        <tr class="border-t border-gray-200 dark:border-gray-600">
+                                            <td class="py-1 pr-4">{a.mnemonic.clone()}</td>
+                                            <td class="py-1 pr-4 text-right">{a.actions_executed}</td>
+                                            <td class="py-1 pr-4 text-right">
+                                                {humantime::format_duration(a.user_time).to_string()}
+                                            </td>
+                                            <td class="py-1 text-right">
+                                                {humantime::format_duration(a.system_time)
+                                                    .to_string()}
+                                            </td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()}
+                        </tbody>
+                    </table>
+                </Card>
+                <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                    <h3 class="font-semibold text-lg mb-2">"Spawn Runners"</h3>
+                    <table class="min-w-full text-sm">
+                        <thead>
+                            <tr class="text-left text-gray-500 dark:text-gray-400">
+                                <th class="py-1 pr-4">"Runner"</th>
+                                <th class="py-1 pr-4">"Kind"</th>
+                                <th class="py-1 text-right">"Count"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {metrics
+                                .runners
+                                .iter()
+                                .map(|r| {
+                                    view! {
+                                        <tr class="border-t border-gray-200 dark:border-gray-600">
+                                            <td class="py-1 pr-4">{r.name.clone()}</td>
+                                            <td class="py-1 pr-4">{r.exec_kind.clone()}</td>
+                                            <td class="py-1 text-right">{r.count}</td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()}
+                        </tbody>
+                    </table>
+                </Card>
+            </div>
+        </div>
+    }
+}
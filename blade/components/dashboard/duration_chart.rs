@@ -43,8 +43,10 @@ pub fn DurationChart(history: TestHistory) -> impl IntoView {
             y_accessor=|point| point.test.duration.as_secs_f64()
             line_color="#4299e1"
             point_color_accessor=|p| {
-                (match p.test.status {
-                    state::Status::Success => "#48bb78",
+                (match (p.test.status, p.test.detailed_status) {
+                    (_, Some(state::TestStatus::Flaky)) => "#ecc94b",
+                    (state::Status::Success, _) => "#48bb78",
+                    (state::Status::Incomplete, _) => "#a0aec0",
                     _ => "#f56565",
                 })
                     .to_string()
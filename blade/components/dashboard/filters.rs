@@ -4,7 +4,7 @@ use time::{Date, Time};
 use wasm_bindgen::JsCast;
 
 // Helper function to parse ISO date string (YYYY-MM-DD) to SystemTime
-fn parse_date_string(date_str: &str) -> Option<std::time::SystemTime> {
+pub(crate) fn parse_date_string(date_str: &str) -> Option<std::time::SystemTime> {
     if date_str.is_empty() {
         return None;
     }
@@ -27,7 +27,7 @@ fn parse_date_string(date_str: &str) -> Option<std::time::SystemTime> {
 #[derive(Clone, Debug)]
 struct FilterBuilder {
     filter_type: String, /* "Duration", "Status", "Metadata", "BazelFlags", "LogOutput",
-                          * "DateRange" */
+                          * "DateRange", "ProjectId", "NotificationKeyword", "ToolTag" */
     operation: TestFilterOp,
     invert: bool,
     // Values for different filter types
@@ -38,6 +38,8 @@ struct FilterBuilder {
     bazel_flag: String,
     bazel_value: String,
     log_output: String,
+    // Project ID, notification keyword or tool tag of the invocation
+    invocation_value: String,
     // Date range fields
     date_from: String, // ISO date string (YYYY-MM-DD)
     date_to: String,   // ISO date string (YYYY-MM-DD)
@@ -56,6 +58,7 @@ impl Default for FilterBuilder {
             bazel_flag: String::new(),
             bazel_value: String::new(),
             log_output: String::new(),
+            invocation_value: String::new(),
             date_from: String::new(),
             date_to: String::new(),
         }
@@ -102,6 +105,17 @@ impl FilterBuilder {
                 let to = parse_date_string(&self.date_to)?;
                 TestFilterItem::DateRange { from, to }
             },
+            "ProjectId" | "NotificationKeyword" | "ToolTag" => {
+                let value = self.invocation_value.trim().to_string();
+                if value.is_empty() {
+                    return None;
+                }
+                match self.filter_type.as_str() {
+                    "ProjectId" => TestFilterItem::ProjectId(value),
+                    "NotificationKeyword" => TestFilterItem::NotificationKeyword(value),
+                    _ => TestFilterItem::ToolTag(value),
+                }
+            },
             _ => return None,
         };
 
@@ -240,6 +254,9 @@ fn FilterRow(
                         <option value="BazelFlags">"Bazel Flags"</option>
                         <option value="LogOutput">"Log Output"</option>
                         <option value="DateRange">"Date Range"</option>
+                        <option value="ProjectId">"Project"</option>
+                        <option value="NotificationKeyword">"Keyword"</option>
+                        <option value="ToolTag">"Tool Tag"</option>
                     </select>
                 </div>
 
@@ -284,7 +301,12 @@ fn FilterRow(
                                 "Status" => {
                                     view! { <option value="Equals">"Equals"</option> }.into_any()
                                 }
-                                "Metadata" | "BazelFlags" | "LogOutput" => {
+                                "Metadata"
+                                | "BazelFlags"
+                                | "LogOutput"
+                                | "ProjectId"
+                                | "NotificationKeyword"
+                                | "ToolTag" => {
                                     view! {
                                         <option value="Equals">"Equals"</option>
                                         <option value="Contains">"Contains"</option>
@@ -341,6 +363,7 @@ fn FilterRow(
                                         <option value="Success">"Success"</option>
                                         <option value="Fail">"Fail"</option>
                                         <option value="Skip">"Skip"</option>
+                                        <option value="Incomplete">"Incomplete"</option>
                                         <option value="InProgress">"In Progress"</option>
                                         <option value="Unknown">"Unknown"</option>
                                     </select>
@@ -402,6 +425,21 @@ fn FilterRow(
                                 }
                                     .into_any()
                             }
+                            "ProjectId" | "NotificationKeyword" | "ToolTag" => {
+                                view! {
+                                    <input
+                                        type="text"
+                                        placeholder="Value"
+                                        class="w-full p-2 bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-500 dark:text-white"
+                                        on:input=move |ev| {
+                                            let value = event_target_value(&ev);
+                                            set_current_builder.update(|b| b.invocation_value = value);
+                                        }
+                                        prop:value=move || current_builder.get().invocation_value
+                                    />
+                                }
+                                    .into_any()
+                            }
                             "LogOutput" => {
                                 view! {
                                     <input
@@ -0,0 +1,88 @@
+use leptos::prelude::*;
+use state::FlakyTest;
+
+use crate::summaryheader::format_time;
+
+#[allow(non_snake_case)]
+#[component]
+pub fn FlakyTestsTable(tests: Vec<FlakyTest>) -> impl IntoView {
+    if tests.is_empty() {
+        return view! {
+            <p class="text-gray-500 mt-8 text-center">"No flaky tests found in this window."</p>
+        }
+        .into_any();
+    }
+    view! {
+        <div class="overflow-x-auto">
+            <table class="min-w-full bg-white dark:bg-gray-700 rounded-lg shadow-md">
+                <thead>
+                    <tr class="bg-gray-100 dark:bg-gray-700 text-gray-600 dark:text-gray-300 uppercase text-sm leading-normal">
+                        <th class="py-3 px-6 text-left">"Test"</th>
+                        <th class="py-3 px-6 text-left">"Flake Rate"</th>
+                        <th class="py-3 px-6 text-left">"Flaky / Total"</th>
+                        <th class="py-3 px-6 text-left">"Last Flaky Invocation"</th>
+                    </tr>
+                </thead>
+                <tbody class="text-gray-700 dark:text-gray-300 text-sm font-light">
+                    {tests
+                        .into_iter()
+                        .map(|t| {
+                            let history = format!(
+                                "/dashboard?test_name={}",
+                                url_escape::encode_component(&t.name),
+                            );
+                            let rate = t.flake_rate() * 100.0;
+                            view! {
+                                <tr class="border-b border-gray-200 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-600">
+                                    <td class="py-3 px-6 text-left break-all">
+                                        <a
+                                            class="text-blue-600 dark:text-blue-400 hover:underline"
+                                            href=history
+                                        >
+                                            {t.name.clone()}
+                                        </a>
+                                    </td>
+                                    <td class="py-3 px-6 text-left whitespace-nowrap">
+                                        <div class="flex items-center">
+                                            <div class="w-24 h-2 mr-2 bg-gray-200 dark:bg-gray-600 rounded-full">
+                                                <div
+                                                    class="h-2 bg-yellow-400 rounded-full"
+                                                    style=format!("width: {rate:.0}%")
+                                                ></div>
+                                            </div>
+                                            {format!("{rate:.1}%")}
+                                        </div>
+                                    </td>
+                                    <td class="py-3 px-6 text-left">
+                                        {format!("{} / {}", t.flaky_invocations, t.total_invocations)}
+                                    </td>
+                                    <td class="py-3 px-6 text-left whitespace-nowrap">
+                                        {t
+                                            .last_flaky_invocation
+                                            .clone()
+                                            .map(|id| {
+                                                let label = t
+                                                    .last_flaky_start
+                                                    .as_ref()
+                                                    .map(format_time)
+                                                    .unwrap_or_else(|| id.clone());
+                                                view! {
+                                                    <a
+                                                        class="text-blue-600 dark:text-blue-400 hover:underline"
+                                                        href=format!("/invocation/{id}")
+                                                    >
+                                                        {label}
+                                                    </a>
+                                                }
+                                            })}
+                                    </td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()}
+                </tbody>
+            </table>
+        </div>
+    }
+    .into_any()
+}
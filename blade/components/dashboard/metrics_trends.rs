@@ -0,0 +1,121 @@
+use leptos::{either::Either, prelude::*};
+use state::BuildMetricsPoint;
+
+use crate::{
+    buildmetrics::{format_bytes, format_ms},
+    charts::linechart::LineChart,
+    dashboard::duration_chart::format_unix,
+    navigation::open_in_new_tab,
+    summaryheader::format_time,
+};
+
+fn start_secs(p: &BuildMetricsPoint) -> f64 {
+    p.start
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+fn open_invocation(p: BuildMetricsPoint) {
+    open_in_new_tab(&format!("/invocation/{}/metrics", p.invocation_id));
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn TrendChart<Y, TC>(
+    title: &'static str,
+    data: Vec<BuildMetricsPoint>,
+    y_accessor: Y,
+    tooltip: TC,
+    y_axis_label: &'static str,
+    line_color: &'static str,
+) -> impl IntoView
+where
+    Y: Fn(&BuildMetricsPoint) -> f64 + Copy + 'static + Send,
+    TC: Fn(&BuildMetricsPoint) -> String + Copy + 'static + Send,
+{
+    view! {
+        <div class="bg-white dark:bg-gray-700 p-6 rounded-lg shadow-lg">
+            <h2 class="text-xl font-semibold mb-4">{title}</h2>
+            <LineChart
+                data=data
+                x_accessor=start_secs
+                y_accessor=y_accessor
+                line_color=line_color
+                point_color_accessor=move |_| line_color.to_string()
+                tooltip_content_accessor=move |p| {
+                    format!(
+                        "Invocation: {}\n{}\nDate: {}",
+                        p.invocation_id.chars().take(8).collect::<String>(),
+                        tooltip(p),
+                        format_time(&p.start),
+                    )
+                }
+                x_tick_formatter=Box::new(format_unix)
+                on_point_click=open_invocation
+                x_axis_label="Time"
+                y_axis_label=y_axis_label
+                x_axis_label_rotation=10.0
+            />
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn MetricsTrends(history: Vec<BuildMetricsPoint>) -> impl IntoView {
+    if history.is_empty() {
+        return Either::Left(view! {
+            <p class="text-gray-500 mt-8 text-center">"No build metrics in this window."</p>
+        });
+    }
+    // Builds that never consulted the action cache would otherwise show up as
+    // a 0% hit rate.
+    let cached = history
+        .iter()
+        .filter(|p| p.metrics.action_cache_hit_rate().is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Either::Right(view! {
+        <div class="grid grid-cols-1 lg:grid-cols-2 gap-8 mt-8">
+            <TrendChart
+                title="Action Cache Hit Rate (%)"
+                data=cached
+                y_accessor=|p| p.metrics.action_cache_hit_rate().unwrap_or_default() * 100.0
+                tooltip=|p| {
+                    format!(
+                        "Hit rate: {:.1}%",
+                        p.metrics.action_cache_hit_rate().unwrap_or_default() * 100.0,
+                    )
+                }
+                y_axis_label="Hit Rate (%)"
+                line_color="#48bb78"
+            />
+            <TrendChart
+                title="Wall Time (s)"
+                data=history.clone()
+                y_accessor=|p| p.metrics.wall_time_ms as f64 / 1000.0
+                tooltip=|p| format!("Wall time: {}", format_ms(p.metrics.wall_time_ms))
+                y_axis_label="Wall Time (s)"
+                line_color="#4299e1"
+            />
+            <TrendChart
+                title="Actions Executed"
+                data=history.clone()
+                y_accessor=|p| p.metrics.actions_executed as f64
+                tooltip=|p| format!("Actions executed: {}", p.metrics.actions_executed)
+                y_axis_label="Actions"
+                line_color="#ed8936"
+            />
+            <TrendChart
+                title="Peak Post GC Heap (MiB)"
+                data=history
+                y_accessor=|p| p.metrics.peak_post_gc_heap_size as f64 / (1024.0 * 1024.0)
+                tooltip=|p| format!("Peak heap: {}", format_bytes(p.metrics.peak_post_gc_heap_size))
+                y_axis_label="Heap (MiB)"
+                line_color="#9f7aea"
+            />
+        </div>
+    })
+}
@@ -1,6 +1,8 @@
 pub mod duration_chart;
 pub mod filters;
+pub mod flaky_tests_table;
 pub mod graphs;
+pub mod metrics_trends;
 pub mod pass_fail_chart;
 pub mod test_history_table;
 pub mod test_search;
@@ -1,29 +1,45 @@
 use leptos::prelude::*;
-use state::{Status, TestHistory};
+use state::{Status, TestHistory, TestStatus};
 
 use crate::charts::piechart::PieChart;
 // use chrono::prelude::*;
 
+// Buckets a history point into a chart slice label and color, keeping flakes
+// and timeouts apart from plain passes and failures.
+fn bucket(status: Status, detailed_status: Option<TestStatus>) -> (&'static str, &'static str) {
+    match (status, detailed_status) {
+        (_, Some(TestStatus::Flaky)) => ("Flaky", "#ecc94b"),
+        (_, Some(TestStatus::Timeout)) => ("Timeout", "#ed8936"),
+        (Status::Success, _) => ("Pass", "#48bb78"),
+        (Status::Fail, _) => ("Fail", "#f56565"),
+        (Status::Incomplete, _) => ("Incomplete", "#718096"),
+        _ => ("Other", "#a0aec0"),
+    }
+}
+
 #[allow(non_snake_case)]
 #[component]
 pub fn PassFailChart(history: TestHistory) -> impl IntoView {
-    let (pass, fail): (Vec<_>, Vec<_>) = history
-        .history
-        .into_iter()
-        .partition(|p| matches!(p.test.status, Status::Success));
-    let passed = pass.len();
-    let failed = fail.len();
+    let mut data: Vec<(&'static str, &'static str, usize)> = vec![];
+    history.history.iter().for_each(|p| {
+        let (label, color) = bucket(p.test.status, p.test.detailed_status);
+        match data.iter_mut().find(|d| d.0 == label) {
+            Some(d) => d.2 += 1,
+            None => data.push((label, color, 1)),
+        }
+    });
+    data.sort_by_key(|d| d.0);
 
     view! {
         <PieChart
-            data=vec![(true, passed), (false, failed)]
+            data=data
             size=200
             inner_radius_ratio=0.75
-            value_accessor=|v| v.1 as f64
-            label_accessor=|v| (if v.0 { "Pass" } else { "Fail" }).to_string()
-            color_accessor=|v| (if v.0 { "#48bb78" } else { "#f56565" }).to_string()
+            value_accessor=|v| v.2 as f64
+            label_accessor=|v| v.0.to_string()
+            color_accessor=|v| v.1.to_string()
             tooltip_content_accessor=|v| {
-                format!("{} {}", v.1, if v.1 == 1 { "run" } else { "runs" })
+                format!("{} {}", v.2, if v.2 == 1 { "run" } else { "runs" })
             }
         />
     }
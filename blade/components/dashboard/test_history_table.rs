@@ -1,7 +1,7 @@
 use leptos::prelude::*;
 use state::{Status, TestHistory};
 
-use crate::{navigation::open_in_new_tab, summaryheader::format_time};
+use crate::{navigation::open_in_new_tab, statusicon::TestStatusBadge, summaryheader::format_time};
 
 #[derive(Debug, Clone)]
 struct RuntimeStats {
@@ -162,6 +162,7 @@ pub fn TestHistoryTable(history: TestHistory) -> impl IntoView {
                                                     _ => format!("{base_class} bg-gray-100 text-gray-800"),
                                                 }
                                             }>{point.test.status.to_string()}</span>
+                                            <TestStatusBadge status=point.test.detailed_status />
                                         </td>
                                         <td class="py-3 px-6 text-left">
                                             {format!("{duration_secs:.3} s")}
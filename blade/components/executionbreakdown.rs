@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+
+use leptos::prelude::*;
+use state::{ExecutionCategory, ExecutionStats};
+
+use crate::{buildmetrics::format_ms, card::Card, charts::piechart::PieChart};
+
+fn category_color(c: ExecutionCategory) -> &'static str {
+    match c {
+        ExecutionCategory::Remote => "#4299e1",
+        ExecutionCategory::RemoteCache => "#48bb78",
+        ExecutionCategory::DiskCache => "#38b2ac",
+        ExecutionCategory::ActionCache => "#9ae6b4",
+        ExecutionCategory::Sandbox => "#ed8936",
+        ExecutionCategory::Worker => "#9f7aea",
+        ExecutionCategory::Local => "#f56565",
+        ExecutionCategory::Other => "#a0aec0",
+    }
+}
+
+fn share(shares: &[(ExecutionCategory, f64)], c: ExecutionCategory) -> f64 {
+    shares
+        .iter()
+        .find(|(s, _)| *s == c)
+        .map(|(_, v)| *v)
+        .unwrap_or_default()
+}
+
+fn percent(v: Option<f64>) -> String {
+    v.map(|v| format!("{:.1}%", v * 100.0))
+        .unwrap_or_else(|| "N/A".to_string())
+}
+
+// Signed difference, e.g. "+12.5pp" for percentage points.
+fn delta(cur: Option<f64>, prev: Option<f64>, fmt: fn(f64) -> String) -> String {
+    match (cur, prev) {
+        (Some(c), Some(p)) => {
+            let d = c - p;
+            format!("{}{}", if d >= 0.0 { "+" } else { "-" }, fmt(d.abs()))
+        },
+        _ => String::new(),
+    }
+}
+
+// Per mnemonic executed action counts and the strategies seen for that
+// mnemonic, keyed by mnemonic. The BEP only reports strategies of test
+// attempts, so other mnemonics only have counts.
+fn by_mnemonic(stats: &ExecutionStats) -> BTreeMap<String, (Option<i64>, Vec<(String, i64)>)> {
+    let mut ret: BTreeMap<String, (Option<i64>, Vec<(String, i64)>)> = BTreeMap::new();
+    if let Some(m) = stats.metrics.as_ref() {
+        m.actions.iter().for_each(|a| {
+            ret.entry(a.mnemonic.clone()).or_default().0 = Some(a.actions_executed);
+        });
+    }
+    stats.strategies.iter().for_each(|s| {
+        ret.entry(s.mnemonic.clone())
+            .or_default()
+            .1
+            .push((s.strategy.clone(), s.count));
+    });
+    ret
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn ComparisonRow(name: String, current: String, previous: String, delta: String) -> impl IntoView {
+    view! {
+        <tr class="border-t border-gray-200 dark:border-gray-600">
+            <td class="py-1 pr-4">{name}</td>
+            <td class="py-1 pr-4 text-right">{current}</td>
+            <td class="py-1 pr-4 text-right">{previous}</td>
+            <td class="py-1 text-right">{delta}</td>
+        </tr>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn ExecutionBreakdown(
+    current: ExecutionStats,
+    previous: Option<ExecutionStats>,
+) -> impl IntoView {
+    let counts = current.category_counts();
+    let shares = current.category_shares();
+    let prev_shares = previous
+        .as_ref()
+        .map(|p| p.category_shares())
+        .unwrap_or_default();
+    let mut categories = shares.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+    prev_shares.iter().for_each(|(c, _)| {
+        if !categories.contains(c) {
+            categories.push(*c);
+        }
+    });
+
+    let hit_rate = current
+        .metrics
+        .as_ref()
+        .and_then(|m| m.action_cache_hit_rate());
+    let prev_hit_rate = previous
+        .as_ref()
+        .and_then(|p| p.metrics.as_ref())
+        .and_then(|m| m.action_cache_hit_rate());
+    let wall_time = current.metrics.as_ref().map(|m| m.wall_time_ms as f64);
+    let prev_wall_time = previous
+        .as_ref()
+        .and_then(|p| p.metrics.as_ref())
+        .map(|m| m.wall_time_ms as f64);
+    let has_previous = previous.is_some();
+    let pp = |v: f64| format!("{:.1}pp", v * 100.0);
+    let ms = |v: f64| format_ms(v as i64);
+
+    let mut rows = vec![
+        (
+            "Action Cache Hit Rate".to_string(),
+            percent(hit_rate),
+            percent(prev_hit_rate),
+            delta(hit_rate, prev_hit_rate, pp),
+        ),
+        (
+            "Wall Time".to_string(),
+            wall_time.map(ms).unwrap_or_default(),
+            prev_wall_time.map(ms).unwrap_or_default(),
+            delta(wall_time, prev_wall_time, ms),
+        ),
+    ];
+    categories.iter().for_each(|c| {
+        let cur = share(&shares, *c);
+        let prev = has_previous.then(|| share(&prev_shares, *c));
+        rows.push((
+            c.to_string(),
+            percent(Some(cur)),
+            percent(prev),
+            delta(Some(cur), prev, pp),
+        ));
+    });
+
+    let mnemonics = by_mnemonic(&current);
+    let invocation_id = current.invocation_id.clone();
+
+    view! {
+        <div class="grid grid-cols-1 xl:grid-cols-3">
+            <Card class="p-4 m-2 max-w-full">
+                <h3 class="font-semibold text-lg mb-2">"Execution Strategies"</h3>
+                {if counts.is_empty() {
+                    view! { <p class="text-gray-500">"No spawns reported."</p> }.into_any()
+                } else {
+                    view! {
+                        <div class="flex flex-row items-center gap-4">
+                            <PieChart
+                                data=counts.clone()
+                                size=160
+                                inner_radius_ratio=0.6
+                                value_accessor=|v| v.1 as f64
+                                label_accessor=|v| v.0.to_string()
+                                color_accessor=|v| category_color(v.0).to_string()
+                                tooltip_content_accessor=|v| format!("{}: {}", v.0, v.1)
+                            />
+                            <ul class="text-sm">
+                                {shares
+                                    .iter()
+                                    .map(|(c, s)| {
+                                        view! {
+                                            <li class="flex items-center gap-2">
+                                                <span
+                                                    class="inline-block w-3 h-3 rounded-full"
+                                                    style=format!("background-color: {}", category_color(*c))
+                                                ></span>
+                                                {format!("{c}: {:.1}%", s * 100.0)}
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </ul>
+                        </div>
+                    }
+                        .into_any()
+                }}
+            </Card>
+            <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                <h3 class="font-semibold text-lg mb-2">
+                    {match previous.as_ref() {
+                        Some(p) => {
+                            view! {
+                                "Compared to "
+                                <a
+                                    class="text-blue-600 dark:text-blue-400 hover:underline"
+                                    href=format!("/invocation/{}/metrics", p.invocation_id)
+                                >
+                                    "previous run of this pattern"
+                                </a>
+                                " "
+                                <a
+                                    class="text-sm text-blue-600 dark:text-blue-400 hover:underline"
+                                    href=format!("/compare/{}/{}", p.invocation_id, invocation_id)
+                                >
+                                    "(full diff)"
+                                </a>
+                            }
+                                .into_any()
+                        }
+                        None => "No previous run of this pattern".into_any(),
+                    }}
+                </h3>
+                <table class="min-w-full text-sm">
+                    <thead>
+                        <tr class="text-left text-gray-500 dark:text-gray-400">
+                            <th class="py-1 pr-4"></th>
+                            <th class="py-1 pr-4 text-right">"This"</th>
+                            <th class="py-1 pr-4 text-right">"Previous"</th>
+                            <th class="py-1 text-right">"Change"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {rows
+                            .into_iter()
+                            .map(|(name, current, previous, delta)| {
+                                view! {
+                                    <ComparisonRow
+                                        name=name
+                                        current=current
+                                        previous=previous
+                                        delta=delta
+                                    />
+                                }
+                            })
+                            .collect_view()}
+                    </tbody>
+                </table>
+            </Card>
+            <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                <h3 class="font-semibold text-lg mb-2">"Actions by Mnemonic"</h3>
+                <table class="min-w-full text-sm">
+                    <thead>
+                        <tr class="text-left text-gray-500 dark:text-gray-400">
+                            <th class="py-1 pr-4">"Mnemonic"</th>
+                            <th class="py-1 pr-4 text-right">"Executed"</th>
+                            <th class="py-1">"Strategies"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {mnemonics
+                            .into_iter()
+                            .map(|(mnemonic, (executed, strategies))| {
+                                view! {
+                                    <tr class="border-t border-gray-200 dark:border-gray-600">
+                                        <td class="py-1 pr-4">{mnemonic}</td>
+                                        <td class="py-1 pr-4 text-right">
+                                            {executed.map(|e| e.to_string()).unwrap_or_default()}
+                                        </td>
+                                        <td class="py-1">
+                                            {strategies
+                                                .into_iter()
+                                                .map(|(strategy, count)| {
+                                                    let color = ExecutionCategory::classify(&strategy)
+                                                        .map(category_color)
+                                                        .unwrap_or("#a0aec0");
+                                                    view! {
+                                                        <span
+                                                            class="inline-block rounded px-1 mr-1 text-xs text-gray-900"
+                                                            style=format!("background-color: {color}")
+                                                        >
+                                                            {format!("{strategy}: {count}")}
+                                                        </span>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                    </tbody>
+                </table>
+                <p class="mt-2 text-xs text-gray-500">
+                    "Bazel only reports strategies for test attempts. Strategy shares of other actions are in the invocation totals."
+                </p>
+            </Card>
+        </div>
+    }
+}
@@ -0,0 +1,217 @@
+use leptos::prelude::*;
+use state::{ChangeKind, InvocationDiff, InvocationResults, ListDiff, StatusChange};
+
+use crate::{card::Card, statusicon::StatusIcon, summaryheader::format_time};
+
+// Only the largest duration changes are interesting.
+const MAX_DURATION_DELTAS: usize = 50;
+
+fn change_label(kind: ChangeKind) -> (&'static str, &'static str) {
+    match kind {
+        ChangeKind::NewlyFailing => ("Newly Failing", "bg-red-200 text-red-900"),
+        ChangeKind::NewlyPassing => ("Newly Passing", "bg-green-200 text-green-900"),
+        ChangeKind::Added => ("Added", "bg-blue-200 text-blue-900"),
+        ChangeKind::Removed => ("Removed", "bg-gray-300 text-gray-900"),
+        ChangeKind::Changed => ("Changed", "bg-yellow-200 text-yellow-900"),
+    }
+}
+
+fn status_str(s: Option<state::Status>) -> String {
+    s.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn InvocationCard(title: &'static str, invocation: InvocationResults) -> impl IntoView {
+    let status = invocation.status;
+    view! {
+        <Card class="p-4 m-2 max-w-full flex-1">
+            <h3 class="text-sm text-gray-500 dark:text-gray-400">{title}</h3>
+            <div class="flex items-center gap-2">
+                <StatusIcon class="h-5 w-5" status=Signal::derive(move || status) />
+                <a
+                    class="text-blue-600 dark:text-blue-400 hover:underline break-all"
+                    href=format!("/invocation/{}", invocation.id)
+                >
+                    {invocation.id.clone()}
+                </a>
+            </div>
+            <div class="text-sm">
+                <b>{invocation.command.clone()}</b>
+                " "
+                {invocation.pattern.join(" ")}
+            </div>
+            <div class="text-sm text-gray-400">{format_time(&invocation.start)}</div>
+        </Card>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn StatusChanges(title: &'static str, changes: Vec<StatusChange>) -> impl IntoView {
+    view! {
+        <Card class="p-4 m-2 max-w-full overflow-x-auto">
+            <h3 class="font-semibold text-lg mb-2">{format!("{title} ({})", changes.len())}</h3>
+            {if changes.is_empty() {
+                view! { <p class="text-gray-500 text-sm">"No changes."</p> }.into_any()
+            } else {
+                view! {
+                    <table class="min-w-full text-sm">
+                        <tbody>
+                            {changes
+                                .into_iter()
+                                .map(|c| {
+                                    let (label, class) = change_label(c.kind);
+                                    view! {
+                                        <tr class="border-t border-gray-200 dark:border-gray-600">
+                                            <td class="py-1 pr-4 whitespace-nowrap">
+                                                <span class=format!(
+                                                    "inline-block rounded px-1 text-xs {class}",
+                                                )>{label}</span>
+                                            </td>
+                                            <td class="py-1 pr-4 break-all">{c.name}</td>
+                                            <td class="py-1 whitespace-nowrap text-gray-500">
+                                                {format!(
+                                                    "{} → {}",
+                                                    status_str(c.before),
+                                                    status_str(c.after),
+                                                )}
+                                            </td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()}
+                        </tbody>
+                    </table>
+                }
+                    .into_any()
+            }}
+        </Card>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn OptionsChanges(title: &'static str, diff: ListDiff) -> impl IntoView {
+    view! {
+        <div class="mb-2">
+            <h4 class="font-semibold">{title}</h4>
+            {if diff.is_empty() {
+                view! { <p class="text-gray-500 text-sm">"Identical."</p> }.into_any()
+            } else {
+                view! {
+                    <ul class="font-mono text-xs">
+                        {diff
+                            .removed
+                            .into_iter()
+                            .map(|o| view! { <li class="text-red-500">{format!("- {o}")}</li> })
+                            .collect_view()}
+                        {diff
+                            .added
+                            .into_iter()
+                            .map(|o| view! { <li class="text-green-500">{format!("+ {o}")}</li> })
+                            .collect_view()}
+                    </ul>
+                }
+                    .into_any()
+            }}
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn InvocationDiffView(diff: InvocationDiff) -> impl IntoView {
+    let durations = diff
+        .test_durations
+        .into_iter()
+        .take(MAX_DURATION_DELTAS)
+        .collect::<Vec<_>>();
+    view! {
+        <div class="flex flex-col">
+            <div class="flex flex-row">
+                <InvocationCard title="Before" invocation=diff.before />
+                <InvocationCard title="After" invocation=diff.after />
+            </div>
+            <div class="grid grid-cols-1 xl:grid-cols-2">
+                <StatusChanges title="Targets" changes=diff.targets />
+                <StatusChanges title="Tests" changes=diff.tests />
+                <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                    <h3 class="font-semibold text-lg mb-2">"Test Duration Changes"</h3>
+                    <table class="min-w-full text-sm">
+                        <thead>
+                            <tr class="text-left text-gray-500 dark:text-gray-400">
+                                <th class="py-1 pr-4">"Test"</th>
+                                <th class="py-1 pr-4 text-right">"Before"</th>
+                                <th class="py-1 pr-4 text-right">"After"</th>
+                                <th class="py-1 text-right">"Change"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {durations
+                                .into_iter()
+                                .map(|d| {
+                                    let delta = d.delta_secs();
+                                    // Slower is worse.
+                                    let color = if delta > 0.0 {
+                                        "text-red-500"
+                                    } else {
+                                        "text-green-500"
+                                    };
+                                    view! {
+                                        <tr class="border-t border-gray-200 dark:border-gray-600">
+                                            <td class="py-1 pr-4 break-all">{d.name.clone()}</td>
+                                            <td class="py-1 pr-4 text-right">
+                                                {format!("{:.2}s", d.before.as_secs_f64())}
+                                            </td>
+                                            <td class="py-1 pr-4 text-right">
+                                                {format!("{:.2}s", d.after.as_secs_f64())}
+                                            </td>
+                                            <td class=format!("py-1 text-right {color}")>
+                                                {format!("{delta:+.2}s")}
+                                            </td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()}
+                        </tbody>
+                    </table>
+                </Card>
+                <Card class="p-4 m-2 max-w-full overflow-x-auto">
+                    <h3 class="font-semibold text-lg mb-2">"Build Options"</h3>
+                    <OptionsChanges title="Explicit Command Line" diff=diff.explicit_cmd_line />
+                    <OptionsChanges title="Explicit Startup Options" diff=diff.explicit_startup />
+                    <h4 class="font-semibold">"Build Metadata"</h4>
+                    {if diff.build_metadata.is_empty() {
+                        view! { <p class="text-gray-500 text-sm">"Identical."</p> }.into_any()
+                    } else {
+                        view! {
+                            <table class="min-w-full text-xs font-mono">
+                                <tbody>
+                                    {diff
+                                        .build_metadata
+                                        .into_iter()
+                                        .map(|m| {
+                                            view! {
+                                                <tr class="border-t border-gray-200 dark:border-gray-600">
+                                                    <td class="py-1 pr-4">{m.key}</td>
+                                                    <td class="py-1 pr-4 text-red-500 break-all">
+                                                        {m.before.unwrap_or_default()}
+                                                    </td>
+                                                    <td class="py-1 text-green-500 break-all">
+                                                        {m.after.unwrap_or_default()}
+                                                    </td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </tbody>
+                            </table>
+                        }
+                            .into_any()
+                    }}
+                </Card>
+            </div>
+        </div>
+    }
+}
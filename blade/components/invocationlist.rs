@@ -0,0 +1,392 @@
+use leptos::{leptos_dom::helpers::event_target_value, prelude::*};
+use state::{InvocationFilter, InvocationPage, InvocationQuery, InvocationSortField, Status};
+
+use crate::{
+    dashboard::filters::parse_date_string,
+    statusicon::StatusIcon,
+    summaryheader::format_time,
+};
+
+const INPUT_CLASS: &str = "w-full p-2 bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-500 dark:text-white";
+const LABEL_CLASS: &str = "block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1";
+
+fn format_duration(start: &std::time::SystemTime, end: Option<&std::time::SystemTime>) -> String {
+    end.and_then(|e| e.duration_since(*start).ok())
+        .map(|d| {
+            humantime::format_duration(std::time::Duration::from_secs(d.as_secs())).to_string()
+        })
+        .unwrap_or_default()
+}
+
+// Parses "KEY=VALUE, KEY2=VALUE2" into metadata pairs, skipping malformed
+// entries.
+fn parse_metadata(s: &str) -> Vec<(String, String)> {
+    s.split(',')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .filter(|(k, _)| !k.is_empty())
+        .collect()
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn InvocationFilterControls(set_query: WriteSignal<InvocationQuery>) -> impl IntoView {
+    let (status, set_status) = signal(String::new());
+    let (command, set_command) = signal(String::new());
+    let (pattern, set_pattern) = signal(String::new());
+    let (date_from, set_date_from) = signal(String::new());
+    let (date_to, set_date_to) = signal(String::new());
+    let (metadata, set_metadata) = signal(String::new());
+    let (project_id, set_project_id) = signal(String::new());
+    let (keyword, set_keyword) = signal(String::new());
+    let (tool_tag, set_tool_tag) = signal(String::new());
+    let (exit_code, set_exit_code) = signal(String::new());
+    let (failure_category, set_failure_category) = signal(String::new());
+    let (build_tool_version, set_build_tool_version) = signal(String::new());
+    let (build_host, set_build_host) = signal(String::new());
+
+    let apply = move |_| {
+        let filter = InvocationFilter {
+            status: non_empty(&status.get()).map(|s| Status::parse(&s)),
+            command: non_empty(&command.get()),
+            pattern: non_empty(&pattern.get()),
+            start_from: parse_date_string(&date_from.get()),
+            // Include the whole end day.
+            start_to: parse_date_string(&date_to.get())
+                .map(|t| t + std::time::Duration::from_secs(24 * 60 * 60)),
+            metadata: parse_metadata(&metadata.get()),
+            project_id: non_empty(&project_id.get()),
+            keyword: non_empty(&keyword.get()),
+            tool_tag: non_empty(&tool_tag.get()),
+            exit_code: non_empty(&exit_code.get()),
+            failure_category: non_empty(&failure_category.get()),
+            build_tool_version: non_empty(&build_tool_version.get()),
+            workspace_status: non_empty(&build_host.get())
+                .map(|h| ("BUILD_HOST".to_string(), h))
+                .into_iter()
+                .collect(),
+        };
+        // A new search resets paging but keeps the current sort order.
+        set_query.update(|q| {
+            q.filter = filter;
+            q.offset = 0;
+        });
+    };
+
+    view! {
+        <div class="bg-white dark:bg-gray-700 p-6 rounded-lg shadow-md mb-6">
+            <div class="grid grid-cols-1 md:grid-cols-6 gap-4 items-end">
+                <div>
+                    <label class=LABEL_CLASS>"Status"</label>
+                    <select
+                        class=INPUT_CLASS
+                        on:change=move |ev| set_status.set(event_target_value(&ev))
+                    >
+                        <option value="">"Any"</option>
+                        <option value="Success">"Success"</option>
+                        <option value="Fail">"Fail"</option>
+                        <option value="InProgress">"In Progress"</option>
+                        <option value="Interrupted">"Interrupted"</option>
+                        <option value="Unknown">"Unknown"</option>
+                    </select>
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Command"</label>
+                    <input
+                        type="text"
+                        placeholder="test"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_command.set(event_target_value(&ev))
+                        prop:value=command
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Pattern"</label>
+                    <input
+                        type="text"
+                        placeholder="//foo/..."
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_pattern.set(event_target_value(&ev))
+                        prop:value=pattern
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Started After"</label>
+                    <input
+                        type="date"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_date_from.set(event_target_value(&ev))
+                        prop:value=date_from
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Started Before"</label>
+                    <input
+                        type="date"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_date_to.set(event_target_value(&ev))
+                        prop:value=date_to
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Build Metadata"</label>
+                    <input
+                        type="text"
+                        placeholder="USER=me, BRANCH=main"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_metadata.set(event_target_value(&ev))
+                        prop:value=metadata
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Project"</label>
+                    <input
+                        type="text"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_project_id.set(event_target_value(&ev))
+                        prop:value=project_id
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Keyword"</label>
+                    <input
+                        type="text"
+                        placeholder="ci"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_keyword.set(event_target_value(&ev))
+                        prop:value=keyword
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Tool Tag"</label>
+                    <input
+                        type="text"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_tool_tag.set(event_target_value(&ev))
+                        prop:value=tool_tag
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Exit Code"</label>
+                    <input
+                        type="text"
+                        placeholder="BUILD_FAILURE"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_exit_code.set(event_target_value(&ev))
+                        prop:value=exit_code
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Failure Category"</label>
+                    <input
+                        type="text"
+                        placeholder="remote_execution"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_failure_category.set(event_target_value(&ev))
+                        prop:value=failure_category
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Bazel Version"</label>
+                    <input
+                        type="text"
+                        placeholder="7.4.1"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_build_tool_version.set(event_target_value(&ev))
+                        prop:value=build_tool_version
+                    />
+                </div>
+                <div>
+                    <label class=LABEL_CLASS>"Host"</label>
+                    <input
+                        type="text"
+                        class=INPUT_CLASS
+                        on:input=move |ev| set_build_host.set(event_target_value(&ev))
+                        prop:value=build_host
+                    />
+                </div>
+            </div>
+            <div class="flex justify-end mt-4">
+                <button
+                    class="px-6 py-2 bg-blue-600 hover:bg-blue-700 text-white rounded-md font-semibold transition-colors"
+                    on:click=apply
+                >
+                    "Search"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn SortHeader(
+    name: &'static str,
+    field: InvocationSortField,
+    query: ReadSignal<InvocationQuery>,
+    set_query: WriteSignal<InvocationQuery>,
+) -> impl IntoView {
+    let arrow = move || {
+        query.with(|q| {
+            if q.sort_by != field {
+                ""
+            } else if q.ascending {
+                " ▲"
+            } else {
+                " ▼"
+            }
+        })
+    };
+    view! {
+        <th
+            class="py-3 px-6 text-left cursor-pointer select-none"
+            on:click=move |_| {
+                set_query
+                    .update(|q| {
+                        if q.sort_by == field {
+                            q.ascending = !q.ascending;
+                        } else {
+                            q.sort_by = field;
+                            q.ascending = false;
+                        }
+                        q.offset = 0;
+                    })
+            }
+        >
+            {name}
+            {arrow}
+        </th>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn InvocationTable(
+    page: InvocationPage,
+    query: ReadSignal<InvocationQuery>,
+    set_query: WriteSignal<InvocationQuery>,
+) -> impl IntoView {
+    let first = if page.total == 0 { 0 } else { page.offset + 1 };
+    let last = page.offset + page.invocations.len();
+    let has_prev = page.offset > 0;
+    let has_next = last < page.total;
+    let limit = page.limit;
+
+    view! {
+        <div class="mt-4">
+            <div class="flex items-center justify-between mb-4">
+                <div class="text-sm text-gray-600 dark:text-gray-400">
+                    {format!("Showing {first}-{last} of {} invocations", page.total)}
+                </div>
+                <div class="flex space-x-2">
+                    <button
+                        class="px-4 py-2 bg-gray-200 dark:bg-gray-600 rounded-md disabled:opacity-50"
+                        disabled=!has_prev
+                        on:click=move |_| {
+                            set_query.update(|q| q.offset = q.offset.saturating_sub(limit))
+                        }
+                    >
+                        "Previous"
+                    </button>
+                    <button
+                        class="px-4 py-2 bg-gray-200 dark:bg-gray-600 rounded-md disabled:opacity-50"
+                        disabled=!has_next
+                        on:click=move |_| set_query.update(|q| q.offset += limit)
+                    >
+                        "Next"
+                    </button>
+                </div>
+            </div>
+            <div class="overflow-x-auto">
+                <table class="min-w-full bg-white dark:bg-gray-700 rounded-lg shadow-md">
+                    <thead>
+                        <tr class="bg-gray-100 dark:bg-gray-700 text-gray-600 dark:text-gray-300 uppercase text-sm leading-normal">
+                            <SortHeader
+                                name="Status"
+                                field=InvocationSortField::Status
+                                query=query
+                                set_query=set_query
+                            />
+                            <th class="py-3 px-6 text-left">"Invocation ID"</th>
+                            <SortHeader
+                                name="Command"
+                                field=InvocationSortField::Command
+                                query=query
+                                set_query=set_query
+                            />
+                            <th class="py-3 px-6 text-left">"Pattern"</th>
+                            <SortHeader
+                                name="Start"
+                                field=InvocationSortField::Start
+                                query=query
+                                set_query=set_query
+                            />
+                            <SortHeader
+                                name="Duration"
+                                field=InvocationSortField::End
+                                query=query
+                                set_query=set_query
+                            />
+                            <th class="py-3 px-6 text-left">"Exit Code"</th>
+                        </tr>
+                    </thead>
+                    <tbody class="text-gray-700 dark:text-gray-300 text-sm font-light">
+                        {page
+                            .invocations
+                            .into_iter()
+                            .map(|inv| {
+                                let status = inv.status;
+                                view! {
+                                    <tr class="border-b border-gray-200 dark:border-gray-600 hover:bg-gray-100 dark:hover:bg-gray-600">
+                                        <td class="py-3 px-6 text-left">
+                                            <StatusIcon
+                                                class="h-5 w-5"
+                                                status=Signal::derive(move || status)
+                                            />
+                                        </td>
+                                        <td class="py-3 px-6 text-left whitespace-nowrap">
+                                            <a
+                                                class="text-blue-600 dark:text-blue-400 hover:underline"
+                                                href=format!("/invocation/{}", inv.id)
+                                            >
+                                                {inv.id.clone()}
+                                            </a>
+                                        </td>
+                                        <td class="py-3 px-6 text-left">{inv.command.clone()}</td>
+                                        <td class="py-3 px-6 text-left break-all">
+                                            {inv.pattern.join(" ")}
+                                        </td>
+                                        <td class="py-3 px-6 text-left whitespace-nowrap">
+                                            {format_time(&inv.start)}
+                                        </td>
+                                        <td class="py-3 px-6 text-left whitespace-nowrap">
+                                            {format_duration(&inv.start, inv.end.as_ref())}
+                                        </td>
+                                        <td
+                                            class="py-3 px-6 text-left whitespace-nowrap"
+                                            title=inv
+                                                .failure_detail
+                                                .as_ref()
+                                                .map(|f| f.message.clone())
+                                        >
+                                            {inv.exit_code.as_ref().map(|e| e.name.clone())}
+                                        </td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,70 @@
+use leptos::prelude::*;
+use state::{InvocationRef, LastGreen};
+
+use crate::summaryheader::format_time;
+
+#[allow(non_snake_case)]
+#[component]
+fn InvocationLink(title: &'static str, invocation: Option<InvocationRef>) -> impl IntoView {
+    view! {
+        <div class="pl-4 pr-4">
+            <span class="text-xs text-gray-500 dark:text-gray-400">{title}</span>
+            {match invocation {
+                Some(i) => {
+                    view! {
+                        <div class="text-sm">
+                            <a
+                                class="text-blue-600 dark:text-blue-400 hover:underline"
+                                href=format!("/invocation/{}", i.invocation_id)
+                            >
+                                {i.invocation_id.chars().take(8).collect::<String>()}
+                            </a>
+                            " "
+                            <span class="text-gray-400">{format_time(&i.start)}</span>
+                            {i
+                                .commit
+                                .map(|c| {
+                                    view! {
+                                        <span class="font-mono text-xs ml-2">
+                                            {c.chars().take(12).collect::<String>()}
+                                        </span>
+                                    }
+                                })}
+                        </div>
+                    }
+                        .into_any()
+                }
+                None => view! { <div class="text-sm text-gray-400">"Not found"</div> }.into_any(),
+            }}
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn LastGreenView(last_green: LastGreen, current: String) -> impl IntoView {
+    let compare = match (
+        last_green.last_passed.as_ref(),
+        last_green.first_failed.as_ref(),
+    ) {
+        (Some(passed), Some(_)) => Some(format!("/compare/{}/{current}", passed.invocation_id)),
+        _ => None,
+    };
+    view! {
+        <div class="flex flex-row items-center">
+            <InvocationLink title="First failed in" invocation=last_green.first_failed />
+            <InvocationLink title="Last passed in" invocation=last_green.last_passed />
+            {compare
+                .map(|href| {
+                    view! {
+                        <a
+                            class="pl-4 text-sm text-blue-600 dark:text-blue-400 hover:underline"
+                            href=href
+                        >
+                            "(diff with last pass)"
+                        </a>
+                    }
+                })}
+        </div>
+    }
+}
@@ -1,8 +1,14 @@
 pub mod accordion;
+pub mod actionfailures;
+pub mod buildmetrics;
 pub mod card;
 pub mod charts;
 pub mod clipboard;
 pub mod dashboard;
+pub mod executionbreakdown;
+pub mod invocationdiff;
+pub mod invocationlist;
+pub mod lastgreen;
 pub mod list;
 pub mod measuretime;
 pub mod nav;
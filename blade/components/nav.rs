@@ -67,6 +67,21 @@ pub fn Nav(
                         class:hidden=move || !*menu_open.read()
                     >
                         <ul class="flex flex-col font-medium mt-4 rounded-lg bg-gray-50 dark:bg-gray-800">
+                            <li>
+                                <a href="/invocations" class="block p-2 text-xl hover:underline">
+                                    Invocations
+                                </a>
+                            </li>
+                            <li>
+                                <a href="/dashboard" class="block p-2 text-xl hover:underline">
+                                    Dashboard
+                                </a>
+                            </li>
+                            <li>
+                                <a href="/flaky" class="block p-2 text-xl hover:underline">
+                                    Flaky Tests
+                                </a>
+                            </li>
                             <li>
                                 <label class="relative flex items-center group p-2 text-xl">
                                     Dark Mode
@@ -1,4 +1,4 @@
-use leptos::{either::EitherOf4, prelude::*};
+use leptos::{either::EitherOf5, prelude::*};
 use tailwindmerge::tailwind_merge;
 
 use crate::tooltip::Tooltip;
@@ -12,22 +12,27 @@ pub fn StatusIcon(
     move || {
         let c = move || class.get();
         match status.get() {
-            state::Status::Success => EitherOf4::A(view! {
+            state::Status::Success => EitherOf5::A(view! {
                 <Tooltip tooltip=move || view! { <span class="p-2">Success</span> }>
                     <img class=c src="/assets/success.svg" />
                 </Tooltip>
             }),
-            state::Status::Fail => EitherOf4::B(view! {
+            state::Status::Fail => EitherOf5::B(view! {
                 <Tooltip tooltip=move || view! { <span class="p-2">Fail</span> }>
                     <img class=c src="/assets/fail.svg" />
                 </Tooltip>
             }),
-            state::Status::Skip => EitherOf4::C(view! {
+            state::Status::Skip => EitherOf5::C(view! {
                 <Tooltip tooltip=move || view! { <span class="p-2">Skip</span> }>
                     <img class=c src="/assets/skip.svg" />
                 </Tooltip>
             }),
-            _ => EitherOf4::D(view! {
+            s @ (state::Status::Incomplete | state::Status::Interrupted) => EitherOf5::D(view! {
+                <Tooltip tooltip=move || view! { <span class="p-2">{s.to_string()}</span> }>
+                    <img class=c src="/assets/incomplete.svg" />
+                </Tooltip>
+            }),
+            _ => EitherOf5::E(view! {
                 <Tooltip tooltip=move || view! { <span class="p-2">In Progress</span> }>
                     <div role="status">
                         <svg
@@ -57,3 +62,26 @@ pub fn StatusIcon(
         }
     }
 }
+
+/// Badge for detailed test outcomes that the pass/fail icon can't convey, such
+/// as flakes and timeouts.
+#[allow(non_snake_case)]
+#[component]
+pub fn TestStatusBadge(status: Option<state::TestStatus>) -> impl IntoView {
+    let badge = match status? {
+        state::TestStatus::Flaky => ("Flaky", "bg-yellow-100 text-yellow-800"),
+        state::TestStatus::Timeout => ("Timeout", "bg-orange-100 text-orange-800"),
+        state::TestStatus::Incomplete => ("Incomplete", "bg-gray-100 text-gray-800"),
+        state::TestStatus::RemoteFailure => ("Remote Failure", "bg-purple-100 text-purple-800"),
+        state::TestStatus::FailedToBuild => ("Failed to Build", "bg-red-100 text-red-800"),
+        state::TestStatus::ToolHaltedBeforeTesting => ("Halted", "bg-gray-100 text-gray-800"),
+        state::TestStatus::NoStatus => ("No Status", "bg-gray-100 text-gray-800"),
+        state::TestStatus::Passed | state::TestStatus::Failed => return None,
+    };
+    Some(view! {
+        <span class=format!(
+            "ml-2 px-2 inline-flex text-xs leading-5 font-semibold rounded-full whitespace-nowrap {}",
+            badge.1,
+        )>{badge.0}</span>
+    })
+}
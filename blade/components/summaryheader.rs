@@ -90,7 +90,7 @@ pub fn SummaryHeader() -> impl IntoView {
             let mut failing_tests: usize = 0;
             invocation.tests.values().for_each(|t| match t.status {
                 state::Status::Success => passing_tests += 1,
-                state::Status::InProgress => {},
+                state::Status::InProgress | state::Status::Incomplete => {},
                 _ => failing_tests += 1,
             });
 
@@ -116,6 +116,56 @@ pub fn SummaryHeader() -> impl IntoView {
         let cmd = ucfirst(&invocation.read().command);
         let patterns = invocation.read().pattern.join(" ");
         let start = format_time(&invocation.read().start);
+        let attempt = invocation
+            .read()
+            .attempt_number
+            .map(|n| format!(" (attempt {n})"))
+            .unwrap_or_default();
+        // Where the build came from, e.g. a CI pipeline or a developer.
+        let origin = {
+            let inv = invocation.read();
+            inv.project_id
+                .iter()
+                .map(|p| format!("project {p}"))
+                .chain(inv.tool_tag.iter().map(|t| format!("tool tag {t}")))
+                .map(|s| format!(" · {s}"))
+                .collect::<String>()
+        };
+        // Who ran the build where, and with which Bazel version.
+        let build_info = {
+            let inv = invocation.read();
+            let who = match (&inv.build_user, &inv.build_host) {
+                (Some(u), Some(h)) => Some(format!("{u}@{h}")),
+                (u, h) => u.clone().or(h.clone()),
+            };
+            who.into_iter()
+                .chain(inv.build_tool_version.iter().map(|v| format!("Bazel {v}")))
+                .map(|s| format!(" · {s}"))
+                .collect::<String>()
+        };
+        let keywords = invocation.read().notification_keywords.clone();
+        // Why Bazel failed, e.g. failing tests or an unreachable remote cache.
+        let exit = {
+            let inv = invocation.read();
+            inv.exit_code.clone().filter(|e| e.code != 0).map(|e| {
+                let (category, message) = inv
+                    .failure_detail
+                    .as_ref()
+                    .map(|f| (format!("{} {}", f.category, f.code), f.message.clone()))
+                    .unwrap_or_default();
+                view! {
+                    <span
+                        class="px-1.5 rounded bg-red-100 dark:bg-red-900 text-red-800 dark:text-red-200 text-sm"
+                        title=category
+                    >
+                        {format!("{} ({})", e.name, e.code)}
+                    </span>
+                    <span class="text-gray-500 text-sm truncate max-w-md" title=message.clone()>
+                        {message}
+                    </span>
+                }
+            })
+        };
         let location = use_location();
         let duration = invocation
             .read()
@@ -133,7 +183,7 @@ pub fn SummaryHeader() -> impl IntoView {
                 inv.status,
                 state::Status::InProgress | state::Status::Unknown
             );
-            is_incomplete && !inv.is_live
+            inv.status == state::Status::Interrupted || (is_incomplete && !inv.is_live)
         });
         view! {
             <div class="w-screen h-fit grid grid-rows-1 grid-flow-col content-start divide-x overflow-hidden">
@@ -172,15 +222,43 @@ pub fn SummaryHeader() -> impl IntoView {
                                 <CopyToClipboard text=patterns />
                             </span>
                         </div>
-                        <div class="text-gray-400 text-sm self-center">{start}</div>
+                        <div class="text-gray-400 text-sm self-center">
+                            {start}
+                            {attempt}
+                            {origin}
+                            {build_info}
+                            {keywords
+                                .into_iter()
+                                .map(|k| {
+                                    view! {
+                                        <span class="ml-2 px-1.5 rounded bg-gray-100 dark:bg-gray-700 text-gray-600 dark:text-gray-300">
+                                            {k}
+                                        </span>
+                                    }
+                                })
+                                .collect_view()}
+                        </div>
                         <div class="flex gap-2 items-center">
                             {duration}
+                            {exit}
                             <A href=move || {
                                 let current_path = location.pathname.read();
                                 toggle_page_url(&current_path, "details")
                             }>
                                 <span class="text-blue-500 underline">(details)</span>
                             </A>
+                            <A href=move || {
+                                let current_path = location.pathname.read();
+                                toggle_page_url(&current_path, "metrics")
+                            }>
+                                <span class="text-blue-500 underline">(metrics)</span>
+                            </A>
+                            <A href=move || {
+                                let current_path = location.pathname.read();
+                                toggle_page_url(&current_path, "events")
+                            }>
+                                <span class="text-blue-500 underline">(events)</span>
+                            </A>
                             {move || {
                                 invocation
                                     .read()
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use anyhow::anyhow;
-use leptos::prelude::*;
+use leptos::{either::Either, prelude::*};
 use leptos_router::components::A;
 use web_sys::KeyboardEvent;
 
@@ -10,7 +10,7 @@ use crate::{
     card::Card,
     list::*,
     searchbar::Searchbar,
-    statusicon::StatusIcon,
+    statusicon::{StatusIcon, TestStatusBadge},
     tooltip::Tooltip,
 };
 
@@ -28,9 +28,20 @@ fn status_weight(s: &state::Status) -> u8 {
     match s {
         state::Status::InProgress => 0,
         state::Status::Fail => 1,
-        state::Status::Skip => 2,
-        state::Status::Success => 3,
-        state::Status::Unknown => 4,
+        state::Status::Incomplete | state::Status::Interrupted => 2,
+        state::Status::Skip => 3,
+        state::Status::Success => 4,
+        state::Status::Unknown => 5,
+    }
+}
+
+fn test_weight(t: &state::Test) -> u8 {
+    let w = status_weight(&t.status) * 2;
+    // Flakes eventually passed but still deserve attention ahead of clean passes.
+    if t.detailed_status == Some(state::TestStatus::Flaky) {
+        w.saturating_sub(1)
+    } else {
+        w
     }
 }
 
@@ -119,8 +130,8 @@ fn sorted_tests(
 
     vec.sort_unstable_by(|a, b| {
         if failed_first {
-            let a_status = status_weight(&a.status);
-            let b_status = status_weight(&b.status);
+            let a_status = test_weight(a);
+            let b_status = test_weight(b);
             if a_status != b_status {
                 return a_status.partial_cmp(&b_status).unwrap();
             }
@@ -145,6 +156,75 @@ fn sorted_tests(
     vec
 }
 
+fn target_id(name: &str) -> String { format!("target-{name}") }
+
+// The reason the target failed, linking to the targets that actually broke.
+// Links load as many targets as needed to show the root cause. Root causes
+// that were never announced as targets are not linked.
+fn failure_view(
+    failure: state::TargetFailure,
+    targets: Memo<Vec<state::Target>>,
+    set_target_limit: WriteSignal<usize>,
+) -> impl IntoView {
+    let reason = if failure.message.is_empty() {
+        failure.category.clone()
+    } else {
+        failure.message.clone()
+    };
+    view! {
+        <div class="pl-8 text-xs text-gray-500 dark:text-gray-400">
+            <span class="whitespace-pre-wrap break-all" title=failure.category>
+                {reason}
+            </span>
+            {(!failure.root_causes.is_empty())
+                .then(|| {
+                    view! {
+                        <span class="pl-2">
+                            "caused by "
+                            {failure
+                                .root_causes
+                                .into_iter()
+                                .map(|cause| {
+                                    if !targets.with_untracked(|t| t.iter().any(|t| t.name == cause)) {
+                                        return Either::Left(
+                                            view! { <span class="pr-2">{cause}</span> },
+                                        );
+                                    }
+                                    let id = target_id(&cause);
+                                    let name = cause.clone();
+                                    Either::Right(
+                                        view! {
+                                            <button
+                                                class="pr-2 text-blue-500 underline"
+                                                on:click=move |_| {
+                                                    if let Some(pos) = targets
+                                                        .with_untracked(|t| {
+                                                            t.iter().position(|t| t.name == name)
+                                                        })
+                                                    {
+                                                        set_target_limit.update(|v| *v = (*v).max(pos + 1));
+                                                    }
+                                                    let id = id.clone();
+                                                    request_animation_frame(move || {
+                                                        document()
+                                                            .get_element_by_id(&id)
+                                                            .map(|el| el.scroll_into_view());
+                                                    });
+                                                }
+                                            >
+                                                {cause}
+                                            </button>
+                                        },
+                                    )
+                                })
+                                .collect_view()}
+                        </span>
+                    }
+                })}
+        </div>
+    }
+}
+
 #[allow(non_snake_case)]
 pub fn TargetList() -> impl IntoView {
     let invocation = expect_context::<RwSignal<state::InvocationResults>>();
@@ -244,7 +324,7 @@ pub fn TargetList() -> impl IntoView {
                                                     .take(test_limit.get())
                                                     .collect::<Vec<_>>()
                                             }
-                                            key=|t| (t.name.clone(), t.status)
+                                            key=|t| (t.name.clone(), t.status, t.detailed_status)
                                             children=move |t| {
                                                 let test_name = t.name.clone();
                                                 let test_name_filter = test_name.clone();
@@ -276,6 +356,7 @@ pub fn TargetList() -> impl IntoView {
                                                                         </span>
                                                                     </Tooltip>
                                                                 </span>
+                                                                <TestStatusBadge status=t.detailed_status />
                                                                 <span class="text-gray-400 text-xs pl-1 ml-auto float-right whitespace-nowrap">
                                                                     {format!("{:.2?}", t.duration)}
                                                                 </span>
@@ -324,7 +405,7 @@ pub fn TargetList() -> impl IntoView {
                                     .take(target_limit.get())
                                     .collect::<Vec<_>>()
                             }
-                            key=|t| (t.name.clone(), t.status)
+                            key=|t| (t.name.clone(), t.status, t.failure.clone())
                             children=move |t| {
                                 let target_name = t.name.clone();
                                 let target_name_filter = target_name.clone();
@@ -334,7 +415,10 @@ pub fn TargetList() -> impl IntoView {
                                         !filter.get().is_empty()
                                             && !target_name_filter.contains(&filter.get())
                                     })>
-                                        <div class="flex items-center justify-start w-full">
+                                        <div
+                                            id=target_id(&t.name)
+                                            class="flex items-center justify-start w-full"
+                                        >
                                             <span class="float-left">
                                                 <StatusIcon
                                                     class="h-4 w-4 max-w-fit"
@@ -358,6 +442,12 @@ pub fn TargetList() -> impl IntoView {
 
                                             </span>
                                         </div>
+                                        {t
+                                            .failure
+                                            .clone()
+                                            .map(|f| {
+                                                failure_view(f, sorted_targets_memo, set_target_limit)
+                                            })}
                                     </ListItem>
                                 }
                             }
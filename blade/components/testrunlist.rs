@@ -8,7 +8,7 @@ use crate::{
     card::Card,
     list::*,
     searchbar::Searchbar,
-    statusicon::StatusIcon,
+    statusicon::{StatusIcon, TestStatusBadge},
     tooltip::Tooltip,
 };
 
@@ -25,9 +25,10 @@ fn status_weight(s: state::Status) -> u8 {
     match s {
         state::Status::InProgress => 1,
         state::Status::Fail => 2,
-        state::Status::Skip => 3,
-        state::Status::Success => 4,
-        _ => 5,
+        state::Status::Incomplete | state::Status::Interrupted => 3,
+        state::Status::Skip => 4,
+        state::Status::Success => 5,
+        _ => 6,
     }
 }
 
@@ -208,7 +209,17 @@ pub fn TestRunList(
                 test.with(|test| test.as_ref().map(|test| test.runs.len() > 1).unwrap_or(false))
                     .then(move || {
                         view! {
-                            <AccordionItem header=move || view! { <h3>Runs</h3> }>
+                            <AccordionItem header=move || {
+                                let detailed_status = test
+                                    .with(|test| {
+                                        test.as_ref().ok().and_then(|t| t.detailed_status)
+                                    });
+                                view! {
+                                    <h3 class="flex items-center">
+                                        Runs <TestStatusBadge status=detailed_status />
+                                    </h3>
+                                }
+                            }>
                                 <List>
                                     <For
                                         each=move || {
@@ -267,6 +278,7 @@ pub fn TestRunList(
                                                                     </div>
                                                                 </Tooltip>
                                                             </div>
+                                                            <TestStatusBadge status=run.detailed_status />
                                                             <span class="text-gray-400 text-xs pl-2 ml-auto float-right whitespace-nowrap">
                                                                 {format!("{}", humantime::format_duration(run.duration))}
                                                             </span>
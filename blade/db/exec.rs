@@ -82,14 +82,20 @@ where
 }
 
 /// Execute database operations within a transaction.
-/// The closure must be synchronous and perform all DB operations sequentially.
+/// Nothing is written if the closure returns an error.
 #[instrument(skip(mgr, f), name = "db_transaction")]
 pub async fn transaction<T, F>(mgr: Arc<dyn state::DBManager>, f: F) -> Result<T>
 where
     T: Send + 'static,
     F: FnOnce(&mut dyn state::DB) -> Result<T> + Send + 'static,
 {
-    // For now, this is identical to run_group but provides semantic clarity
-    // Later we could add actual transaction handling if needed
-    run_group(mgr, f).await
+    run_group(mgr, move |db| {
+        let mut ret = None;
+        db.transaction(Box::new(|db: &mut dyn state::DB| {
+            ret = Some(f(db)?);
+            Ok(())
+        }))?;
+        ret.ok_or_else(|| anyhow::anyhow!("transaction returned no result"))
+    })
+    .await
 }
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+/// A single result of the test in an earlier (or the current) invocation, as
+/// loaded by a backend.
+pub(crate) struct HistoryPoint {
+    pub invocation_id: String,
+    pub start: std::time::SystemTime,
+    pub status: state::Status,
+}
+
+// Build metadata keys that identify where a build came from. Invocations only
+// compare if these match.
+const MATCH_KEYS: &[&str] = &["BRANCH", "REPO_URL"];
+
+// Build metadata keys that may hold the commit an invocation was built at.
+const COMMIT_KEYS: &[&str] = &["COMMIT_SHA", "GIT_COMMIT", "COMMIT"];
+
+// Flags that differ between every invocation and never change test results.
+const VOLATILE_FLAGS: &[&str] = &[
+    "--bes_backend",
+    "--bes_header",
+    "--bes_instance_name",
+    "--bes_keywords",
+    "--bes_results_url",
+    "--bes_timeout",
+    "--build_event_binary_file",
+    "--build_event_json_file",
+    "--build_event_text_file",
+    "--build_metadata",
+    "--build_request_id",
+    "--execution_log_binary_file",
+    "--execution_log_json_file",
+    "--invocation_id",
+    "--profile",
+    "--remote_cache_header",
+    "--remote_downloader_header",
+    "--remote_exec_header",
+    "--remote_header",
+];
+
+// Flags that set an environment variable, and the variables CI systems set to
+// a new value for every run.
+const ENV_FLAGS: &[&str] = &[
+    "--action_env",
+    "--host_action_env",
+    "--repo_env",
+    "--test_env",
+];
+const VOLATILE_ENV: &[&str] = &[
+    "BUILD_ID",
+    "BUILD_NUMBER",
+    "BUILD_URL",
+    "BUILDKITE_BUILD_ID",
+    "BUILDKITE_BUILD_NUMBER",
+    "BUILDKITE_JOB_ID",
+    "CI_JOB_ID",
+    "CI_PIPELINE_ID",
+    "GITHUB_RUN_ATTEMPT",
+    "GITHUB_RUN_ID",
+    "GITHUB_RUN_NUMBER",
+    "JOB_ID",
+];
+
+fn is_volatile(flag: &str) -> bool {
+    let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+    let var = value.split_once('=').map_or(value, |(k, _)| k);
+    VOLATILE_FLAGS.contains(&name) || (ENV_FLAGS.contains(&name) && VOLATILE_ENV.contains(&var))
+}
+
+/// The explicit command line and build metadata of an invocation, as stored in
+/// the `Options` table.
+#[derive(Default)]
+pub(crate) struct InvocationOptions {
+    pub explicit_cmd_line: Vec<String>,
+    pub build_metadata: HashMap<String, String>,
+}
+
+impl InvocationOptions {
+    pub(crate) fn push(&mut self, kind: &str, keyval: String) {
+        match kind {
+            "Explicit Command Line" => self.explicit_cmd_line.push(keyval),
+            "Build Metadata" => {
+                if let Some((k, v)) = keyval.split_once('=') {
+                    self.build_metadata.insert(k.to_string(), v.to_string());
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Canonical form of the flags and metadata that have to match for two
+    // invocations to be comparable.
+    fn signature(&self) -> Vec<String> {
+        let mut sig = self
+            .explicit_cmd_line
+            .iter()
+            .filter(|f| !is_volatile(f))
+            .cloned()
+            .collect::<Vec<_>>();
+        sig.sort();
+        MATCH_KEYS.iter().for_each(|k| {
+            if let Some(v) = self.build_metadata.get(*k) {
+                sig.push(format!("{k}={v}"));
+            }
+        });
+        sig
+    }
+
+    fn commit(&self) -> Option<String> {
+        COMMIT_KEYS
+            .iter()
+            .find_map(|k| self.build_metadata.get(*k).cloned())
+    }
+}
+
+/// Walks the history of a test backwards from `current` and finds where the
+/// current failure streak started and the last invocation where the test
+/// passed before it. Only invocations whose explicit flags and source metadata
+/// match the current invocation are considered.
+pub(crate) fn find(
+    current: &str,
+    mut points: Vec<HistoryPoint>,
+    options: &HashMap<String, InvocationOptions>,
+) -> state::LastGreen {
+    points.sort_by(|a, b| b.start.cmp(&a.start));
+    let empty = InvocationOptions::default();
+    let opts = |id: &str| options.get(id).unwrap_or(&empty);
+    let to_ref = |p: &HistoryPoint| state::InvocationRef {
+        invocation_id: p.invocation_id.clone(),
+        start: p.start,
+        commit: opts(&p.invocation_id).commit(),
+    };
+
+    let mut ret = state::LastGreen::default();
+    let Some(pos) = points.iter().position(|p| p.invocation_id == current) else {
+        return ret;
+    };
+    let sig = opts(current).signature();
+    for p in &points[pos..] {
+        if opts(&p.invocation_id).signature() != sig {
+            continue;
+        }
+        match p.status {
+            state::Status::Fail => ret.first_failed = Some(to_ref(p)),
+            state::Status::Success => {
+                ret.last_passed = Some(to_ref(p));
+                break;
+            },
+            _ => {},
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn point(inv: &str, secs: u64, status: state::Status) -> HistoryPoint {
+        HistoryPoint {
+            invocation_id: inv.to_string(),
+            start: UNIX_EPOCH + Duration::from_secs(secs),
+            status,
+        }
+    }
+
+    fn opts(flags: &[&str], metadata: &[(&str, &str)]) -> InvocationOptions {
+        let mut o = InvocationOptions::default();
+        flags
+            .iter()
+            .for_each(|f| o.push("Explicit Command Line", f.to_string()));
+        metadata
+            .iter()
+            .for_each(|(k, v)| o.push("Build Metadata", format!("{k}={v}")));
+        o
+    }
+
+    #[test]
+    fn test_find() {
+        use state::Status::*;
+        let options = HashMap::from([
+            (
+                "a".to_string(),
+                opts(&["--config=ci"], &[("BRANCH", "main"), ("COMMIT_SHA", "1")]),
+            ),
+            (
+                "b".to_string(),
+                opts(
+                    &[
+                        "--config=ci",
+                        "--build_metadata=COMMIT_SHA=2",
+                        "--test_env=BUILD_ID=2",
+                    ],
+                    &[("BRANCH", "main"), ("COMMIT_SHA", "2")],
+                ),
+            ),
+            // Different branch, ignored.
+            (
+                "c".to_string(),
+                opts(&["--config=ci"], &[("BRANCH", "dev"), ("COMMIT_SHA", "3")]),
+            ),
+            (
+                "d".to_string(),
+                opts(
+                    &[
+                        "--config=ci",
+                        "--bes_keywords=run_4",
+                        "--bes_results_url=https://ci/4",
+                        "--remote_header=x-run=4",
+                    ],
+                    &[("BRANCH", "main"), ("COMMIT_SHA", "4")],
+                ),
+            ),
+            // Different flags, ignored.
+            (
+                "e".to_string(),
+                opts(&["--config=asan"], &[("BRANCH", "main")]),
+            ),
+            (
+                "f".to_string(),
+                opts(&["--config=ci"], &[("BRANCH", "main"), ("COMMIT_SHA", "6")]),
+            ),
+        ]);
+        let points = vec![
+            point("a", 1, Success),
+            point("b", 2, Success),
+            point("c", 3, Fail),
+            point("d", 4, Fail),
+            point("e", 5, Success),
+            point("f", 6, Fail),
+        ];
+
+        let got = find("f", points, &options);
+        let first_failed = got.first_failed.unwrap();
+        assert_eq!(first_failed.invocation_id, "d");
+        assert_eq!(first_failed.commit.as_deref(), Some("4"));
+        let last_passed = got.last_passed.unwrap();
+        assert_eq!(last_passed.invocation_id, "b");
+        assert_eq!(last_passed.commit.as_deref(), Some("2"));
+
+        // Never passed before.
+        let got = find("d", vec![point("d", 4, Fail)], &options);
+        assert_eq!(got.first_failed.unwrap().invocation_id, "d");
+        assert!(got.last_passed.is_none());
+
+        // Unknown invocation.
+        assert_eq!(find("z", vec![], &options), state::LastGreen::default());
+    }
+
+    #[test]
+    fn test_signature() {
+        let sig = |flags: &[&str]| opts(flags, &[]).signature();
+        assert_eq!(
+            sig(&["--config=ci", "--test_env=BUILD_ID=1"]),
+            sig(&["--config=ci", "--test_env=BUILD_ID=2"])
+        );
+        // Other variables can change test results.
+        assert_ne!(sig(&["--test_env=FOO=1"]), sig(&["--test_env=FOO=2"]));
+        // Only whole flag names are ignored.
+        assert_ne!(sig(&["--profile=a"]), sig(&["--profiles=a"]));
+    }
+}
@@ -2,13 +2,27 @@ use anyhow::anyhow;
 
 mod envscrub;
 mod exec;
+mod lastgreen;
+mod like;
 mod manager;
 mod postgres;
+mod rawevents;
 mod sqlite;
 mod time;
 
 pub use exec::{run, run_group, transaction};
 
+/// Returns whether `e` was caused by a row that doesn't exist, as opposed to
+/// e.g. a database that can't be reached.
+pub fn is_not_found(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        matches!(
+            c.downcast_ref::<diesel::result::Error>(),
+            Some(diesel::result::Error::NotFound)
+        )
+    })
+}
+
 pub fn new(uri: &str) -> anyhow::Result<std::sync::Arc<dyn state::DBManager>> {
     if uri.starts_with("postgres://") {
         return Ok(std::sync::Arc::from(manager::PostgresManager::new(uri)?));
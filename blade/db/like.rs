@@ -0,0 +1,31 @@
+/// Escape character used with `escape()` on LIKE expressions built from
+/// `contains` or `escape`.
+pub(crate) const ESCAPE: char = '\\';
+
+/// Returns a LIKE pattern matching strings that contain `s` literally, with
+/// the `%` and `_` wildcards and the escape character itself escaped.
+pub(crate) fn contains(s: &str) -> String { format!("%{}%", escape(s)) }
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | ESCAPE) {
+            ret.push(ESCAPE);
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("//foo/..."), "//foo/...");
+        assert_eq!(escape("//foo_bar:100%"), "//foo\\_bar:100\\%");
+        assert_eq!(escape("a\\b"), "a\\\\b");
+        assert_eq!(contains("a_b"), "%a\\_b%");
+    }
+}
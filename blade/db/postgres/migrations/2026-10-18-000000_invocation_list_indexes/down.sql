@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS invocations_command_start_idx;
+DROP INDEX IF EXISTS invocations_status_start_idx;
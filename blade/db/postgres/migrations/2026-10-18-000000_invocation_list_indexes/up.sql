@@ -0,0 +1,4 @@
+-- The invocation list page filters by status/command and orders by start time.
+-- invocations_start_idx already covers the unfiltered listing.
+CREATE INDEX IF NOT EXISTS invocations_status_start_idx ON invocations (status, start DESC);
+CREATE INDEX IF NOT EXISTS invocations_command_start_idx ON invocations (command, start DESC);
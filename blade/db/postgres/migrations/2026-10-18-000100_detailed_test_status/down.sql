@@ -0,0 +1,2 @@
+ALTER TABLE testruns DROP COLUMN detailed_status;
+ALTER TABLE tests DROP COLUMN detailed_status;
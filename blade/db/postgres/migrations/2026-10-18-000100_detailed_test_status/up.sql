@@ -0,0 +1,2 @@
+ALTER TABLE tests ADD COLUMN detailed_status TEXT;
+ALTER TABLE testruns ADD COLUMN detailed_status TEXT;
@@ -0,0 +1,15 @@
+CREATE TABLE actionfailures (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    label TEXT NOT NULL,
+    mnemonic TEXT NOT NULL,
+    exit_code INTEGER NOT NULL,
+    -- Arguments as a JSON array.
+    command_line TEXT NOT NULL,
+    primary_output TEXT,
+    stdout_uri TEXT,
+    stderr_uri TEXT,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS actionfailures_inv_id ON actionfailures (invocation_id);
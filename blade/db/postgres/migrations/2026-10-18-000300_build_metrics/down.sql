@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS runnermetrics;
+DROP TABLE IF EXISTS actionmetrics;
+DROP TABLE IF EXISTS buildmetrics;
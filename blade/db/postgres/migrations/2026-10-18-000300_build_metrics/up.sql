@@ -0,0 +1,53 @@
+CREATE TABLE buildmetrics (
+    invocation_id TEXT NOT NULL PRIMARY KEY,
+    actions_created BIGINT NOT NULL,
+    actions_executed BIGINT NOT NULL,
+    action_cache_hits BIGINT NOT NULL,
+    action_cache_misses BIGINT NOT NULL,
+    used_heap_size_post_build BIGINT NOT NULL,
+    peak_post_gc_heap_size BIGINT NOT NULL,
+    targets_loaded BIGINT NOT NULL,
+    targets_configured BIGINT NOT NULL,
+    packages_loaded BIGINT NOT NULL,
+    cpu_time_ms BIGINT NOT NULL,
+    wall_time_ms BIGINT NOT NULL,
+    analysis_phase_time_ms BIGINT NOT NULL,
+    execution_phase_time_ms BIGINT NOT NULL,
+    source_artifacts_read_bytes BIGINT NOT NULL,
+    source_artifacts_read_count BIGINT NOT NULL,
+    output_artifacts_seen_bytes BIGINT NOT NULL,
+    output_artifacts_seen_count BIGINT NOT NULL,
+    output_artifacts_from_action_cache_bytes BIGINT NOT NULL,
+    output_artifacts_from_action_cache_count BIGINT NOT NULL,
+    top_level_artifacts_bytes BIGINT NOT NULL,
+    top_level_artifacts_count BIGINT NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+
+-- Per mnemonic action counts from BuildMetrics.action_summary.action_data.
+CREATE TABLE actionmetrics (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    mnemonic TEXT NOT NULL,
+    actions_executed BIGINT NOT NULL,
+    first_started_ms BIGINT NOT NULL,
+    last_ended_ms BIGINT NOT NULL,
+    system_time_ms BIGINT NOT NULL,
+    user_time_ms BIGINT NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS actionmetrics_inv_id ON actionmetrics (invocation_id);
+
+-- Spawn runner counts from BuildMetrics.action_summary.runner_count.
+CREATE TABLE runnermetrics (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    exec_kind TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS runnermetrics_inv_id ON runnermetrics (invocation_id);
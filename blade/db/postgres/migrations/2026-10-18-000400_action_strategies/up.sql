@@ -0,0 +1,11 @@
+-- One row per individually reported spawn, keyed by the reporting event so
+-- that replayed events are not double counted.
+CREATE TABLE actionstrategies (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    mnemonic TEXT NOT NULL,
+    strategy TEXT NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS actionstrategies_inv_id ON actionstrategies (invocation_id);
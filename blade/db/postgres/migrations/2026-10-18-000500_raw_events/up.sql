@@ -0,0 +1,14 @@
+-- The payload is the deflate compressed JSON of the event. Events are keyed by
+-- their sequence number so that retried streams overwrite instead of
+-- duplicating them.
+CREATE TABLE rawevents (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    sequence BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    payload BYTEA NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS rawevents_inv_seq ON rawevents (invocation_id, sequence);
+CREATE INDEX IF NOT EXISTS rawevents_inv_kind ON rawevents (invocation_id, kind, sequence);
@@ -0,0 +1,12 @@
+-- Builds and invocation attempts as reported by BES lifecycle events. A build
+-- can be retried with several invocation attempts.
+CREATE TABLE builds (
+    id TEXT NOT NULL PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    enqueued TIMESTAMP WITH TIME ZONE,
+    finished TIMESTAMP WITH TIME ZONE,
+    result TEXT
+);
+ALTER TABLE invocations ADD COLUMN build_id TEXT;
+ALTER TABLE invocations ADD COLUMN attempt_number BIGINT;
+CREATE INDEX IF NOT EXISTS invocations_build_id ON invocations (build_id);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS invocations_project_id;
+ALTER TABLE invocations DROP COLUMN tool_tag;
+ALTER TABLE invocations DROP COLUMN notification_keywords;
+ALTER TABLE invocations DROP COLUMN project_id;
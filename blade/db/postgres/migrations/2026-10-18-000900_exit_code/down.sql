@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS invocations_exit_code_name;
+ALTER TABLE invocations DROP COLUMN failure_code;
+ALTER TABLE invocations DROP COLUMN failure_category;
+ALTER TABLE invocations DROP COLUMN failure_message;
+ALTER TABLE invocations DROP COLUMN exit_code;
+ALTER TABLE invocations DROP COLUMN exit_code_name;
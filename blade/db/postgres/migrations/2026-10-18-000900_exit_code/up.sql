@@ -0,0 +1,8 @@
+-- Set from BuildFinished. The failure detail is stored decoded so that
+-- infrastructure failures can be told apart from broken code.
+ALTER TABLE invocations ADD COLUMN exit_code_name TEXT;
+ALTER TABLE invocations ADD COLUMN exit_code INTEGER;
+ALTER TABLE invocations ADD COLUMN failure_message TEXT;
+ALTER TABLE invocations ADD COLUMN failure_category TEXT;
+ALTER TABLE invocations ADD COLUMN failure_code TEXT;
+CREATE INDEX IF NOT EXISTS invocations_exit_code_name ON invocations (exit_code_name);
@@ -0,0 +1,3 @@
+ALTER TABLE targets DROP COLUMN root_causes;
+ALTER TABLE targets DROP COLUMN failure_category;
+ALTER TABLE targets DROP COLUMN failure_message;
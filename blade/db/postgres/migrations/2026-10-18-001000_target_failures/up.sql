@@ -0,0 +1,4 @@
+-- Why a target failed. Root causes are stored as a JSON array of labels.
+ALTER TABLE targets ADD COLUMN failure_message TEXT;
+ALTER TABLE targets ADD COLUMN failure_category TEXT;
+ALTER TABLE targets ADD COLUMN root_causes TEXT;
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS invocations_build_tool_version;
+ALTER TABLE invocations DROP COLUMN build_host;
+ALTER TABLE invocations DROP COLUMN build_user;
+ALTER TABLE invocations DROP COLUMN options_description;
+ALTER TABLE invocations DROP COLUMN server_pid;
+ALTER TABLE invocations DROP COLUMN working_directory;
+ALTER TABLE invocations DROP COLUMN workspace_directory;
+ALTER TABLE invocations DROP COLUMN build_tool_version;
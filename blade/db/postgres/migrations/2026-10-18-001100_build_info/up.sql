@@ -0,0 +1,10 @@
+-- Set from BuildStarted and the BUILD_USER and BUILD_HOST workspace status
+-- keys. The other workspace status keys are stored with the options.
+ALTER TABLE invocations ADD COLUMN build_tool_version TEXT;
+ALTER TABLE invocations ADD COLUMN workspace_directory TEXT;
+ALTER TABLE invocations ADD COLUMN working_directory TEXT;
+ALTER TABLE invocations ADD COLUMN server_pid BIGINT;
+ALTER TABLE invocations ADD COLUMN options_description TEXT;
+ALTER TABLE invocations ADD COLUMN build_user TEXT;
+ALTER TABLE invocations ADD COLUMN build_host TEXT;
+CREATE INDEX IF NOT EXISTS invocations_build_tool_version ON invocations (build_tool_version);
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use anyhow::{Context, anyhow};
-use diesel::{prelude::*, r2d2::ConnectionManager};
+use diesel::{connection::TransactionManager, prelude::*, r2d2::ConnectionManager};
 use diesel_migrations::{FileBasedMigrations, MigrationHarness};
 use diesel_tracing::pg::InstrumentedPgConnection;
 use r2d2::PooledConnection;
@@ -31,6 +31,25 @@ pub fn init_db(db_path: &str) -> anyhow::Result<()> {
 }
 
 impl state::DB for Postgres {
+    fn transaction(
+        &mut self,
+        f: Box<dyn FnOnce(&mut dyn state::DB) -> anyhow::Result<()> + '_>,
+    ) -> anyhow::Result<()> {
+        type Manager = <InstrumentedPgConnection as Connection>::TransactionManager;
+        Manager::begin_transaction(&mut *self.conn).context("failed to begin transaction")?;
+        match f(self) {
+            Ok(()) => {
+                Manager::commit_transaction(&mut *self.conn).context("failed to commit transaction")
+            },
+            Err(e) => {
+                if let Err(re) = Manager::rollback_transaction(&mut *self.conn) {
+                    tracing::warn!("failed to roll back transaction: {re:#?}");
+                }
+                Err(e)
+            },
+        }
+    }
+
     fn upsert_shallow_invocation(
         &mut self,
         invocation: &state::InvocationResults,
@@ -113,7 +132,7 @@ impl state::DB for Postgres {
             .select(models::Target::as_select())
             .filter(schema::targets::dsl::invocation_id.eq(id))
             .load(&mut self.conn)?;
-        targets.iter().for_each(|res| {
+        targets.iter().try_for_each(|res| -> anyhow::Result<()> {
             ret.targets.insert(
                 res.name.clone(),
                 state::Target {
@@ -126,9 +145,11 @@ impl state::DB for Postgres {
                         crate::time::to_systemtime(t)
                             .unwrap_or_else(|_| std::time::SystemTime::now())
                     }),
+                    failure: res.failure()?,
                 },
             );
-        });
+            Ok(())
+        })?;
         let tests = schema::tests::table
             .select(models::Test::as_select())
             .filter(schema::tests::invocation_id.eq(id))
@@ -155,6 +176,10 @@ impl state::DB for Postgres {
                 state::Test {
                     name: test.name,
                     status: state::Status::parse(&test.status),
+                    detailed_status: test
+                        .detailed_status
+                        .as_deref()
+                        .and_then(state::TestStatus::parse),
                     duration: std::time::Duration::from_secs_f64(test.duration_s.unwrap_or(0.0)),
                     end: crate::time::to_systemtime(&test.end)
                         .unwrap_or_else(|_| std::time::SystemTime::now()),
@@ -166,6 +191,10 @@ impl state::DB for Postgres {
                             shard: tr.shard,
                             attempt: tr.attempt,
                             status: state::Status::parse(&tr.status),
+                            detailed_status: tr
+                                .detailed_status
+                                .as_deref()
+                                .and_then(state::TestStatus::parse),
                             details: tr.details,
                             duration: std::time::Duration::from_secs_f64(tr.duration_s),
                             files: test_artifacts
@@ -221,6 +250,47 @@ impl state::DB for Postgres {
         }
     }
 
+    fn get_output_since(
+        &mut self,
+        invocation_id: &str,
+        after_id: i64,
+    ) -> anyhow::Result<state::OutputSince> {
+        use schema::invocationoutput;
+        let after_id: i32 = after_id
+            .try_into()
+            .context("failed to convert output line id")?;
+        let output =
+            || invocationoutput::table.filter(invocationoutput::invocation_id.eq(invocation_id));
+        // Lines are deleted from the end, so if the last known line is still
+        // there, so are all lines before it.
+        let deleted = after_id > 0
+            && !diesel::select(diesel::dsl::exists(
+                output().filter(invocationoutput::id.eq(after_id)),
+            ))
+            .get_result::<bool>(&mut self.conn)
+            .context("failed to check output line")?;
+        let kept = if deleted {
+            let n = output()
+                .filter(invocationoutput::id.le(after_id))
+                .count()
+                .get_result::<i64>(&mut self.conn)
+                .context("failed to count output lines")?;
+            Some(n as usize)
+        } else {
+            None
+        };
+        let lines = output()
+            .filter(invocationoutput::id.gt(after_id))
+            .select((invocationoutput::id, invocationoutput::line))
+            .order(invocationoutput::id.asc())
+            .load::<(i32, String)>(&mut self.conn)
+            .context("failed to get output lines")?
+            .into_iter()
+            .map(|(id, line)| (id.into(), line))
+            .collect();
+        Ok(state::OutputSince { kept, lines })
+    }
+
     fn delete_last_output_lines(&mut self, id: &str, num_lines: u32) -> anyhow::Result<()> {
         let to_delete = schema::invocationoutput::table
             .filter(schema::invocationoutput::invocation_id.eq(id))
@@ -299,6 +369,28 @@ impl state::DB for Postgres {
             .context("failed to update target result")
     }
 
+    fn update_target_failure(
+        &mut self,
+        invocation_id: &str,
+        name: &str,
+        failure: &state::TargetFailure,
+    ) -> anyhow::Result<()> {
+        use schema::targets;
+
+        let root_causes = (!failure.root_causes.is_empty())
+            .then(|| serde_json::to_string(&failure.root_causes))
+            .transpose()?;
+        diesel::update(targets::table.find(models::Target::gen_id(invocation_id, name)))
+            .set((
+                targets::failure_message.eq(&failure.message),
+                targets::failure_category.eq(&failure.category),
+                targets::root_causes.eq(root_causes),
+            ))
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to update target failure")
+    }
+
     fn get_test(&mut self, id: &str, name: &str) -> anyhow::Result<state::Test> {
         let t = schema::tests::table
             .select(models::Test::as_select())
@@ -330,6 +422,35 @@ impl state::DB for Postgres {
             .context("failed to update test result")
     }
 
+    fn mark_incomplete(
+        &mut self,
+        invocation_id: &str,
+        end: std::time::SystemTime,
+    ) -> anyhow::Result<usize> {
+        use schema::{targets, tests};
+
+        let end: time::OffsetDateTime = end.into();
+        let in_progress = state::Status::InProgress.to_string();
+        let incomplete = state::Status::Incomplete.to_string();
+        let targets = diesel::update(
+            targets::table
+                .filter(targets::invocation_id.eq(invocation_id))
+                .filter(targets::status.eq(&in_progress)),
+        )
+        .set((targets::status.eq(&incomplete), targets::end.eq(Some(end))))
+        .execute(&mut self.conn)
+        .context("failed to mark targets incomplete")?;
+        let tests = diesel::update(
+            tests::table
+                .filter(tests::invocation_id.eq(invocation_id))
+                .filter(tests::status.eq(&in_progress)),
+        )
+        .set((tests::status.eq(&incomplete), tests::end.eq(end)))
+        .execute(&mut self.conn)
+        .context("failed to mark tests incomplete")?;
+        Ok(targets + tests)
+    }
+
     fn delete_invocation(&mut self, id: &str) -> anyhow::Result<()> {
         diesel::delete(schema::invocations::table.find(id))
             .execute(&mut self.conn)
@@ -338,12 +459,77 @@ impl state::DB for Postgres {
     }
 
     fn delete_invocations_since(&mut self, ts: &std::time::SystemTime) -> anyhow::Result<usize> {
+        use schema::{builds, invocations};
+
         let ot: time::OffsetDateTime = (*ts).into();
-        diesel::delete(schema::invocations::table.filter(schema::invocations::start.le(ot)))
-            .execute(&mut self.conn)
+        self.conn
+            .transaction::<_, anyhow::Error, _>(|conn| {
+                // Builds only exist for their invocation attempts, so they go
+                // along with the last of them.
+                diesel::delete(
+                    builds::table
+                        .filter(
+                            builds::id.nullable().eq_any(
+                                invocations::table
+                                    .filter(invocations::start.le(ot))
+                                    .select(invocations::build_id),
+                            ),
+                        )
+                        .filter(
+                            builds::id.nullable().ne_all(
+                                invocations::table
+                                    .filter(invocations::start.gt(ot))
+                                    .filter(invocations::build_id.is_not_null())
+                                    .select(invocations::build_id),
+                            ),
+                        ),
+                )
+                .execute(conn)?;
+                let deleted = diesel::delete(invocations::table.filter(invocations::start.le(ot)))
+                    .execute(conn)?;
+                // Builds that never got an attempt expire with the time they
+                // were enqueued or finished.
+                diesel::delete(
+                    builds::table
+                        .filter(
+                            builds::id.nullable().ne_all(
+                                invocations::table
+                                    .filter(invocations::build_id.is_not_null())
+                                    .select(invocations::build_id),
+                            ),
+                        )
+                        .filter(builds::enqueued.le(ot).or(builds::finished.le(ot))),
+                )
+                .execute(conn)?;
+                Ok(deleted)
+            })
             .context(format!("failed to delete invocation since {ot:#?}"))
     }
 
+    fn get_abandoned_invocations(
+        &mut self,
+        heartbeat_before: &std::time::SystemTime,
+    ) -> anyhow::Result<Vec<state::InvocationResults>> {
+        use schema::invocations;
+
+        let ot: time::OffsetDateTime = (*heartbeat_before).into();
+        // Invocations without a heartbeat never stored any events.
+        invocations::table
+            .filter(invocations::status.eq(state::Status::InProgress.to_string()))
+            .filter(invocations::end.is_null())
+            .filter(
+                invocations::last_heartbeat
+                    .lt(ot)
+                    .or(invocations::last_heartbeat
+                        .is_null()
+                        .and(invocations::start.lt(ot))),
+            )
+            .select(models::Invocation::as_select())
+            .load(&mut self.conn)
+            .map(|res| res.into_iter().map(|i| i.into_state()).collect())
+            .context("failed to get abandoned invocations")
+    }
+
     fn update_invocation_heartbeat(&mut self, invocation_id: &str) -> anyhow::Result<()> {
         use schema::invocations::dsl::*;
         let now: time::OffsetDateTime = std::time::SystemTime::now().into();
@@ -392,6 +578,17 @@ impl state::DB for Postgres {
                 ));
             });
         }
+        if !opts.workspace_status.is_empty() {
+            opts.workspace_status.iter().for_each(|(k, v)| {
+                let uid = uuid::Uuid::new_v4().to_string();
+                vals.push((
+                    id.eq(uid),
+                    invocation_id.eq(inv_id.to_string()),
+                    kind.eq("Workspace Status".to_string()),
+                    keyval.eq(format!("{}={}", k.clone(), v.clone()).to_string()),
+                ));
+            });
+        }
         diesel::insert_into(schema::options::table)
             .values(vals)
             .execute(&mut self.conn)
@@ -419,6 +616,12 @@ impl state::DB for Postgres {
                 };
                 opts.build_metadata.insert(k.to_string(), v.to_string());
             },
+            "Workspace Status" => {
+                let Some((k, v)) = keyval.split_once('=') else {
+                    return;
+                };
+                opts.workspace_status.insert(k.to_string(), v.to_string());
+            },
             _ => {
                 opts.structured
                     .entry(kind)
@@ -638,6 +841,46 @@ impl state::DB for Postgres {
                         )
                     }
                 },
+                state::TestFilterItem::ProjectId(value)
+                | state::TestFilterItem::NotificationKeyword(value)
+                | state::TestFilterItem::ToolTag(value) => {
+                    let subquery = match &f.op {
+                        // Matched the same way as in invocation listings.
+                        state::TestFilterOp::Equals => {
+                            filtered_invocations(&f.filter.invocation_filter().unwrap_or_default())
+                        },
+                        state::TestFilterOp::Contains => {
+                            let pattern = crate::like::contains(value);
+                            let subquery = invocations::table.into_boxed();
+                            match &f.filter {
+                                state::TestFilterItem::ProjectId(_) => subquery.filter(
+                                    invocations::project_id
+                                        .like(pattern)
+                                        .escape(crate::like::ESCAPE),
+                                ),
+                                state::TestFilterItem::NotificationKeyword(_) => subquery.filter(
+                                    invocations::notification_keywords
+                                        .like(pattern)
+                                        .escape(crate::like::ESCAPE),
+                                ),
+                                _ => subquery.filter(
+                                    invocations::tool_tag
+                                        .like(pattern)
+                                        .escape(crate::like::ESCAPE),
+                                ),
+                            }
+                        },
+                        op => {
+                            return Err(anyhow!("{op:?} does not apply to {:?}", f.filter));
+                        },
+                    }
+                    .select(invocations::id);
+                    if f.invert {
+                        query.filter(diesel::dsl::not(invocations::id.eq_any(subquery)))
+                    } else {
+                        query.filter(invocations::id.eq_any(subquery))
+                    }
+                },
             };
         }
 
@@ -699,6 +942,587 @@ impl state::DB for Postgres {
 
         Ok(results)
     }
+
+    fn list_invocations(
+        &mut self,
+        query: &state::InvocationQuery,
+    ) -> anyhow::Result<state::InvocationPage> {
+        use schema::invocations;
+
+        let offset: i64 = query
+            .offset
+            .try_into()
+            .context("failed to convert offset to i64")?;
+        let limit: i64 = query
+            .limit
+            .try_into()
+            .context("failed to convert limit to i64")?;
+
+        let total: i64 = filtered_invocations(&query.filter)
+            .count()
+            .get_result(&mut self.conn)
+            .context("failed to count invocations")?;
+
+        let mut q = filtered_invocations(&query.filter).select(models::Invocation::as_select());
+        q = match (query.sort_by, query.ascending) {
+            (state::InvocationSortField::Start, true) => q.order_by(invocations::start.asc()),
+            (state::InvocationSortField::Start, false) => q.order_by(invocations::start.desc()),
+            (state::InvocationSortField::End, true) => q.order_by(invocations::end.asc()),
+            (state::InvocationSortField::End, false) => q.order_by(invocations::end.desc()),
+            (state::InvocationSortField::Command, true) => q.order_by(invocations::command.asc()),
+            (state::InvocationSortField::Command, false) => q.order_by(invocations::command.desc()),
+            (state::InvocationSortField::Status, true) => q.order_by(invocations::status.asc()),
+            (state::InvocationSortField::Status, false) => q.order_by(invocations::status.desc()),
+        };
+        // Break ties deterministically so that paging is stable.
+        let invocations = q
+            .then_order_by(invocations::id.asc())
+            .offset(offset)
+            .limit(limit)
+            .load::<models::Invocation>(&mut self.conn)
+            .context("failed to list invocations")?
+            .into_iter()
+            .map(|i| i.into_state())
+            .collect();
+
+        Ok(state::InvocationPage {
+            invocations,
+            total: total as usize,
+            offset: query.offset,
+            limit: query.limit,
+        })
+    }
+
+    fn get_last_green(
+        &mut self,
+        invocation_id: &str,
+        test_name: &str,
+        max_history: usize,
+    ) -> anyhow::Result<state::LastGreen> {
+        use schema::{invocations, options, tests};
+        let max_history: i64 = max_history
+            .try_into()
+            .context("failed to convert into i64")?;
+        let start = invocations::table
+            .find(invocation_id)
+            .select(invocations::start)
+            .get_result::<time::OffsetDateTime>(&mut self.conn)
+            .context("failed to get invocation")?;
+
+        let points = tests::table
+            .inner_join(invocations::table.on(tests::invocation_id.eq(invocations::id)))
+            .filter(tests::name.eq(test_name))
+            .filter(invocations::start.le(start))
+            .order_by(invocations::start.desc())
+            .limit(max_history)
+            .select((invocations::id, invocations::start, tests::status))
+            .load::<(String, time::OffsetDateTime, String)>(&mut self.conn)
+            .context("failed to load test history")?
+            .into_iter()
+            .map(
+                |(invocation_id, start, status)| crate::lastgreen::HistoryPoint {
+                    invocation_id,
+                    start: crate::time::to_systemtime(&start)
+                        .unwrap_or_else(|_| std::time::SystemTime::now()),
+                    status: state::Status::parse(&status),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let ids = points
+            .iter()
+            .map(|p| p.invocation_id.clone())
+            .collect::<Vec<_>>();
+        let mut options: HashMap<String, crate::lastgreen::InvocationOptions> = HashMap::new();
+        options::table
+            .filter(options::invocation_id.eq_any(ids))
+            .filter(options::kind.eq_any(["Explicit Command Line", "Build Metadata"]))
+            .order_by(options::id.asc())
+            .select((options::invocation_id, options::kind, options::keyval))
+            .load::<(String, String, String)>(&mut self.conn)
+            .context("failed to load options")?
+            .into_iter()
+            .for_each(|(inv, kind, keyval)| options.entry(inv).or_default().push(&kind, keyval));
+
+        Ok(crate::lastgreen::find(invocation_id, points, &options))
+    }
+
+    fn get_flaky_tests(
+        &mut self,
+        since: &std::time::SystemTime,
+        min_invocations: usize,
+        limit: usize,
+    ) -> anyhow::Result<Vec<state::FlakyTest>> {
+        use diesel::sql_types::{BigInt, Timestamptz};
+        let since: time::OffsetDateTime = (*since).into();
+        let min_invocations: i64 = min_invocations
+            .try_into()
+            .context("failed to convert min_invocations to i64")?;
+        let limit: i64 = limit.try_into().context("failed to convert limit to i64")?;
+        diesel::sql_query(FLAKY_TESTS_QUERY)
+            .bind::<Timestamptz, _>(since)
+            .bind::<BigInt, _>(min_invocations)
+            .bind::<BigInt, _>(limit)
+            .load::<models::FlakyTest>(&mut self.conn)
+            .map(|rows| {
+                rows.into_iter()
+                    .map(models::FlakyTest::into_state)
+                    .collect()
+            })
+            .context("failed to get flaky tests")
+    }
+
+    fn insert_action_failure(
+        &mut self,
+        inv_id: &str,
+        failure: &state::ActionFailure,
+    ) -> anyhow::Result<()> {
+        let val = models::ActionFailure::from_state(inv_id, failure)?;
+        diesel::insert_into(schema::actionfailures::table)
+            .values(&val)
+            .on_conflict(schema::actionfailures::id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to insert action failure")
+    }
+
+    fn get_action_failures(&mut self, id: &str) -> anyhow::Result<Vec<state::ActionFailure>> {
+        schema::actionfailures::table
+            .select(models::ActionFailure::as_select())
+            .filter(schema::actionfailures::invocation_id.eq(id))
+            .order_by(schema::actionfailures::label.asc())
+            .load::<models::ActionFailure>(&mut self.conn)
+            .context("failed to get action failures")?
+            .into_iter()
+            .map(|a| a.into_state())
+            .collect()
+    }
+
+    fn upsert_build_metrics(
+        &mut self,
+        inv_id: &str,
+        metrics: &state::BuildMetrics,
+    ) -> anyhow::Result<()> {
+        let val = models::BuildMetrics::from_state(inv_id, metrics);
+        diesel::insert_into(schema::buildmetrics::table)
+            .values(&val)
+            .on_conflict(schema::buildmetrics::invocation_id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .context("failed to insert build metrics")?;
+        for a in &metrics.actions {
+            let val = models::ActionMetrics::from_state(inv_id, a);
+            diesel::insert_into(schema::actionmetrics::table)
+                .values(&val)
+                .on_conflict(schema::actionmetrics::id)
+                .do_update()
+                .set(&val)
+                .execute(&mut self.conn)
+                .context(format!("failed to insert action metrics: {}", a.mnemonic))?;
+        }
+        for r in &metrics.runners {
+            let val = models::RunnerMetrics::from_state(inv_id, r);
+            diesel::insert_into(schema::runnermetrics::table)
+                .values(&val)
+                .on_conflict(schema::runnermetrics::id)
+                .do_update()
+                .set(&val)
+                .execute(&mut self.conn)
+                .context(format!("failed to insert runner metrics: {}", r.name))?;
+        }
+        Ok(())
+    }
+
+    fn get_build_metrics(&mut self, id: &str) -> anyhow::Result<Option<state::BuildMetrics>> {
+        let Some(m) = schema::buildmetrics::table
+            .find(id)
+            .select(models::BuildMetrics::as_select())
+            .first(&mut self.conn)
+            .optional()
+            .context("failed to get build metrics")?
+        else {
+            return Ok(None);
+        };
+        let mut metrics = m.into_state();
+        metrics.actions = schema::actionmetrics::table
+            .select(models::ActionMetrics::as_select())
+            .filter(schema::actionmetrics::invocation_id.eq(id))
+            .order_by(schema::actionmetrics::actions_executed.desc())
+            .load(&mut self.conn)
+            .context("failed to get action metrics")?
+            .into_iter()
+            .map(|a| a.into_state())
+            .collect();
+        metrics.runners = schema::runnermetrics::table
+            .select(models::RunnerMetrics::as_select())
+            .filter(schema::runnermetrics::invocation_id.eq(id))
+            .order_by(schema::runnermetrics::count.desc())
+            .load(&mut self.conn)
+            .context("failed to get runner metrics")?
+            .into_iter()
+            .map(|r| r.into_state())
+            .collect();
+        Ok(Some(metrics))
+    }
+
+    fn get_build_metrics_history(
+        &mut self,
+        since: &std::time::SystemTime,
+        limit: usize,
+    ) -> anyhow::Result<Vec<state::BuildMetricsPoint>> {
+        use schema::{buildmetrics, invocations};
+        let since: time::OffsetDateTime = (*since).into();
+        // Keep the most recent invocations, but return them oldest first for
+        // plotting.
+        let mut ret = buildmetrics::table
+            .inner_join(invocations::table.on(buildmetrics::invocation_id.eq(invocations::id)))
+            .filter(invocations::start.ge(since))
+            .order_by(invocations::start.desc())
+            .limit(limit as i64)
+            .select((models::BuildMetrics::as_select(), invocations::start))
+            .load::<(models::BuildMetrics, time::OffsetDateTime)>(&mut self.conn)
+            .context("failed to get build metrics history")?
+            .into_iter()
+            .map(|(m, start)| state::BuildMetricsPoint {
+                invocation_id: m.invocation_id.clone(),
+                start: crate::time::to_systemtime(&start)
+                    .unwrap_or_else(|_| std::time::SystemTime::now()),
+                metrics: m.into_state(),
+            })
+            .collect::<Vec<_>>();
+        ret.reverse();
+        Ok(ret)
+    }
+
+    fn insert_action_strategy(
+        &mut self,
+        inv_id: &str,
+        key: &str,
+        mnemonic: &str,
+        strategy: &str,
+    ) -> anyhow::Result<()> {
+        let val = models::ActionStrategy::new(inv_id, key, mnemonic, strategy);
+        diesel::insert_into(schema::actionstrategies::table)
+            .values(&val)
+            .on_conflict(schema::actionstrategies::id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to insert action strategy")
+    }
+
+    fn get_action_strategies(&mut self, id: &str) -> anyhow::Result<Vec<state::StrategyCount>> {
+        use schema::actionstrategies;
+        actionstrategies::table
+            .filter(actionstrategies::invocation_id.eq(id))
+            .group_by((actionstrategies::mnemonic, actionstrategies::strategy))
+            .select((
+                actionstrategies::mnemonic,
+                actionstrategies::strategy,
+                diesel::dsl::count_star(),
+            ))
+            .order_by((
+                actionstrategies::mnemonic.asc(),
+                actionstrategies::strategy.asc(),
+            ))
+            .load::<(String, String, i64)>(&mut self.conn)
+            .map(|res| {
+                res.into_iter()
+                    .map(|(mnemonic, strategy, count)| state::StrategyCount {
+                        mnemonic,
+                        strategy,
+                        count,
+                    })
+                    .collect()
+            })
+            .context("failed to get action strategies")
+    }
+
+    fn insert_raw_event(&mut self, inv_id: &str, event: &state::RawEvent) -> anyhow::Result<()> {
+        let val = models::RawEvent::from_state(inv_id, event)?;
+        diesel::insert_into(schema::rawevents::table)
+            .values(&val)
+            .on_conflict(schema::rawevents::id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to insert raw event")
+    }
+
+    fn get_raw_event_kinds(&mut self, id: &str) -> anyhow::Result<Vec<(String, usize)>> {
+        schema::rawevents::table
+            .filter(schema::rawevents::invocation_id.eq(id))
+            .group_by(schema::rawevents::kind)
+            .select((schema::rawevents::kind, diesel::dsl::count_star()))
+            .order_by(schema::rawevents::kind.asc())
+            .load::<(String, i64)>(&mut self.conn)
+            .map(|res| {
+                res.into_iter()
+                    .map(|(kind, count)| (kind, count as usize))
+                    .collect()
+            })
+            .context("failed to get raw event kinds")
+    }
+
+    fn get_raw_events(
+        &mut self,
+        id: &str,
+        kind: Option<&str>,
+        after: i64,
+        limit: usize,
+    ) -> anyhow::Result<Vec<state::RawEvent>> {
+        let mut query = schema::rawevents::table
+            .select(models::RawEvent::as_select())
+            .filter(schema::rawevents::invocation_id.eq(id))
+            .filter(schema::rawevents::sequence.gt(after))
+            .into_boxed();
+        if let Some(kind) = kind {
+            query = query.filter(schema::rawevents::kind.eq(kind));
+        }
+        query
+            .order_by(schema::rawevents::sequence.asc())
+            .limit(limit as i64)
+            .load(&mut self.conn)
+            .context("failed to get raw events")?
+            .into_iter()
+            .map(|e| e.into_state())
+            .collect()
+    }
+
+    fn get_stream_sequence_number(
+        &mut self,
+        inv_id: &str,
+        stream: &str,
+    ) -> anyhow::Result<Option<i64>> {
+        schema::streams::table
+            .find([inv_id, stream].join("|"))
+            .select(schema::streams::sequence_number)
+            .first::<i64>(&mut self.conn)
+            .optional()
+            .context("failed to get stream sequence number")
+    }
+
+    fn update_stream_sequence_number(
+        &mut self,
+        inv_id: &str,
+        stream: &str,
+        sequence_number: i64,
+    ) -> anyhow::Result<()> {
+        let val = models::Stream::new(inv_id, stream, sequence_number);
+        diesel::insert_into(schema::streams::table)
+            .values(&val)
+            .on_conflict(schema::streams::id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to update stream sequence number")
+    }
+
+    fn upsert_build(&mut self, build: &state::Build) -> anyhow::Result<()> {
+        let val = models::Build::from_state(build);
+        diesel::insert_into(schema::builds::table)
+            .values(&val)
+            .on_conflict(schema::builds::id)
+            .do_update()
+            .set(&val)
+            .execute(&mut self.conn)
+            .map(|_| {})
+            .context("failed to upsert build")
+    }
+
+    fn get_build(&mut self, build_id: &str) -> anyhow::Result<Option<state::Build>> {
+        schema::builds::table
+            .find(build_id)
+            .select(models::Build::as_select())
+            .get_result(&mut self.conn)
+            .optional()
+            .context("failed to get build")
+            .map(|b| b.map(models::Build::into_state))
+    }
+
+    fn get_build_invocations(
+        &mut self,
+        build_id: &str,
+    ) -> anyhow::Result<Vec<state::InvocationResults>> {
+        use schema::invocations;
+        invocations::table
+            .filter(invocations::build_id.eq(build_id))
+            .order_by((invocations::attempt_number.asc(), invocations::start.asc()))
+            .select(models::Invocation::as_select())
+            .load(&mut self.conn)
+            .context("failed to get build invocations")
+            .map(|invs| invs.into_iter().map(|i| i.into_state()).collect())
+    }
+
+    fn get_previous_invocation(
+        &mut self,
+        id: &str,
+    ) -> anyhow::Result<Option<state::InvocationResults>> {
+        use schema::invocations;
+        let cur = invocations::table
+            .find(id)
+            .select(models::Invocation::as_select())
+            .get_result(&mut self.conn)
+            .context("failed to get invocation")?;
+        invocations::table
+            .select(models::Invocation::as_select())
+            .filter(invocations::pattern.eq(cur.pattern))
+            .filter(invocations::start.lt(cur.start))
+            .filter(invocations::id.ne(id))
+            .order_by(invocations::start.desc())
+            .first(&mut self.conn)
+            .optional()
+            .map(|res| res.map(|i| i.into_state()))
+            .context("failed to get previous invocation")
+    }
+}
+
+/// Ranks the tests of invocations started since the first bind parameter by
+/// flake rate. Build metadata is concatenated in key order so that invocations
+/// with identical metadata get the same signature.
+///
+/// A test is flaky in an invocation if Bazel reported it as flaky, if attempts
+/// of the same run and shard disagree, or if its status flipped relative to the
+/// previous invocation with identical build metadata. Invocations without
+/// build metadata never count as flips.
+const FLAKY_TESTS_QUERY: &str = r"
+WITH inv AS (
+    SELECT i.id, i.start, (
+        SELECT string_agg(o.keyval, E'\n' ORDER BY o.keyval) FROM options o
+        WHERE o.invocation_id = i.id AND o.kind = 'Build Metadata'
+    ) AS signature
+    FROM invocations i
+    WHERE i.start >= $1
+),
+mixed AS (
+    SELECT DISTINCT r.test_id
+    FROM testruns r JOIN inv ON r.invocation_id = inv.id
+    GROUP BY r.test_id, r.run, r.shard
+    HAVING COUNT(DISTINCT r.status) > 1
+),
+flips AS (
+    SELECT t.id, t.status <> LAG(t.status) OVER (
+        PARTITION BY t.name, inv.signature ORDER BY inv.start
+    ) AS flipped
+    FROM tests t JOIN inv ON t.invocation_id = inv.id
+    WHERE inv.signature IS NOT NULL AND t.status IN ('Success', 'Fail')
+),
+points AS (
+    SELECT t.name, inv.id AS invocation_id, inv.start,
+        CASE WHEN t.detailed_status = 'Flaky'
+            OR t.id IN (SELECT test_id FROM mixed)
+            OR f.flipped THEN 1 ELSE 0 END AS flaky
+    FROM tests t
+    JOIN inv ON t.invocation_id = inv.id
+    LEFT JOIN flips f ON f.id = t.id
+),
+ranked AS (
+    SELECT name,
+        COUNT(*) AS total_invocations,
+        SUM(flaky) AS flaky_invocations,
+        MAX(CASE WHEN flaky = 1 THEN start END) AS last_flaky_start
+    FROM points
+    GROUP BY name
+    HAVING SUM(flaky) > 0 AND COUNT(*) >= $2
+)
+SELECT r.name, r.total_invocations, r.flaky_invocations, r.last_flaky_start, (
+        SELECT p.invocation_id FROM points p
+        WHERE p.name = r.name AND p.flaky = 1
+        ORDER BY p.start DESC LIMIT 1
+    ) AS last_flaky_invocation
+FROM ranked r
+ORDER BY 1.0 * r.flaky_invocations / r.total_invocations DESC,
+    r.flaky_invocations DESC, r.name
+LIMIT $3
+";
+
+/// Builds the boxed Invocations query shared by the count and page queries of
+/// `list_invocations`.
+fn filtered_invocations(
+    filter: &state::InvocationFilter,
+) -> schema::invocations::BoxedQuery<'static, diesel::pg::Pg> {
+    use schema::{invocations, options};
+
+    let mut query = invocations::table.into_boxed();
+    if let Some(status) = filter.status {
+        query = query.filter(invocations::status.eq(status.to_string()));
+    }
+    if let Some(command) = &filter.command {
+        query = query.filter(invocations::command.eq(command.clone()));
+    }
+    if let Some(pattern) = &filter.pattern {
+        query = query.filter(
+            invocations::pattern
+                .ilike(crate::like::contains(pattern))
+                .escape(crate::like::ESCAPE),
+        );
+    }
+    if let Some(from) = filter.start_from {
+        let odt: time::OffsetDateTime = from.into();
+        query = query.filter(invocations::start.ge(odt));
+    }
+    if let Some(to) = filter.start_to {
+        let odt: time::OffsetDateTime = to.into();
+        query = query.filter(invocations::start.le(odt));
+    }
+    for (key, value) in &filter.metadata {
+        let subquery = options::table
+            .into_boxed()
+            .filter(options::kind.eq("Build Metadata"))
+            .filter(options::keyval.eq(format!("{key}={value}")))
+            .select(options::invocation_id)
+            .distinct();
+        query = query.filter(invocations::id.eq_any(subquery));
+    }
+    if let Some(project_id) = &filter.project_id {
+        query = query.filter(invocations::project_id.eq(project_id.clone()));
+    }
+    if let Some(keyword) = &filter.keyword {
+        // Keywords are stored comma separated, so match whole entries only.
+        let keywords = invocations::notification_keywords;
+        let escaped = crate::like::escape(keyword);
+        query = query.filter(
+            keywords
+                .eq(keyword.clone())
+                .or(keywords
+                    .like(format!("{escaped},%"))
+                    .escape(crate::like::ESCAPE))
+                .or(keywords
+                    .like(format!("%,{escaped}"))
+                    .escape(crate::like::ESCAPE))
+                .or(keywords
+                    .like(format!("%,{escaped},%"))
+                    .escape(crate::like::ESCAPE)),
+        );
+    }
+    if let Some(tool_tag) = &filter.tool_tag {
+        query = query.filter(invocations::tool_tag.eq(tool_tag.clone()));
+    }
+    if let Some(exit_code) = &filter.exit_code {
+        query = query.filter(invocations::exit_code_name.eq(exit_code.clone()));
+    }
+    if let Some(category) = &filter.failure_category {
+        query = query.filter(invocations::failure_category.eq(category.clone()));
+    }
+    if let Some(version) = &filter.build_tool_version {
+        query = query.filter(invocations::build_tool_version.eq(version.clone()));
+    }
+    for (key, value) in &filter.workspace_status {
+        let subquery = options::table
+            .into_boxed()
+            .filter(options::kind.eq("Workspace Status"))
+            .filter(options::keyval.eq(format!("{key}={value}")))
+            .select(options::invocation_id)
+            .distinct();
+        query = query.filter(invocations::id.eq_any(subquery));
+    }
+    query
 }
 
 #[cfg(test)]
@@ -712,6 +1536,22 @@ mod tests {
 
     use super::schema;
 
+    // The ids of the invocations matching the filter, sorted.
+    fn list(db: &mut Box<dyn state::DB>, filter: state::InvocationFilter) -> Vec<String> {
+        let mut ids = db
+            .list_invocations(&state::InvocationQuery {
+                filter,
+                ..Default::default()
+            })
+            .unwrap()
+            .invocations
+            .into_iter()
+            .map(|i| i.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
     #[test]
     fn test_migration() {
         let tmp = tempdir::TempDir::new("test_invocation").unwrap();
@@ -781,6 +1621,7 @@ mod tests {
             kind: "real_rule".to_string(),
             start: std::time::SystemTime::now(),
             end: None,
+            failure: None,
         };
         db.upsert_shallow_invocation(&inv).unwrap();
         db.upsert_target("blah", &target).unwrap();
@@ -840,6 +1681,7 @@ mod tests {
         let mut test = state::Test {
             name: "//target/path:thing".to_string(),
             status: state::Status::InProgress,
+            detailed_status: None,
             duration: std::time::Duration::from_secs_f64(4.343),
             end: std::time::SystemTime::now(),
             num_runs: 0,
@@ -898,6 +1740,20 @@ mod tests {
             last_heartbeat: None,
             is_live: false,
             profile_uri: None,
+            build_id: None,
+            attempt_number: None,
+            project_id: None,
+            notification_keywords: vec![],
+            tool_tag: None,
+            exit_code: None,
+            failure_detail: None,
+            build_tool_version: None,
+            workspace_directory: None,
+            working_directory: None,
+            server_pid: None,
+            options_description: None,
+            build_user: None,
+            build_host: None,
             targets: HashMap::from([
                 (
                     "//target1".to_string(),
@@ -907,6 +1763,7 @@ mod tests {
                         kind: "real_rule".to_string(),
                         start: std::time::SystemTime::now(),
                         end: Some(std::time::SystemTime::now()),
+                        failure: None,
                     },
                 ),
                 (
@@ -917,6 +1774,7 @@ mod tests {
                         kind: "real_test".to_string(),
                         start: std::time::SystemTime::now(),
                         end: Some(std::time::SystemTime::now()),
+                        failure: None,
                     },
                 ),
             ]),
@@ -925,6 +1783,7 @@ mod tests {
                 state::Test {
                     name: "//target1:some_test".to_string(),
                     status: state::Status::Fail,
+                    detailed_status: Some(state::TestStatus::Failed),
                     duration: std::time::Duration::from_secs(5),
                     num_runs: 2,
                     end: std::time::SystemTime::now(),
@@ -934,6 +1793,7 @@ mod tests {
                             shard: 1,
                             attempt: 1,
                             status: state::Status::Success,
+                            detailed_status: Some(state::TestStatus::Passed),
                             details: "".to_string(),
                             duration: std::time::Duration::from_secs(5),
                             files: HashMap::from([
@@ -958,6 +1818,7 @@ mod tests {
                             shard: 1,
                             attempt: 1,
                             status: state::Status::Fail,
+                            detailed_status: Some(state::TestStatus::Timeout),
                             details: "".to_string(),
                             duration: std::time::Duration::from_secs(2),
                             files: HashMap::from([
@@ -1000,6 +1861,14 @@ mod tests {
         assert_eq!(new_inv.id, inv.id);
         assert_eq!(new_inv.tests.len(), inv.tests.len());
         assert_eq!(new_inv.targets.len(), inv.targets.len());
+        let new_test = &new_inv.tests["//target1:some_test"];
+        assert_eq!(new_test.detailed_status, Some(state::TestStatus::Failed));
+        assert!(
+            new_test
+                .runs
+                .iter()
+                .any(|r| r.detailed_status == Some(state::TestStatus::Timeout))
+        );
         let _ = db.get_test("blah", "//target1:some_test").unwrap();
         db.delete_invocation("blah").unwrap();
         let _ = db.get_invocation("blah").unwrap_err();
@@ -1023,11 +1892,29 @@ mod tests {
             db.upsert_shallow_invocation(&state::InvocationResults {
                 id: format!("id{i}"),
                 start: curr.checked_add(day).unwrap(),
+                build_id: match i {
+                    0 => Some("old".to_string()),
+                    1 | 4 => Some("retried".to_string()),
+                    _ => None,
+                },
                 ..Default::default()
             })
             .unwrap();
             curr += day;
         }
+        for (id, enqueued) in [
+            ("old", None),
+            ("retried", None),
+            ("abandoned", Some(start)),
+            ("enqueued", Some(start + day * 10)),
+        ] {
+            db.upsert_build(&state::Build {
+                id: id.to_string(),
+                enqueued,
+                ..Default::default()
+            })
+            .unwrap();
+        }
         {
             let res = super::schema::invocations::table
                 .select(super::models::Invocation::as_select())
@@ -1046,6 +1933,15 @@ mod tests {
             assert_eq!(res.len(), 5 - i);
             curr += day;
         }
+        // A build is deleted along with its last invocation.
+        assert!(db.get_build("old").unwrap().is_none());
+        assert!(db.get_build("retried").unwrap().is_some());
+        db.delete_invocations_since(&curr).unwrap();
+        assert!(db.get_build("retried").unwrap().is_none());
+        // Builds without attempts expire once they were enqueued long enough
+        // ago.
+        assert!(db.get_build("abandoned").unwrap().is_none());
+        assert!(db.get_build("enqueued").unwrap().is_some());
     }
 
     #[test]
@@ -1086,24 +1982,1071 @@ mod tests {
         assert_eq!(res.build_metadata, opts.build_metadata);
     }
 
-    fn make<S>(lines: &[S]) -> Vec<String>
-    where
-        S: ToString,
-    {
-        lines.iter().map(|s| s.to_string()).collect()
-    }
-
     #[test]
-    fn test_progress() {
-        let tmp = tempdir::TempDir::new("test_test").unwrap();
+    fn test_list_invocations() {
+        let tmp = tempdir::TempDir::new("test_list_invocations").unwrap();
         let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
         let uri = harness.uri();
         super::init_db(&uri).unwrap();
         let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
         let mut db = mgr.get().unwrap();
-        let inv = state::InvocationResults {
-            id: "blah".to_string(),
-            command: "test".to_string(),
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for i in 0..6u64 {
+            let inv = state::InvocationResults {
+                id: format!("inv{i}"),
+                command: if i % 2 == 0 { "test" } else { "build" }.to_string(),
+                pattern: vec![format!("//pkg{i}/...")],
+                status: if i < 3 {
+                    state::Status::Success
+                } else {
+                    state::Status::Fail
+                },
+                start: start + Duration::from_secs(i * 60),
+                ..Default::default()
+            };
+            db.upsert_shallow_invocation(&inv).unwrap();
+            db.insert_options(
+                &inv.id,
+                &state::BuildOptions {
+                    build_metadata: HashMap::from([(
+                        "USER".to_string(),
+                        if i < 2 { "alice" } else { "bob" }.to_string(),
+                    )]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        // Default query: newest first.
+        let page = db
+            .list_invocations(&state::InvocationQuery::default())
+            .unwrap();
+        assert_eq!(page.total, 6);
+        assert_eq!(
+            page.invocations
+                .iter()
+                .map(|i| i.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["inv5", "inv4", "inv3", "inv2", "inv1", "inv0"]
+        );
+
+        // Paging keeps the total count.
+        let page = db
+            .list_invocations(&state::InvocationQuery {
+                ascending: true,
+                offset: 2,
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 6);
+        assert_eq!(
+            page.invocations
+                .iter()
+                .map(|i| i.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["inv2", "inv3"]
+        );
+
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    status: Some(state::Status::Fail),
+                    command: Some("test".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv4"]
+        );
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    pattern: Some("pkg1".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv1"]
+        );
+        // LIKE wildcards in the pattern are matched literally.
+        assert!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    pattern: Some("pkg_".to_string()),
+                    ..Default::default()
+                }
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    start_from: Some(start + Duration::from_secs(60)),
+                    start_to: Some(start + Duration::from_secs(180)),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv1", "inv2", "inv3"]
+        );
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    metadata: vec![("USER".to_string(), "alice".to_string())],
+                    ..Default::default()
+                }
+            ),
+            vec!["inv0", "inv1"]
+        );
+    }
+
+    #[test]
+    fn test_get_flaky_tests() {
+        let tmp = tempdir::TempDir::new("test_get_flaky_tests").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let now = std::time::SystemTime::now();
+        for (i, status) in [state::Status::Fail, state::Status::Success]
+            .into_iter()
+            .enumerate()
+        {
+            let inv = state::InvocationResults {
+                id: format!("inv{i}"),
+                start: now - Duration::from_secs(60 * (2 - i as u64)),
+                ..Default::default()
+            };
+            db.upsert_shallow_invocation(&inv).unwrap();
+            db.insert_options(
+                &inv.id,
+                &state::BuildOptions {
+                    build_metadata: HashMap::from([("COMMIT_SHA".to_string(), "abc".to_string())]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let flip = state::Test {
+                name: "//:flip".to_string(),
+                status,
+                detailed_status: None,
+                duration: Duration::from_secs(1),
+                end: now,
+                runs: vec![],
+                num_runs: 1,
+            };
+            db.upsert_test(&inv.id, &flip).unwrap();
+            let stable = state::Test {
+                name: "//:stable".to_string(),
+                status: state::Status::Success,
+                ..flip.clone()
+            };
+            db.upsert_test(&inv.id, &stable).unwrap();
+        }
+
+        // Attempts of the same run and shard that disagree are flaky, shards
+        // that disagree are not.
+        for (name, runs) in [
+            (
+                "//:mixed",
+                [(1, 1, state::Status::Fail), (1, 2, state::Status::Success)],
+            ),
+            (
+                "//:sharded",
+                [(1, 1, state::Status::Success), (2, 1, state::Status::Fail)],
+            ),
+        ] {
+            let test = state::Test {
+                name: name.to_string(),
+                status: state::Status::Fail,
+                detailed_status: None,
+                duration: Duration::from_secs(1),
+                end: now,
+                runs: vec![],
+                num_runs: 2,
+            };
+            let test_id = db.upsert_test("inv1", &test).unwrap();
+            for (shard, attempt, status) in runs {
+                db.upsert_test_run(
+                    "inv1",
+                    &test_id,
+                    &state::TestRun {
+                        run: 1,
+                        shard,
+                        attempt,
+                        status,
+                        detailed_status: None,
+                        details: "".to_string(),
+                        duration: Duration::from_secs(1),
+                        files: HashMap::new(),
+                    },
+                )
+                .unwrap();
+            }
+        }
+
+        let flaky = db
+            .get_flaky_tests(&(now - Duration::from_secs(60 * 60)), 1, 10)
+            .unwrap();
+        let names = flaky.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["//:mixed", "//:flip"]);
+        assert_eq!(flaky[1].flaky_invocations, 1);
+        assert_eq!(flaky[1].total_invocations, 2);
+        assert_eq!(flaky[1].last_flaky_invocation.as_deref(), Some("inv1"));
+
+        // Nothing in the window.
+        assert!(db.get_flaky_tests(&now, 1, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_action_failures() {
+        let tmp = tempdir::TempDir::new("test_action_failures").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "blah".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let failure = state::ActionFailure {
+            label: "//foo:bar".to_string(),
+            mnemonic: "CppCompile".to_string(),
+            exit_code: 1,
+            command_line: vec![
+                "gcc".to_string(),
+                "-DGREETING=hello\nworld".to_string(),
+                "".to_string(),
+                "bar.cc".to_string(),
+            ],
+            primary_output: Some("bazel-out/k8-fastbuild/bin/foo/_objs/bar/bar.o".to_string()),
+            stdout_uri: None,
+            stderr_uri: Some("bytestream://localhost/blobs/abc/12".to_string()),
+        };
+        db.insert_action_failure("blah", &failure).unwrap();
+        // Re-sending the same event must not duplicate the row.
+        db.insert_action_failure("blah", &failure).unwrap();
+        assert_eq!(
+            db.get_action_failures("blah").unwrap(),
+            vec![failure.clone()]
+        );
+
+        // Actions without a primary output don't overwrite each other.
+        let no_output = state::ActionFailure {
+            primary_output: None,
+            ..failure.clone()
+        };
+        db.insert_action_failure("blah", &no_output).unwrap();
+        db.insert_action_failure("blah", &no_output).unwrap();
+        assert_eq!(db.get_action_failures("blah").unwrap().len(), 3);
+
+        db.delete_invocation("blah").unwrap();
+        assert!(db.get_action_failures("blah").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_metrics() {
+        let tmp = tempdir::TempDir::new("test_build_metrics").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+        let now = std::time::SystemTime::now();
+        for (i, id) in ["old", "new"].iter().enumerate() {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: id.to_string(),
+                start: now - std::time::Duration::from_secs(60 * (2 - i as u64)),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        assert_eq!(db.get_build_metrics("new").unwrap(), None);
+
+        let metrics = state::BuildMetrics {
+            actions_created: 10,
+            actions_executed: 8,
+            action_cache_hits: 6,
+            action_cache_misses: 2,
+            wall_time_ms: 1234,
+            actions: vec![state::ActionMetrics {
+                mnemonic: "CppCompile".to_string(),
+                actions_executed: 5,
+                first_started_ms: 1000,
+                last_ended_ms: 2000,
+                system_time: std::time::Duration::from_millis(30),
+                user_time: std::time::Duration::from_millis(400),
+            }],
+            runners: vec![state::RunnerMetrics {
+                name: "remote cache hit".to_string(),
+                exec_kind: "Remote".to_string(),
+                count: 3,
+            }],
+            ..Default::default()
+        };
+        db.upsert_build_metrics("new", &metrics).unwrap();
+        // Re-sending the event must not duplicate the breakdown rows.
+        db.upsert_build_metrics("new", &metrics).unwrap();
+        db.upsert_build_metrics(
+            "old",
+            &state::BuildMetrics {
+                actions_executed: 20,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(db.get_build_metrics("new").unwrap(), Some(metrics.clone()));
+        assert_eq!(metrics.action_cache_hit_rate(), Some(0.75));
+
+        let since = now - std::time::Duration::from_secs(3600);
+        let history = db.get_build_metrics_history(&since, 10).unwrap();
+        let ids = history
+            .iter()
+            .map(|p| p.invocation_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["old", "new"]);
+        assert_eq!(history[0].metrics.actions_executed, 20);
+        assert!(history[1].metrics.actions.is_empty());
+        // The limit keeps the most recent invocations.
+        let history = db.get_build_metrics_history(&since, 1).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].invocation_id, "new");
+
+        db.delete_invocation("new").unwrap();
+        assert_eq!(db.get_build_metrics("new").unwrap(), None);
+    }
+
+    #[test]
+    fn test_action_strategies() {
+        let tmp = tempdir::TempDir::new("test_action_strategies").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+        let now = std::time::SystemTime::now();
+        for (i, (id, pattern)) in [
+            ("a", "//..."),
+            ("b", "//foo/..."),
+            ("c", "//..."),
+            ("d", "//..."),
+        ]
+        .iter()
+        .enumerate()
+        {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: id.to_string(),
+                pattern: vec![pattern.to_string()],
+                start: now + std::time::Duration::from_secs(i as u64),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        db.insert_action_strategy("c", "//:t|1|1|1", "TestRunner", "remote")
+            .unwrap();
+        // Replayed events must not be counted twice.
+        db.insert_action_strategy("c", "//:t|1|1|1", "TestRunner", "remote")
+            .unwrap();
+        db.insert_action_strategy("c", "//:t|2|1|1", "TestRunner", "remote cache hit")
+            .unwrap();
+        db.insert_action_strategy("c", "//:u|1|1|1", "TestRunner", "remote cache hit")
+            .unwrap();
+        assert_eq!(
+            db.get_action_strategies("c").unwrap(),
+            vec![
+                state::StrategyCount {
+                    mnemonic: "TestRunner".to_string(),
+                    strategy: "remote".to_string(),
+                    count: 1,
+                },
+                state::StrategyCount {
+                    mnemonic: "TestRunner".to_string(),
+                    strategy: "remote cache hit".to_string(),
+                    count: 2,
+                },
+            ]
+        );
+
+        let prev = |db: &mut Box<dyn state::DB>, id: &str| {
+            db.get_previous_invocation(id).unwrap().map(|i| i.id)
+        };
+        assert_eq!(prev(&mut db, "d").as_deref(), Some("c"));
+        assert_eq!(prev(&mut db, "c").as_deref(), Some("a"));
+        assert_eq!(prev(&mut db, "b"), None);
+        assert_eq!(prev(&mut db, "a"), None);
+    }
+
+    #[test]
+    fn test_get_last_green() {
+        let tmp = tempdir::TempDir::new("test_get_last_green").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let now = std::time::SystemTime::now();
+        let statuses = [
+            state::Status::Success,
+            state::Status::Fail,
+            state::Status::Fail,
+        ];
+        for (i, status) in statuses.into_iter().enumerate() {
+            let inv = state::InvocationResults {
+                id: format!("inv{i}"),
+                start: now - Duration::from_secs(60 * (3 - i as u64)),
+                ..Default::default()
+            };
+            db.upsert_shallow_invocation(&inv).unwrap();
+            db.insert_options(
+                &inv.id,
+                &state::BuildOptions {
+                    explicit_cmd_line: vec!["--config=ci".to_string()],
+                    build_metadata: HashMap::from([
+                        ("BRANCH".to_string(), "main".to_string()),
+                        ("COMMIT_SHA".to_string(), format!("sha{i}")),
+                    ]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            db.upsert_test(
+                &inv.id,
+                &state::Test {
+                    name: "//:test".to_string(),
+                    status,
+                    detailed_status: None,
+                    duration: Duration::from_secs(1),
+                    end: now,
+                    runs: vec![],
+                    num_runs: 1,
+                },
+            )
+            .unwrap();
+        }
+
+        let got = db.get_last_green("inv2", "//:test", 100).unwrap();
+        let first_failed = got.first_failed.unwrap();
+        assert_eq!(first_failed.invocation_id, "inv1");
+        assert_eq!(first_failed.commit.as_deref(), Some("sha1"));
+        let last_passed = got.last_passed.unwrap();
+        assert_eq!(last_passed.invocation_id, "inv0");
+        assert_eq!(last_passed.commit.as_deref(), Some("sha0"));
+
+        // A passing test is its own last green; later invocations are ignored.
+        let got = db.get_last_green("inv0", "//:test", 100).unwrap();
+        assert!(got.first_failed.is_none());
+        assert_eq!(got.last_passed.unwrap().invocation_id, "inv0");
+    }
+
+    #[test]
+    fn test_raw_events() {
+        let tmp = tempdir::TempDir::new("test_raw_events").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "inv".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let event = |sequence: i64, kind: &str| state::RawEvent {
+            sequence,
+            kind: kind.to_string(),
+            json: format!(r#"{{"id":{{"{kind}":{{}}}},"sequence":{sequence}}}"#),
+        };
+        db.insert_raw_event("inv", &event(2, "progress")).unwrap();
+        db.insert_raw_event("inv", &event(1, "started")).unwrap();
+        db.insert_raw_event("inv", &event(3, "progress")).unwrap();
+        // Retried streams resend events with the same sequence number.
+        db.insert_raw_event("inv", &event(2, "progress")).unwrap();
+        assert_eq!(
+            db.get_raw_events("inv", None, 0, 10).unwrap(),
+            vec![
+                event(1, "started"),
+                event(2, "progress"),
+                event(3, "progress")
+            ]
+        );
+        assert_eq!(
+            db.get_raw_event_kinds("inv").unwrap(),
+            vec![("progress".to_string(), 2), ("started".to_string(), 1)]
+        );
+        assert_eq!(
+            db.get_raw_events("inv", Some("progress"), 0, 1).unwrap(),
+            vec![event(2, "progress")]
+        );
+        assert_eq!(
+            db.get_raw_events("inv", Some("progress"), 2, 10).unwrap(),
+            vec![event(3, "progress")]
+        );
+        assert!(db.get_raw_events("other", None, 0, 10).unwrap().is_empty());
+        assert!(db.get_raw_event_kinds("other").unwrap().is_empty());
+
+        db.delete_invocation("inv").unwrap();
+        assert!(db.get_raw_events("inv", None, 0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stream_sequence_number() {
+        let tmp = tempdir::TempDir::new("test_stream_sequence_number").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "inv".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            db.get_stream_sequence_number("inv", "stream").unwrap(),
+            None
+        );
+        db.update_stream_sequence_number("inv", "stream", 1)
+            .unwrap();
+        db.update_stream_sequence_number("inv", "stream", 2)
+            .unwrap();
+        db.update_stream_sequence_number("inv", "other", 5).unwrap();
+        assert_eq!(
+            db.get_stream_sequence_number("inv", "stream").unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            db.get_stream_sequence_number("inv", "other").unwrap(),
+            Some(5)
+        );
+
+        db.delete_invocation("inv").unwrap();
+        assert_eq!(
+            db.get_stream_sequence_number("inv", "stream").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_transaction() {
+        let tmp = tempdir::TempDir::new("test_transaction").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let inv = |id: &str| state::InvocationResults {
+            id: id.to_string(),
+            ..Default::default()
+        };
+        db.transaction(Box::new(|db: &mut dyn state::DB| {
+            db.upsert_shallow_invocation(&inv("committed"))?;
+            // A failed nested transaction only rolls back its own writes.
+            let res = db.transaction(Box::new(|db: &mut dyn state::DB| {
+                db.upsert_shallow_invocation(&inv("nested"))?;
+                Err(anyhow::anyhow!("nested failure"))
+            }));
+            assert!(res.is_err());
+            Ok(())
+        }))
+        .unwrap();
+        let res = db.transaction(Box::new(|db: &mut dyn state::DB| {
+            db.upsert_shallow_invocation(&inv("rolled_back"))?;
+            Err(anyhow::anyhow!("failure"))
+        }));
+        assert!(res.is_err());
+
+        assert!(db.get_shallow_invocation("committed").is_ok());
+        assert!(db.get_shallow_invocation("nested").is_err());
+        assert!(db.get_shallow_invocation("rolled_back").is_err());
+    }
+
+    #[test]
+    fn test_builds() {
+        let tmp = tempdir::TempDir::new("test_builds").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        assert_eq!(db.get_build("build").unwrap(), None);
+        let mut build = state::Build {
+            id: "build".to_string(),
+            project_id: "project".to_string(),
+            enqueued: Some(UNIX_EPOCH + Duration::from_secs(100)),
+            ..Default::default()
+        };
+        db.upsert_build(&build).unwrap();
+        build.finished = Some(UNIX_EPOCH + Duration::from_secs(200));
+        build.result = Some("COMMAND_SUCCEEDED".to_string());
+        db.upsert_build(&build).unwrap();
+        assert_eq!(db.get_build("build").unwrap(), Some(build));
+
+        for (id, attempt) in [("second", 2), ("first", 1)] {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: id.to_string(),
+                build_id: Some("build".to_string()),
+                attempt_number: Some(attempt),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "other".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let attempts = db.get_build_invocations("build").unwrap();
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|i| (i.id.as_str(), i.attempt_number))
+                .collect::<Vec<_>>(),
+            vec![("first", Some(1)), ("second", Some(2))]
+        );
+    }
+
+    #[test]
+    fn test_stream_info() {
+        use state::{TestFilter, TestFilterItem, TestFilterOp};
+
+        let tmp = tempdir::TempDir::new("test_stream_info").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let now = std::time::SystemTime::now();
+        for (i, (project_id, keywords, tool_tag)) in [
+            (Some("ci"), vec!["pipeline", "nightly"], Some("presubmit")),
+            (Some("ci"), vec!["pipeline-dev"], None),
+            (None, vec![], None),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let inv = state::InvocationResults {
+                id: format!("inv{i}"),
+                start: now - Duration::from_secs(60 * i as u64),
+                project_id: project_id.map(str::to_string),
+                notification_keywords: keywords.into_iter().map(str::to_string).collect(),
+                tool_tag: tool_tag.map(str::to_string),
+                ..Default::default()
+            };
+            db.upsert_shallow_invocation(&inv).unwrap();
+            db.upsert_test(
+                &inv.id,
+                &state::Test {
+                    name: "//:test".to_string(),
+                    status: state::Status::Success,
+                    detailed_status: None,
+                    duration: Duration::from_secs(1),
+                    end: now,
+                    num_runs: 1,
+                    runs: vec![],
+                },
+            )
+            .unwrap();
+        }
+        let inv = db.get_shallow_invocation("inv0").unwrap();
+        assert_eq!(inv.project_id.as_deref(), Some("ci"));
+        assert_eq!(inv.notification_keywords, vec!["pipeline", "nightly"]);
+        assert_eq!(inv.tool_tag.as_deref(), Some("presubmit"));
+        assert!(
+            db.get_shallow_invocation("inv2")
+                .unwrap()
+                .notification_keywords
+                .is_empty()
+        );
+
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    project_id: Some("ci".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv0", "inv1"]
+        );
+        // Keywords only match whole entries.
+        for (keyword, want) in [
+            ("pipeline", vec!["inv0"]),
+            ("nightly", vec!["inv0"]),
+            ("pipeline-dev", vec!["inv1"]),
+            ("dev", vec![]),
+            // Wildcards are matched literally.
+            ("%", vec![]),
+            ("pipeline_dev", vec![]),
+        ] {
+            assert_eq!(
+                list(
+                    &mut db,
+                    state::InvocationFilter {
+                        keyword: Some(keyword.to_string()),
+                        ..Default::default()
+                    }
+                ),
+                want
+            );
+        }
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    tool_tag: Some("presubmit".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv0"]
+        );
+
+        let mut history = |op: TestFilterOp, filter: TestFilterItem, invert: bool| {
+            db.get_test_history("//:test", &[TestFilter { op, invert, filter }], 10, None)
+                .map(|h| {
+                    let mut ids = h
+                        .history
+                        .into_iter()
+                        .map(|h| h.invocation_id)
+                        .collect::<Vec<_>>();
+                    ids.sort();
+                    ids
+                })
+        };
+        assert_eq!(
+            history(
+                TestFilterOp::Equals,
+                TestFilterItem::ProjectId("ci".to_string()),
+                false
+            )
+            .unwrap(),
+            vec!["inv0", "inv1"]
+        );
+        assert_eq!(
+            history(
+                TestFilterOp::Equals,
+                TestFilterItem::NotificationKeyword("pipeline".to_string()),
+                true
+            )
+            .unwrap(),
+            vec!["inv1", "inv2"]
+        );
+        assert_eq!(
+            history(
+                TestFilterOp::Equals,
+                TestFilterItem::ToolTag("presubmit".to_string()),
+                false
+            )
+            .unwrap(),
+            vec!["inv0"]
+        );
+        assert_eq!(
+            history(
+                TestFilterOp::Contains,
+                TestFilterItem::NotificationKeyword("line-d".to_string()),
+                false
+            )
+            .unwrap(),
+            vec!["inv1"]
+        );
+        assert_eq!(
+            history(
+                TestFilterOp::Contains,
+                TestFilterItem::ToolTag("sub".to_string()),
+                true
+            )
+            .unwrap(),
+            vec!["inv1", "inv2"]
+        );
+        assert!(
+            history(
+                TestFilterOp::GreaterThan,
+                TestFilterItem::ProjectId("ci".to_string()),
+                false
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_mark_incomplete() {
+        let tmp = tempdir::TempDir::new("test_mark_incomplete").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let now = std::time::SystemTime::now();
+        let end = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "inv".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        for (name, status) in [
+            ("//:done", state::Status::Success),
+            ("//:running", state::Status::InProgress),
+        ] {
+            db.upsert_target(
+                "inv",
+                &state::Target {
+                    name: name.to_string(),
+                    status,
+                    kind: "rule".to_string(),
+                    start: now,
+                    end: None,
+                    failure: None,
+                },
+            )
+            .unwrap();
+            db.upsert_test(
+                "inv",
+                &state::Test {
+                    name: name.to_string(),
+                    status,
+                    detailed_status: None,
+                    duration: Duration::from_secs(1),
+                    end: now,
+                    num_runs: 1,
+                    runs: vec![],
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(db.mark_incomplete("inv", end).unwrap(), 2);
+        assert_eq!(db.mark_incomplete("inv", end).unwrap(), 0);
+
+        let inv = db.get_invocation("inv").unwrap();
+        assert_eq!(inv.targets["//:done"].status, state::Status::Success);
+        assert_eq!(inv.targets["//:done"].end, None);
+        assert_eq!(inv.targets["//:running"].status, state::Status::Incomplete);
+        assert_eq!(inv.targets["//:running"].end, Some(end));
+        assert_eq!(inv.tests["//:done"].status, state::Status::Success);
+        assert_eq!(inv.tests["//:running"].status, state::Status::Incomplete);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let tmp = tempdir::TempDir::new("test_exit_code").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        let now = std::time::SystemTime::now();
+        for (i, (exit_code, failure_detail)) in [
+            (
+                Some(("BUILD_FAILURE", 1)),
+                Some(("remote_execution", "CACHE_NOT_FOUND", "cache miss")),
+            ),
+            (
+                Some(("BUILD_FAILURE", 1)),
+                Some(("execution", "NON_ACTION_EXECUTION_FAILURE", "compile error")),
+            ),
+            (Some(("SUCCESS", 0)), None),
+            (None, None),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: format!("inv{i}"),
+                start: now - Duration::from_secs(60 * i as u64),
+                exit_code: exit_code.map(|(name, code)| state::ExitCode {
+                    name: name.to_string(),
+                    code,
+                }),
+                failure_detail: failure_detail.map(|(category, code, message)| {
+                    state::FailureDetail {
+                        message: message.to_string(),
+                        category: category.to_string(),
+                        code: code.to_string(),
+                    }
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        let inv = db.get_shallow_invocation("inv0").unwrap();
+        assert_eq!(
+            inv.exit_code,
+            Some(state::ExitCode {
+                name: "BUILD_FAILURE".to_string(),
+                code: 1
+            })
+        );
+        assert_eq!(
+            inv.failure_detail,
+            Some(state::FailureDetail {
+                message: "cache miss".to_string(),
+                category: "remote_execution".to_string(),
+                code: "CACHE_NOT_FOUND".to_string(),
+            })
+        );
+        let inv = db.get_shallow_invocation("inv3").unwrap();
+        assert_eq!(inv.exit_code, None);
+        assert_eq!(inv.failure_detail, None);
+
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    exit_code: Some("BUILD_FAILURE".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv0", "inv1"]
+        );
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    exit_code: Some("BUILD_FAILURE".to_string()),
+                    failure_category: Some("remote_execution".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv0"]
+        );
+    }
+
+    #[test]
+    fn test_target_failure() {
+        let tmp = tempdir::TempDir::new("test_target_failure").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "inv".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        for name in ["//:lib", "//:bin"] {
+            db.upsert_target(
+                "inv",
+                &state::Target {
+                    name: name.to_string(),
+                    status: state::Status::Fail,
+                    kind: "rule".to_string(),
+                    start: std::time::SystemTime::now(),
+                    end: None,
+                    failure: None,
+                },
+            )
+            .unwrap();
+        }
+        let failure = state::TargetFailure {
+            message: "compile failed".to_string(),
+            category: "spawn".to_string(),
+            // Target names may contain commas.
+            root_causes: vec!["//:lib".to_string(), "//:a,b".to_string()],
+        };
+        db.update_target_failure("inv", "//:bin", &failure).unwrap();
+
+        let inv = db.get_invocation("inv").unwrap();
+        assert_eq!(inv.targets["//:bin"].failure, Some(failure));
+        assert_eq!(inv.targets["//:lib"].failure, None);
+    }
+
+    #[test]
+    fn test_build_info() {
+        let tmp = tempdir::TempDir::new("test_build_info").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+
+        for (i, (version, host)) in [("7.4.1", "ci-1"), ("8.0.0", "ci-1"), ("8.0.0", "ci-2")]
+            .into_iter()
+            .enumerate()
+        {
+            let id = format!("inv{i}");
+            db.upsert_shallow_invocation(&state::InvocationResults {
+                id: id.clone(),
+                build_tool_version: Some(version.to_string()),
+                build_host: Some(host.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+            db.insert_options(
+                &id,
+                &state::BuildOptions {
+                    workspace_status: HashMap::from([
+                        ("BUILD_HOST".to_string(), host.to_string()),
+                        ("BUILD_USER".to_string(), "alice".to_string()),
+                    ]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            db.get_options("inv2").unwrap().workspace_status["BUILD_HOST"],
+            "ci-2"
+        );
+
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    build_tool_version: Some("8.0.0".to_string()),
+                    ..Default::default()
+                }
+            ),
+            vec!["inv1", "inv2"]
+        );
+        assert_eq!(
+            list(
+                &mut db,
+                state::InvocationFilter {
+                    build_tool_version: Some("8.0.0".to_string()),
+                    workspace_status: vec![("BUILD_HOST".to_string(), "ci-1".to_string())],
+                    ..Default::default()
+                }
+            ),
+            vec!["inv1"]
+        );
+    }
+
+    fn make<S>(lines: &[S]) -> Vec<String>
+    where
+        S: ToString,
+    {
+        lines.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_progress() {
+        let tmp = tempdir::TempDir::new("test_test").unwrap();
+        let harness = harness::new(tmp.path().to_str().unwrap()).unwrap();
+        let uri = harness.uri();
+        super::init_db(&uri).unwrap();
+        let mgr = crate::manager::PostgresManager::new(&uri).unwrap();
+        let mut db = mgr.get().unwrap();
+        let inv = state::InvocationResults {
+            id: "blah".to_string(),
+            command: "test".to_string(),
             status: state::Status::Fail,
             start: std::time::SystemTime::now(),
             is_live: false,
@@ -1117,6 +3060,33 @@ mod tests {
         db.delete_last_output_lines(&inv.id, 2_u32).unwrap();
         let prog = db.get_progress(&inv.id).unwrap();
         assert_eq!(prog, "a\nb");
+
+        let all = db.get_output_since(&inv.id, 0).unwrap();
+        assert_eq!(all.kept, None);
+        assert_eq!(
+            all.lines
+                .iter()
+                .map(|(_, l)| l.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        let last_id = all.lines[1].0;
+        assert_eq!(
+            db.get_output_since(&inv.id, last_id).unwrap(),
+            state::OutputSince::default()
+        );
+        db.delete_last_output_lines(&inv.id, 1_u32).unwrap();
+        db.insert_output_lines(&inv.id, make(&["e"])).unwrap();
+        let since = db.get_output_since(&inv.id, last_id).unwrap();
+        assert_eq!(since.kept, Some(1));
+        assert_eq!(
+            since
+                .lines
+                .iter()
+                .map(|(_, l)| l.as_str())
+                .collect::<Vec<_>>(),
+            vec!["e"]
+        );
     }
 
     #[test]
@@ -1143,6 +3113,7 @@ mod tests {
         let test1 = state::Test {
             name: test_name.to_string(),
             status: Status::Success,
+            detailed_status: None,
             duration: Duration::from_secs(5),
             end: now,
             num_runs: 0,
@@ -1171,6 +3142,7 @@ mod tests {
         let test2 = state::Test {
             name: test_name.to_string(),
             status: Status::Fail,
+            detailed_status: None,
             duration: Duration::from_secs(12),
             end: inv2_time,
             num_runs: 0,
@@ -1198,6 +3170,7 @@ mod tests {
         let test3 = state::Test {
             name: test_name.to_string(),
             status: Status::Success,
+            detailed_status: None,
             duration: Duration::from_secs(6),
             end: inv3_time,
             num_runs: 0,
@@ -1306,6 +3279,7 @@ mod tests {
         let test1 = state::Test {
             name: "//path/to/test:one".to_string(),
             status: state::Status::Success,
+            detailed_status: None,
             duration: Duration::from_secs(1),
             end: SystemTime::now(),
             num_runs: 1,
@@ -1321,6 +3295,7 @@ mod tests {
         let test2 = state::Test {
             name: "//path/to/test:two".to_string(),
             status: state::Status::Success,
+            detailed_status: None,
             duration: Duration::from_secs(2),
             end: SystemTime::now(),
             num_runs: 1,
@@ -1338,6 +3313,7 @@ mod tests {
         let test1_updated = state::Test {
             name: "//path/to/test:one".to_string(),
             status: state::Status::Fail,
+            detailed_status: None,
             duration: Duration::from_secs(5),
             end: SystemTime::now(),
             num_runs: 2,
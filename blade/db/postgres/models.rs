@@ -25,6 +25,23 @@ pub struct Invocation {
     pub pattern: Option<String>,
     pub last_heartbeat: Option<time::OffsetDateTime>,
     pub profile_uri: Option<String>,
+    pub build_id: Option<String>,
+    pub attempt_number: Option<i64>,
+    pub project_id: Option<String>,
+    pub notification_keywords: Option<String>,
+    pub tool_tag: Option<String>,
+    pub exit_code_name: Option<String>,
+    pub exit_code: Option<i32>,
+    pub failure_message: Option<String>,
+    pub failure_category: Option<String>,
+    pub failure_code: Option<String>,
+    pub build_tool_version: Option<String>,
+    pub workspace_directory: Option<String>,
+    pub working_directory: Option<String>,
+    pub server_pid: Option<i64>,
+    pub options_description: Option<String>,
+    pub build_user: Option<String>,
+    pub build_host: Option<String>,
 }
 
 impl Invocation {
@@ -38,6 +55,27 @@ impl Invocation {
             pattern: Some(ir.pattern.join(",")),
             last_heartbeat: ir.last_heartbeat.map(core::convert::Into::into),
             profile_uri: ir.profile_uri.clone(),
+            build_id: ir.build_id.clone(),
+            attempt_number: ir.attempt_number,
+            project_id: ir.project_id.clone(),
+            notification_keywords: (!ir.notification_keywords.is_empty())
+                .then(|| ir.notification_keywords.join(",")),
+            tool_tag: ir.tool_tag.clone(),
+            exit_code_name: ir.exit_code.as_ref().map(|e| e.name.clone()),
+            exit_code: ir.exit_code.as_ref().map(|e| e.code),
+            failure_message: ir.failure_detail.as_ref().map(|f| f.message.clone()),
+            failure_category: ir.failure_detail.as_ref().map(|f| f.category.clone()),
+            failure_code: ir.failure_detail.as_ref().map(|f| f.code.clone()),
+            build_tool_version: ir.build_tool_version.clone(),
+            workspace_directory: ir.workspace_directory.clone(),
+            working_directory: ir.working_directory.clone(),
+            server_pid: ir.server_pid,
+            options_description: ir
+                .options_description
+                .as_deref()
+                .map(crate::envscrub::scrub),
+            build_user: ir.build_user.clone(),
+            build_host: ir.build_host.clone(),
         })
     }
 
@@ -60,6 +98,31 @@ impl Invocation {
                 crate::time::to_systemtime(&h).unwrap_or_else(|_| std::time::SystemTime::now())
             }),
             profile_uri: self.profile_uri,
+            build_id: self.build_id,
+            attempt_number: self.attempt_number,
+            project_id: self.project_id,
+            notification_keywords: self
+                .notification_keywords
+                .as_ref()
+                .map(|k| k.split(',').map(|s| s.to_string()).collect::<Vec<_>>())
+                .unwrap_or_default(),
+            tool_tag: self.tool_tag,
+            exit_code: self.exit_code.map(|code| state::ExitCode {
+                name: self.exit_code_name.unwrap_or_default(),
+                code,
+            }),
+            failure_detail: self.failure_category.map(|category| state::FailureDetail {
+                message: self.failure_message.unwrap_or_default(),
+                category,
+                code: self.failure_code.unwrap_or_default(),
+            }),
+            build_tool_version: self.build_tool_version,
+            workspace_directory: self.workspace_directory,
+            working_directory: self.working_directory,
+            server_pid: self.server_pid,
+            options_description: self.options_description,
+            build_user: self.build_user,
+            build_host: self.build_host,
             ..Default::default()
         };
 
@@ -94,6 +157,9 @@ pub struct Target {
     pub kind: String,
     pub start: time::OffsetDateTime,
     pub end: Option<time::OffsetDateTime>,
+    pub failure_message: Option<String>,
+    pub failure_category: Option<String>,
+    pub root_causes: Option<String>,
 }
 
 impl Target {
@@ -108,8 +174,32 @@ impl Target {
             kind: t.kind.clone(),
             start: t.start.into(),
             end: t.end.map(core::convert::Into::into),
+            failure_message: t.failure.as_ref().map(|f| f.message.clone()),
+            failure_category: t.failure.as_ref().map(|f| f.category.clone()),
+            root_causes: t
+                .failure
+                .as_ref()
+                .filter(|f| !f.root_causes.is_empty())
+                .map(|f| serde_json::to_string(&f.root_causes))
+                .transpose()?,
         })
     }
+
+    pub fn failure(&self) -> anyhow::Result<Option<state::TargetFailure>> {
+        let Some(category) = &self.failure_category else {
+            return Ok(None);
+        };
+        Ok(Some(state::TargetFailure {
+            message: self.failure_message.clone().unwrap_or_default(),
+            category: category.clone(),
+            root_causes: self
+                .root_causes
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?
+                .unwrap_or_default(),
+        }))
+    }
 }
 
 #[derive(
@@ -136,6 +226,7 @@ pub struct Test {
     pub duration_s: Option<f64>,
     pub end: time::OffsetDateTime,
     pub num_runs: Option<i32>,
+    pub detailed_status: Option<String>,
 }
 
 impl Test {
@@ -150,6 +241,7 @@ impl Test {
             end: t.end.into(),
             duration_s: Some(t.duration.as_secs_f64()),
             num_runs: Some(t.num_runs as i32),
+            detailed_status: t.detailed_status.map(|s| s.to_string()),
         })
     }
 
@@ -163,6 +255,10 @@ impl Test {
             num_runs: self.num_runs.unwrap_or(0) as usize,
             runs: vec![],
             status: state::Status::parse(&self.status),
+            detailed_status: self
+                .detailed_status
+                .as_deref()
+                .and_then(state::TestStatus::parse),
             end: crate::time::to_systemtime(&self.end)
                 .unwrap_or_else(|_| std::time::SystemTime::now()),
         }
@@ -196,6 +292,7 @@ pub struct TestRun {
     pub status: String,
     pub details: String,
     pub duration_s: f64,
+    pub detailed_status: Option<String>,
 }
 
 impl TestRun {
@@ -230,6 +327,7 @@ impl TestRun {
             status: t.status.to_string(),
             details: t.details.to_string(),
             duration_s: t.duration.as_secs_f64(),
+            detailed_status: t.detailed_status.map(|s| s.to_string()),
         })
     }
 }
@@ -281,6 +379,442 @@ impl TestArtifact {
     }
 }
 
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::actionfailures)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ActionFailure {
+    pub id: String,
+    pub invocation_id: String,
+    pub label: String,
+    pub mnemonic: String,
+    pub exit_code: i32,
+    pub command_line: String,
+    pub primary_output: Option<String>,
+    pub stdout_uri: Option<String>,
+    pub stderr_uri: Option<String>,
+}
+
+impl ActionFailure {
+    // Retried events for the same action must update the same row, but actions
+    // without a primary output can't be told apart, so they get a random id.
+    pub fn gen_id(invocation_id: &str, a: &state::ActionFailure) -> String {
+        match &a.primary_output {
+            Some(output) => [invocation_id, &a.label, &a.mnemonic, output].join("|"),
+            None => uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    pub fn from_state(invocation_id: &str, a: &state::ActionFailure) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: Self::gen_id(invocation_id, a),
+            invocation_id: invocation_id.to_string(),
+            label: a.label.clone(),
+            mnemonic: a.mnemonic.clone(),
+            exit_code: a.exit_code,
+            command_line: serde_json::to_string(&a.command_line)?,
+            primary_output: a.primary_output.clone(),
+            stdout_uri: a.stdout_uri.clone(),
+            stderr_uri: a.stderr_uri.clone(),
+        })
+    }
+
+    pub fn into_state(self) -> anyhow::Result<state::ActionFailure> {
+        Ok(state::ActionFailure {
+            label: self.label,
+            mnemonic: self.mnemonic,
+            exit_code: self.exit_code,
+            command_line: serde_json::from_str(&self.command_line)?,
+            primary_output: self.primary_output,
+            stdout_uri: self.stdout_uri,
+            stderr_uri: self.stderr_uri,
+        })
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::buildmetrics)]
+#[diesel(primary_key(invocation_id))]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BuildMetrics {
+    pub invocation_id: String,
+    pub actions_created: i64,
+    pub actions_executed: i64,
+    pub action_cache_hits: i64,
+    pub action_cache_misses: i64,
+    pub used_heap_size_post_build: i64,
+    pub peak_post_gc_heap_size: i64,
+    pub targets_loaded: i64,
+    pub targets_configured: i64,
+    pub packages_loaded: i64,
+    pub cpu_time_ms: i64,
+    pub wall_time_ms: i64,
+    pub analysis_phase_time_ms: i64,
+    pub execution_phase_time_ms: i64,
+    pub source_artifacts_read_bytes: i64,
+    pub source_artifacts_read_count: i64,
+    pub output_artifacts_seen_bytes: i64,
+    pub output_artifacts_seen_count: i64,
+    pub output_artifacts_from_action_cache_bytes: i64,
+    pub output_artifacts_from_action_cache_count: i64,
+    pub top_level_artifacts_bytes: i64,
+    pub top_level_artifacts_count: i64,
+}
+
+impl BuildMetrics {
+    pub fn from_state(invocation_id: &str, m: &state::BuildMetrics) -> Self {
+        Self {
+            invocation_id: invocation_id.to_string(),
+            actions_created: m.actions_created,
+            actions_executed: m.actions_executed,
+            action_cache_hits: m.action_cache_hits,
+            action_cache_misses: m.action_cache_misses,
+            used_heap_size_post_build: m.used_heap_size_post_build,
+            peak_post_gc_heap_size: m.peak_post_gc_heap_size,
+            targets_loaded: m.targets_loaded,
+            targets_configured: m.targets_configured,
+            packages_loaded: m.packages_loaded,
+            cpu_time_ms: m.cpu_time_ms,
+            wall_time_ms: m.wall_time_ms,
+            analysis_phase_time_ms: m.analysis_phase_time_ms,
+            execution_phase_time_ms: m.execution_phase_time_ms,
+            source_artifacts_read_bytes: m.source_artifacts_read_bytes,
+            source_artifacts_read_count: m.source_artifacts_read_count,
+            output_artifacts_seen_bytes: m.output_artifacts_seen_bytes,
+            output_artifacts_seen_count: m.output_artifacts_seen_count,
+            output_artifacts_from_action_cache_bytes: m.output_artifacts_from_action_cache_bytes,
+            output_artifacts_from_action_cache_count: m.output_artifacts_from_action_cache_count,
+            top_level_artifacts_bytes: m.top_level_artifacts_bytes,
+            top_level_artifacts_count: m.top_level_artifacts_count,
+        }
+    }
+
+    // Per mnemonic and runner breakdowns are stored separately.
+    pub fn into_state(self) -> state::BuildMetrics {
+        state::BuildMetrics {
+            actions_created: self.actions_created,
+            actions_executed: self.actions_executed,
+            action_cache_hits: self.action_cache_hits,
+            action_cache_misses: self.action_cache_misses,
+            used_heap_size_post_build: self.used_heap_size_post_build,
+            peak_post_gc_heap_size: self.peak_post_gc_heap_size,
+            targets_loaded: self.targets_loaded,
+            targets_configured: self.targets_configured,
+            packages_loaded: self.packages_loaded,
+            cpu_time_ms: self.cpu_time_ms,
+            wall_time_ms: self.wall_time_ms,
+            analysis_phase_time_ms: self.analysis_phase_time_ms,
+            execution_phase_time_ms: self.execution_phase_time_ms,
+            source_artifacts_read_bytes: self.source_artifacts_read_bytes,
+            source_artifacts_read_count: self.source_artifacts_read_count,
+            output_artifacts_seen_bytes: self.output_artifacts_seen_bytes,
+            output_artifacts_seen_count: self.output_artifacts_seen_count,
+            output_artifacts_from_action_cache_bytes: self.output_artifacts_from_action_cache_bytes,
+            output_artifacts_from_action_cache_count: self.output_artifacts_from_action_cache_count,
+            top_level_artifacts_bytes: self.top_level_artifacts_bytes,
+            top_level_artifacts_count: self.top_level_artifacts_count,
+            actions: vec![],
+            runners: vec![],
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::actionmetrics)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ActionMetrics {
+    pub id: String,
+    pub invocation_id: String,
+    pub mnemonic: String,
+    pub actions_executed: i64,
+    pub first_started_ms: i64,
+    pub last_ended_ms: i64,
+    pub system_time_ms: i64,
+    pub user_time_ms: i64,
+}
+
+impl ActionMetrics {
+    pub fn gen_id(invocation_id: &str, mnemonic: &str) -> String {
+        [invocation_id, mnemonic].join("|")
+    }
+
+    pub fn from_state(invocation_id: &str, a: &state::ActionMetrics) -> Self {
+        Self {
+            id: Self::gen_id(invocation_id, &a.mnemonic),
+            invocation_id: invocation_id.to_string(),
+            mnemonic: a.mnemonic.clone(),
+            actions_executed: a.actions_executed,
+            first_started_ms: a.first_started_ms,
+            last_ended_ms: a.last_ended_ms,
+            system_time_ms: a.system_time.as_millis() as i64,
+            user_time_ms: a.user_time.as_millis() as i64,
+        }
+    }
+
+    pub fn into_state(self) -> state::ActionMetrics {
+        state::ActionMetrics {
+            mnemonic: self.mnemonic,
+            actions_executed: self.actions_executed,
+            first_started_ms: self.first_started_ms,
+            last_ended_ms: self.last_ended_ms,
+            system_time: std::time::Duration::from_millis(self.system_time_ms.max(0) as u64),
+            user_time: std::time::Duration::from_millis(self.user_time_ms.max(0) as u64),
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::runnermetrics)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RunnerMetrics {
+    pub id: String,
+    pub invocation_id: String,
+    pub name: String,
+    pub exec_kind: String,
+    pub count: i32,
+}
+
+impl RunnerMetrics {
+    pub fn gen_id(invocation_id: &str, name: &str, exec_kind: &str) -> String {
+        [invocation_id, name, exec_kind].join("|")
+    }
+
+    pub fn from_state(invocation_id: &str, r: &state::RunnerMetrics) -> Self {
+        Self {
+            id: Self::gen_id(invocation_id, &r.name, &r.exec_kind),
+            invocation_id: invocation_id.to_string(),
+            name: r.name.clone(),
+            exec_kind: r.exec_kind.clone(),
+            count: r.count,
+        }
+    }
+
+    pub fn into_state(self) -> state::RunnerMetrics {
+        state::RunnerMetrics {
+            name: self.name,
+            exec_kind: self.exec_kind,
+            count: self.count,
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::actionstrategies)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ActionStrategy {
+    pub id: String,
+    pub invocation_id: String,
+    pub mnemonic: String,
+    pub strategy: String,
+}
+
+impl ActionStrategy {
+    pub fn new(invocation_id: &str, key: &str, mnemonic: &str, strategy: &str) -> Self {
+        Self {
+            id: [invocation_id, key].join("|"),
+            invocation_id: invocation_id.to_string(),
+            mnemonic: mnemonic.to_string(),
+            strategy: strategy.to_string(),
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::rawevents)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RawEvent {
+    pub id: String,
+    pub invocation_id: String,
+    pub sequence: i64,
+    pub kind: String,
+    pub payload: Vec<u8>,
+}
+
+impl RawEvent {
+    pub fn from_state(invocation_id: &str, event: &state::RawEvent) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: [invocation_id, &event.sequence.to_string()].join("|"),
+            invocation_id: invocation_id.to_string(),
+            sequence: event.sequence,
+            kind: event.kind.clone(),
+            payload: crate::rawevents::compress(&event.json)?,
+        })
+    }
+
+    pub fn into_state(self) -> anyhow::Result<state::RawEvent> {
+        Ok(state::RawEvent {
+            sequence: self.sequence,
+            kind: self.kind,
+            json: crate::rawevents::decompress(&self.payload)?,
+        })
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    Associations,
+)]
+#[diesel(table_name = super::schema::streams)]
+#[diesel(belongs_to(Invocation, foreign_key = invocation_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Stream {
+    pub id: String,
+    pub invocation_id: String,
+    pub sequence_number: i64,
+}
+
+impl Stream {
+    pub fn new(invocation_id: &str, stream: &str, sequence_number: i64) -> Self {
+        Self {
+            id: [invocation_id, stream].join("|"),
+            invocation_id: invocation_id.to_string(),
+            sequence_number,
+        }
+    }
+}
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Eq,
+    PartialEq,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+)]
+#[diesel(table_name = super::schema::builds)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Build {
+    pub id: String,
+    pub project_id: String,
+    pub enqueued: Option<time::OffsetDateTime>,
+    pub finished: Option<time::OffsetDateTime>,
+    pub result: Option<String>,
+}
+
+impl Build {
+    pub fn from_state(b: &state::Build) -> Self {
+        Self {
+            id: b.id.clone(),
+            project_id: b.project_id.clone(),
+            enqueued: b.enqueued.map(core::convert::Into::into),
+            finished: b.finished.map(core::convert::Into::into),
+            result: b.result.clone(),
+        }
+    }
+
+    pub fn into_state(self) -> state::Build {
+        state::Build {
+            id: self.id,
+            project_id: self.project_id,
+            enqueued: self
+                .enqueued
+                .and_then(|t| crate::time::to_systemtime(&t).ok()),
+            finished: self
+                .finished
+                .and_then(|t| crate::time::to_systemtime(&t).ok()),
+            result: self.result,
+        }
+    }
+}
+
 #[derive(
     Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Insertable, AsChangeset, Associations,
 )]
@@ -290,3 +824,32 @@ pub struct InvocationOutput {
     pub invocation_id: String,
     pub line: String,
 }
+
+/// A row of the flaky test leaderboard query.
+#[derive(Debug, QueryableByName)]
+pub struct FlakyTest {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub total_invocations: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub flaky_invocations: i64,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub last_flaky_invocation: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>)]
+    pub last_flaky_start: Option<time::OffsetDateTime>,
+}
+
+impl FlakyTest {
+    pub fn into_state(self) -> state::FlakyTest {
+        state::FlakyTest {
+            name: self.name,
+            total_invocations: self.total_invocations as usize,
+            flaky_invocations: self.flaky_invocations as usize,
+            last_flaky_invocation: self.last_flaky_invocation,
+            last_flaky_start: self
+                .last_flaky_start
+                .and_then(|t| crate::time::to_systemtime(&t).ok()),
+        }
+    }
+}
@@ -1,5 +1,78 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    actionfailures (id) {
+        id -> Text,
+        invocation_id -> Text,
+        label -> Text,
+        mnemonic -> Text,
+        exit_code -> Int4,
+        command_line -> Text,
+        primary_output -> Nullable<Text>,
+        stdout_uri -> Nullable<Text>,
+        stderr_uri -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    actionmetrics (id) {
+        id -> Text,
+        invocation_id -> Text,
+        mnemonic -> Text,
+        actions_executed -> Int8,
+        first_started_ms -> Int8,
+        last_ended_ms -> Int8,
+        system_time_ms -> Int8,
+        user_time_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    actionstrategies (id) {
+        id -> Text,
+        invocation_id -> Text,
+        mnemonic -> Text,
+        strategy -> Text,
+    }
+}
+
+diesel::table! {
+    buildmetrics (invocation_id) {
+        invocation_id -> Text,
+        actions_created -> Int8,
+        actions_executed -> Int8,
+        action_cache_hits -> Int8,
+        action_cache_misses -> Int8,
+        used_heap_size_post_build -> Int8,
+        peak_post_gc_heap_size -> Int8,
+        targets_loaded -> Int8,
+        targets_configured -> Int8,
+        packages_loaded -> Int8,
+        cpu_time_ms -> Int8,
+        wall_time_ms -> Int8,
+        analysis_phase_time_ms -> Int8,
+        execution_phase_time_ms -> Int8,
+        source_artifacts_read_bytes -> Int8,
+        source_artifacts_read_count -> Int8,
+        output_artifacts_seen_bytes -> Int8,
+        output_artifacts_seen_count -> Int8,
+        output_artifacts_from_action_cache_bytes -> Int8,
+        output_artifacts_from_action_cache_count -> Int8,
+        top_level_artifacts_bytes -> Int8,
+        top_level_artifacts_count -> Int8,
+    }
+}
+
+diesel::table! {
+    builds (id) {
+        id -> Text,
+        project_id -> Text,
+        enqueued -> Nullable<Timestamptz>,
+        finished -> Nullable<Timestamptz>,
+        result -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     invocations (id) {
         id -> Text,
@@ -10,6 +83,23 @@ diesel::table! {
         pattern -> Nullable<Text>,
         last_heartbeat -> Nullable<Timestamptz>,
         profile_uri -> Nullable<Text>,
+        build_id -> Nullable<Text>,
+        attempt_number -> Nullable<BigInt>,
+        project_id -> Nullable<Text>,
+        notification_keywords -> Nullable<Text>,
+        tool_tag -> Nullable<Text>,
+        exit_code_name -> Nullable<Text>,
+        exit_code -> Nullable<Integer>,
+        failure_message -> Nullable<Text>,
+        failure_category -> Nullable<Text>,
+        failure_code -> Nullable<Text>,
+        build_tool_version -> Nullable<Text>,
+        workspace_directory -> Nullable<Text>,
+        working_directory -> Nullable<Text>,
+        server_pid -> Nullable<BigInt>,
+        options_description -> Nullable<Text>,
+        build_user -> Nullable<Text>,
+        build_host -> Nullable<Text>,
     }
 }
 
@@ -22,6 +112,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    rawevents (id) {
+        id -> Text,
+        invocation_id -> Text,
+        sequence -> BigInt,
+        kind -> Text,
+        payload -> Bytea,
+    }
+}
+
+diesel::table! {
+    runnermetrics (id) {
+        id -> Text,
+        invocation_id -> Text,
+        name -> Text,
+        exec_kind -> Text,
+        count -> Int4,
+    }
+}
+
+diesel::table! {
+    streams (id) {
+        id -> Text,
+        invocation_id -> Text,
+        sequence_number -> BigInt,
+    }
+}
+
 diesel::table! {
     targets (id) {
         id -> Text,
@@ -31,6 +149,9 @@ diesel::table! {
         kind -> Text,
         start -> Timestamptz,
         end -> Nullable<Timestamptz>,
+        failure_message -> Nullable<Text>,
+        failure_category -> Nullable<Text>,
+        root_causes -> Nullable<Text>,
     }
 }
 
@@ -55,6 +176,7 @@ diesel::table! {
         status -> Text,
         details -> Text,
         duration_s -> Float8,
+        detailed_status -> Nullable<Text>,
     }
 }
 
@@ -67,6 +189,7 @@ diesel::table! {
         duration_s -> Nullable<Float8>,
         end -> Timestamptz,
         num_runs -> Nullable<Int4>,
+        detailed_status -> Nullable<Text>,
     }
 }
 
@@ -84,7 +207,14 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(actionfailures -> invocations (invocation_id));
+diesel::joinable!(actionmetrics -> invocations (invocation_id));
+diesel::joinable!(actionstrategies -> invocations (invocation_id));
+diesel::joinable!(buildmetrics -> invocations (invocation_id));
 diesel::joinable!(options -> invocations (invocation_id));
+diesel::joinable!(rawevents -> invocations (invocation_id));
+diesel::joinable!(runnermetrics -> invocations (invocation_id));
+diesel::joinable!(streams -> invocations (invocation_id));
 diesel::joinable!(targets -> invocations (invocation_id));
 diesel::joinable!(testartifacts -> invocations (invocation_id));
 diesel::joinable!(testartifacts -> testruns (test_run_id));
@@ -94,9 +224,17 @@ diesel::joinable!(tests -> invocations (invocation_id));
 diesel::joinable!(invocationoutput -> invocations (invocation_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    actionfailures,
+    actionmetrics,
+    actionstrategies,
+    buildmetrics,
+    builds,
     invocations,
     invocationoutput,
     options,
+    rawevents,
+    runnermetrics,
+    streams,
     targets,
     testartifacts,
     testruns,
@@ -0,0 +1,34 @@
+use std::io::{Read, Write};
+
+use anyhow::Context;
+
+// Build events are verbose JSON with lots of repeated keys and paths, so they
+// compress well.
+pub(crate) fn compress(json: &str) -> anyhow::Result<Vec<u8>> {
+    let mut enc = flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+    enc.write_all(json.as_bytes())
+        .context("failed to compress event")?;
+    enc.finish().context("failed to compress event")
+}
+
+pub(crate) fn decompress(payload: &[u8]) -> anyhow::Result<String> {
+    let mut json = String::new();
+    flate2::read::DeflateDecoder::new(payload)
+        .read_to_string(&mut json)
+        .context("failed to decompress event")?;
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let json = r#"{"id":{"progress":{}},"progress":{"stdout":"hello"}}"#.repeat(10);
+        let payload = compress(&json).unwrap();
+        assert!(payload.len() < json.len());
+        assert_eq!(decompress(&payload).unwrap(), json);
+        assert!(decompress(b"not deflate").is_err());
+    }
+}
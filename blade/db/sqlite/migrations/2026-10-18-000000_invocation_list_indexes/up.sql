@@ -0,0 +1,4 @@
+-- The invocation list page filters by status/command and orders by start time.
+CREATE INDEX IF NOT EXISTS invocations_start_idx ON Invocations (start);
+CREATE INDEX IF NOT EXISTS invocations_status_start_idx ON Invocations (status, start);
+CREATE INDEX IF NOT EXISTS invocations_command_start_idx ON Invocations (command, start);
@@ -0,0 +1,2 @@
+ALTER TABLE TestRuns DROP COLUMN detailed_status;
+ALTER TABLE Tests DROP COLUMN detailed_status;
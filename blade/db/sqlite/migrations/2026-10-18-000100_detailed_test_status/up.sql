@@ -0,0 +1,2 @@
+ALTER TABLE Tests ADD COLUMN detailed_status TEXT;
+ALTER TABLE TestRuns ADD COLUMN detailed_status TEXT;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS RunnerMetrics;
+DROP TABLE IF EXISTS ActionMetrics;
+DROP TABLE IF EXISTS BuildMetrics;
@@ -0,0 +1,14 @@
+-- The payload is the deflate compressed JSON of the event. Events are keyed by
+-- their sequence number so that retried streams overwrite instead of
+-- duplicating them.
+CREATE TABLE RawEvents (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    sequence BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES Invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS rawevents_inv_seq ON RawEvents (invocation_id, sequence);
+CREATE INDEX IF NOT EXISTS rawevents_inv_kind ON RawEvents (invocation_id, kind, sequence);
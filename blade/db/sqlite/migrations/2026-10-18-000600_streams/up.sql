@@ -0,0 +1,10 @@
+-- Last build event processed per BES stream, so that events replayed by a
+-- retried upload are not processed twice.
+CREATE TABLE Streams (
+    id TEXT NOT NULL PRIMARY KEY,
+    invocation_id TEXT NOT NULL,
+    sequence_number BIGINT NOT NULL,
+    FOREIGN KEY(invocation_id) REFERENCES Invocations(id)
+        ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS streams_inv_id ON Streams (invocation_id);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS invocations_build_id;
+ALTER TABLE Invocations DROP COLUMN attempt_number;
+ALTER TABLE Invocations DROP COLUMN build_id;
+DROP TABLE IF EXISTS Builds;
@@ -0,0 +1,12 @@
+-- Builds and invocation attempts as reported by BES lifecycle events. A build
+-- can be retried with several invocation attempts.
+CREATE TABLE Builds (
+    id TEXT NOT NULL PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    enqueued TEXT,
+    finished TEXT,
+    result TEXT
+);
+ALTER TABLE Invocations ADD COLUMN build_id TEXT;
+ALTER TABLE Invocations ADD COLUMN attempt_number BIGINT;
+CREATE INDEX IF NOT EXISTS invocations_build_id ON Invocations (build_id);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS invocations_project_id;
+ALTER TABLE Invocations DROP COLUMN tool_tag;
+ALTER TABLE Invocations DROP COLUMN notification_keywords;
+ALTER TABLE Invocations DROP COLUMN project_id;
@@ -0,0 +1,6 @@
+-- Set from the BES stream and the --tool_tag option. Keywords are stored comma
+-- separated, like the target patterns.
+ALTER TABLE Invocations ADD COLUMN project_id TEXT;
+ALTER TABLE Invocations ADD COLUMN notification_keywords TEXT;
+ALTER TABLE Invocations ADD COLUMN tool_tag TEXT;
+CREATE INDEX IF NOT EXISTS invocations_project_id ON Invocations (project_id);
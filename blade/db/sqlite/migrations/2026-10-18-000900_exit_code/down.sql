@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS invocations_exit_code_name;
+ALTER TABLE Invocations DROP COLUMN failure_code;
+ALTER TABLE Invocations DROP COLUMN failure_category;
+ALTER TABLE Invocations DROP COLUMN failure_message;
+ALTER TABLE Invocations DROP COLUMN exit_code;
+ALTER TABLE Invocations DROP COLUMN exit_code_name;
@@ -0,0 +1,3 @@
+ALTER TABLE Targets DROP COLUMN root_causes;
+ALTER TABLE Targets DROP COLUMN failure_category;
+ALTER TABLE Targets DROP COLUMN failure_message;
@@ -0,0 +1,4 @@
+-- Why a target failed. Root causes are stored as a JSON array of labels.
+ALTER TABLE Targets ADD COLUMN failure_message TEXT;
+ALTER TABLE Targets ADD COLUMN failure_category TEXT;
+ALTER TABLE Targets ADD COLUMN root_causes TEXT;
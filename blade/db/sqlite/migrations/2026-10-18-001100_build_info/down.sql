@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS invocations_build_tool_version;
+ALTER TABLE Invocations DROP COLUMN build_host;
+ALTER TABLE Invocations DROP COLUMN build_user;
+ALTER TABLE Invocations DROP COLUMN options_description;
+ALTER TABLE Invocations DROP COLUMN server_pid;
+ALTER TABLE Invocations DROP COLUMN working_directory;
+ALTER TABLE Invocations DROP COLUMN workspace_directory;
+ALTER TABLE Invocations DROP COLUMN build_tool_version;
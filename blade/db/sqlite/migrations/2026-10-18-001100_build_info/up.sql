@@ -0,0 +1,10 @@
+-- Set from BuildStarted and the BUILD_USER and BUILD_HOST workspace status
+-- keys. The other workspace status keys are stored with the options.
+ALTER TABLE Invocations ADD COLUMN build_tool_version TEXT;
+ALTER TABLE Invocations ADD COLUMN workspace_directory TEXT;
+ALTER TABLE Invocations ADD COLUMN working_directory TEXT;
+ALTER TABLE Invocations ADD COLUMN server_pid BIGINT;
+ALTER TABLE Invocations ADD COLUMN options_description TEXT;
+ALTER TABLE Invocations ADD COLUMN build_user TEXT;
+ALTER TABLE Invocations ADD COLUMN build_host TEXT;
+CREATE INDEX IF NOT EXISTS invocations_build_tool_version ON Invocations (build_tool_version);
@@ -1,5 +1,78 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    ActionFailures (id) {
+        id -> Text,
+        invocation_id -> Text,
+        label -> Text,
+        mnemonic -> Text,
+        exit_code -> Integer,
+        command_line -> Text,
+        primary_output -> Nullable<Text>,
+        stdout_uri -> Nullable<Text>,
+        stderr_uri -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    ActionMetrics (id) {
+        id -> Text,
+        invocation_id -> Text,
+        mnemonic -> Text,
+        actions_executed -> BigInt,
+        first_started_ms -> BigInt,
+        last_ended_ms -> BigInt,
+        system_time_ms -> BigInt,
+        user_time_ms -> BigInt,
+    }
+}
+
+diesel::table! {
+    ActionStrategies (id) {
+        id -> Text,
+        invocation_id -> Text,
+        mnemonic -> Text,
+        strategy -> Text,
+    }
+}
+
+diesel::table! {
+    BuildMetrics (invocation_id) {
+        invocation_id -> Text,
+        actions_created -> BigInt,
+        actions_executed -> BigInt,
+        action_cache_hits -> BigInt,
+        action_cache_misses -> BigInt,
+        used_heap_size_post_build -> BigInt,
+        peak_post_gc_heap_size -> BigInt,
+        targets_loaded -> BigInt,
+        targets_configured -> BigInt,
+        packages_loaded -> BigInt,
+        cpu_time_ms -> BigInt,
+        wall_time_ms -> BigInt,
+        analysis_phase_time_ms -> BigInt,
+        execution_phase_time_ms -> BigInt,
+        source_artifacts_read_bytes -> BigInt,
+        source_artifacts_read_count -> BigInt,
+        output_artifacts_seen_bytes -> BigInt,
+        output_artifacts_seen_count -> BigInt,
+        output_artifacts_from_action_cache_bytes -> BigInt,
+        output_artifacts_from_action_cache_count -> BigInt,
+        top_level_artifacts_bytes -> BigInt,
+        top_level_artifacts_count -> BigInt,
+    }
+}
+
+diesel::table! {
+    Builds (id) {
+        id -> Text,
+        project_id -> Text,
+        enqueued -> Nullable<TimestamptzSqlite>,
+        finished -> Nullable<TimestamptzSqlite>,
+        result -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     Invocations (id) {
         id -> Text,
@@ -10,6 +83,23 @@ diesel::table! {
         pattern -> Nullable<Text>,
         last_heartbeat -> Nullable<TimestamptzSqlite>,
         profile_uri -> Nullable<Text>,
+        build_id -> Nullable<Text>,
+        attempt_number -> Nullable<BigInt>,
+        project_id -> Nullable<Text>,
+        notification_keywords -> Nullable<Text>,
+        tool_tag -> Nullable<Text>,
+        exit_code_name -> Nullable<Text>,
+        exit_code -> Nullable<Integer>,
+        failure_message -> Nullable<Text>,
+        failure_category -> Nullable<Text>,
+        failure_code -> Nullable<Text>,
+        build_tool_version -> Nullable<Text>,
+        workspace_directory -> Nullable<Text>,
+        working_directory -> Nullable<Text>,
+        server_pid -> Nullable<BigInt>,
+        options_description -> Nullable<Text>,
+        build_user -> Nullable<Text>,
+        build_host -> Nullable<Text>,
     }
 }
 
@@ -22,6 +112,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    RawEvents (id) {
+        id -> Text,
+        invocation_id -> Text,
+        sequence -> BigInt,
+        kind -> Text,
+        payload -> Binary,
+    }
+}
+
+diesel::table! {
+    RunnerMetrics (id) {
+        id -> Text,
+        invocation_id -> Text,
+        name -> Text,
+        exec_kind -> Text,
+        count -> Integer,
+    }
+}
+
+diesel::table! {
+    Streams (id) {
+        id -> Text,
+        invocation_id -> Text,
+        sequence_number -> BigInt,
+    }
+}
+
 diesel::table! {
     Targets (id) {
         id -> Text,
@@ -31,6 +149,9 @@ diesel::table! {
         kind -> Text,
         start -> TimestamptzSqlite,
         end -> Nullable<TimestamptzSqlite>,
+        failure_message -> Nullable<Text>,
+        failure_category -> Nullable<Text>,
+        root_causes -> Nullable<Text>,
     }
 }
 
@@ -55,6 +176,7 @@ diesel::table! {
         status -> Text,
         details -> Text,
         duration_s -> Double,
+        detailed_status -> Nullable<Text>,
     }
 }
 
@@ -67,6 +189,7 @@ diesel::table! {
         duration_s -> Nullable<Double>,
         end -> TimestamptzSqlite,
         num_runs -> Nullable<Integer>,
+        detailed_status -> Nullable<Text>,
     }
 }
 
@@ -84,7 +207,14 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(ActionFailures -> Invocations (invocation_id));
+diesel::joinable!(ActionMetrics -> Invocations (invocation_id));
+diesel::joinable!(ActionStrategies -> Invocations (invocation_id));
+diesel::joinable!(BuildMetrics -> Invocations (invocation_id));
 diesel::joinable!(Options -> Invocations (invocation_id));
+diesel::joinable!(RawEvents -> Invocations (invocation_id));
+diesel::joinable!(RunnerMetrics -> Invocations (invocation_id));
+diesel::joinable!(Streams -> Invocations (invocation_id));
 diesel::joinable!(Targets -> Invocations (invocation_id));
 diesel::joinable!(TestArtifacts -> Invocations (invocation_id));
 diesel::joinable!(TestArtifacts -> TestRuns (test_run_id));
@@ -94,8 +224,16 @@ diesel::joinable!(Tests -> Invocations (invocation_id));
 diesel::joinable!(InvocationOutput -> Invocations (invocation_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    ActionFailures,
+    ActionMetrics,
+    ActionStrategies,
+    BuildMetrics,
+    Builds,
     Invocations,
     Options,
+    RawEvents,
+    RunnerMetrics,
+    Streams,
     Targets,
     TestArtifacts,
     TestRuns,
@@ -45,6 +45,7 @@ cfg_if! {
 
 
         pub mod admin;
+        pub mod api;
 
         use routes::app::App;
 
@@ -119,12 +120,44 @@ cfg_if! {
             retention: Option<std::time::Duration>,
             #[arg(short='s', long="session_lock_time", value_name = "LOCK_TIME", value_parser = humantime::parse_duration, default_value="1h")]
             session_lock_time: std::time::Duration,
+            // In progress invocations without a heartbeat for this long are
+            // marked as interrupted.
+            #[arg(long="abandon_after", value_name="DURATION", value_parser = humantime::parse_duration, default_value="1h")]
+            abandon_after: std::time::Duration,
+            // Keep a compressed copy of every build event for the events page,
+            // for all invocations or only for the selected ones.
+            #[arg(long="store_raw_events", value_name="STORE_RAW_EVENTS", default_value="false")]
+            store_raw_events: bool,
+            #[arg(long="raw_events_invocation", value_name="INVOCATION_ID")]
+            raw_events_invocations: Vec<String>,
+            #[arg(long="raw_events_metadata", value_name="KEY=VALUE")]
+            raw_events_metadata: Vec<String>,
             #[arg(long="flame", value_name = "FLAME")]
             flame_path: Option<String>,
             #[arg(long="json", value_name="JSON", default_value="false")]
             json: bool,
             #[arg(long="rstack_child", value_name="RSTACK_CHILD", default_value="false")]
             rstack_child: bool,
+            // Directory to write raw BES streams to for later replay with
+            // streamstress. Only selected invocations are recorded.
+            #[arg(long="record_dir", value_name="DIR")]
+            record_dir: Option<std::path::PathBuf>,
+            #[arg(long="record_invocation", value_name="INVOCATION_ID")]
+            record_invocations: Vec<String>,
+            #[arg(long="record_metadata", value_name="KEY=VALUE")]
+            record_metadata: Vec<String>,
+            // Directory to keep build events in while the database is
+            // unavailable. They are stored once it is back.
+            #[arg(long="spool_dir", value_name="DIR")]
+            spool_dir: Option<std::path::PathBuf>,
+            // Upstream BES backends, e.g. http://bes.example.com:1985, that
+            // receive a copy of every stream.
+            #[arg(long="forward_to", value_name="BES_BACKEND")]
+            forward_to: Vec<String>,
+            // Import recorded --build_event_binary_file or --build_event_json_file
+            // output into the database and exit instead of serving.
+            #[arg(long="import", value_name="BEP_FILE")]
+            import: Vec<String>,
 
             #[arg(long="version", default_value="false")]
             version: bool,
@@ -256,8 +289,21 @@ cfg_if! {
                 }
             }
             let db_manager = db::new(&args.db_path)?;
-            let state = Arc::new(state::Global { db_manager, allow_local: args.allow_local, bytestream_client: bs, retention: args.retention, session_lock_time: args.session_lock_time });
+            let state = Arc::new(state::Global { db_manager, allow_local: args.allow_local, bytestream_client: bs, retention: args.retention, session_lock_time: args.session_lock_time, raw_events: state::RawEventSelection {
+                all: args.store_raw_events,
+                invocation_ids: args.raw_events_invocations.iter().cloned().collect(),
+                metadata: args.raw_events_metadata.iter().filter_map(|kv| kv.split_once('=')).map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            } });
+            if !args.import.is_empty() {
+                for path in &args.import {
+                    let id = bep::import_file(state.clone(), path).await?;
+                    tracing::info!("Imported {path} as invocation {id}");
+                }
+                return Ok(());
+            }
+            let re_handle = Arc::new(Mutex::new(regex::Regex::new(&args.debug_message_pattern)?));
             let actix_state = state.clone();
+            let actix_re_handle = re_handle.clone();
             let cleanup_state = state.clone();
             tracing::info!("Starting blade server at: {}", addr.to_string());
             // NOTE: Ensure we have at least 2 workers so that a single long-running
@@ -274,6 +320,7 @@ cfg_if! {
             let fut1 = HttpServer::new(move || {
                 let leptos_options = conf.leptos_options.clone();
                 let rt_state = actix_state.clone();
+                let api_state = actix_state.clone();
                 let routes = generate_route_list(App);
                 App::new()
                     // serve JS/WASM/CSS and other assets from `/assets`
@@ -281,6 +328,9 @@ cfg_if! {
                     // serve the favicon from /favicon.ico
                     .service(favicon)
                     .service(healthz)
+                    .app_data(web::Data::new(api_state))
+                    .app_data(web::Data::new(actix_re_handle.clone()))
+                    .service(api::service())
                     .leptos_routes_with_context(
                         routes,
                         move|| provide_context(rt_state.clone()),
@@ -306,16 +356,24 @@ cfg_if! {
             .workers(workers)
             .bind(&addr)?
             .run();
-            let re_handle = Arc::new(Mutex::new(regex::Regex::new(&args.debug_message_pattern)?));
-            let fut2 = bep::run_bes_grpc(args.grpc_host, state, re_handle.clone());
-            let fut3 = periodic_cleanup(cleanup_state);
-            let fut4 = admin::run_admin_server(args.admin_host, filter_tx, span_tx, re_handle);
+            let record_config = args.record_dir.clone().map(|dir| Arc::new(bep::RecordConfig {
+                dir,
+                invocation_ids: args.record_invocations.iter().cloned().collect(),
+                metadata: args.record_metadata.iter().filter_map(|kv| kv.split_once('=')).map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }));
+            let spool = args.spool_dir.as_deref().map(bep::Spool::open).transpose()?.map(Arc::new);
+            let forwarder = (!args.forward_to.is_empty()).then(|| bep::Forwarder::new(&args.forward_to)).transpose()?.map(Arc::new);
+            let fut2 = bep::run_bes_grpc(args.grpc_host, state, re_handle.clone(), record_config, spool.clone(), forwarder);
+            let fut3 = periodic_cleanup(cleanup_state.clone());
+            let fut4 = admin::run_admin_server(args.admin_host, filter_tx, span_tx, re_handle, spool);
+            let fut5 = periodic_reaper(cleanup_state, args.abandon_after);
 
             tokio::select! {
                 e = fut1 => { tracing::info!("Main HTTP server finished: {e:#?}"); },
                 e = fut2 => { tracing::info!("gRPC server finished: {e:#?}"); },
                 e = fut3 => { tracing::info!("periodic cleanup finished: {e:#?}"); },
                 e = fut4 => { tracing::info!("Admin HTTP server finished: {e:#?}"); },
+                e = fut5 => { tracing::info!("periodic reaper finished: {e:#?}"); },
                 _ = set_filter_fut => {},
                 _ = set_span_fut => {},
             }
@@ -357,6 +415,17 @@ cfg_if! {
             }
         }
 
+        #[instrument]
+        async fn periodic_reaper(global: Arc<state::Global>, threshold: std::time::Duration) {
+            let check_interval = (threshold / 4).clamp(std::time::Duration::from_secs(10), std::time::Duration::from_secs(60 * 5));
+            loop {
+                tokio::time::sleep(check_interval).await;
+                if let Err(e) = bep::reap_abandoned(global.clone(), threshold).await {
+                    tracing::warn!("Failed to mark abandoned invocations as interrupted: {e:#?}");
+                }
+            }
+        }
+
         #[derive(Default)]
         pub(crate) struct BladeRootSpanBuilder;
 
@@ -6,10 +6,15 @@ use leptos_router::{components::*, path};
 
 use crate::{
     artifact::Artifact,
+    compare::Compare,
     dashboard::Dashboard,
     details::Details,
     empty::Empty,
+    events::Events,
+    flaky::FlakyTests,
     invocation::Invocation,
+    invocations::Invocations,
+    metrics::InvocationMetrics,
     profile::BazelProfile,
     summary::Summary,
     test::Test,
@@ -48,8 +53,13 @@ pub fn App() -> impl IntoView {
                             <Route path=path!("details") view=Details />
                             <Route path=path!("artifact") view=Artifact />
                             <Route path=path!("profile") view=BazelProfile />
+                            <Route path=path!("metrics") view=InvocationMetrics />
+                            <Route path=path!("events") view=Events />
                             <Route path=path!("*any") view=Summary />
                         </ParentRoute>
+                        <Route path=path!("invocations") view=Invocations />
+                        <Route path=path!("compare/:a/:b") view=Compare />
+                        <Route path=path!("flaky") view=FlakyTests />
                         <Route path=path!("dashboard") view=Dashboard />
                         <Route path=path!("/") view=Dashboard />
                         <Route path=path!("*any") view=Empty />
@@ -0,0 +1,75 @@
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+use components::invocationdiff::InvocationDiffView;
+use leptos::{either::Either, prelude::*};
+use leptos_meta::Title;
+use leptos_router::{hooks::use_params, params::Params};
+
+#[server]
+pub async fn compare_invocations(
+    before: String,
+    after: String,
+) -> Result<state::InvocationDiff, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run_group(global.db_manager.clone(), move |db| {
+        let b = db.get_invocation(&before)?;
+        let b_opts = db.get_options(&before)?;
+        let a = db.get_invocation(&after)?;
+        let a_opts = db.get_options(&after)?;
+        Ok(state::InvocationDiff::new(&b, &b_opts, &a, &a_opts))
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
+#[derive(PartialEq, Params)]
+pub(crate) struct CompareParams {
+    pub(crate) a: Option<String>,
+    pub(crate) b: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn Compare() -> impl IntoView {
+    let params = use_params::<CompareParams>();
+    let ids = move || {
+        params.with(|p| {
+            p.as_ref()
+                .map(|p| {
+                    (
+                        p.a.clone().unwrap_or_default(),
+                        p.b.clone().unwrap_or_default(),
+                    )
+                })
+                .unwrap_or_default()
+        })
+    };
+    let diff = Resource::new(ids, |(a, b)| compare_invocations(a, b));
+
+    view! {
+        <Title text="Compare" />
+        <div class="p-4 bg-white dark:bg-gray-800 text-gray-900 dark:text-white h-[calc(100vh-64px)] overflow-y-auto">
+            <h1 class="text-3xl font-bold mb-6">"Compare Invocations"</h1>
+            <Suspense fallback=|| {
+                view! { <p class="text-gray-400">"Loading..."</p> }
+            }>
+                {move || {
+                    diff.get()
+                        .map(|res| match res {
+                            Ok(diff) => Either::Left(view! { <InvocationDiffView diff=diff /> }),
+                            Err(e) => {
+                                Either::Right(
+                                    view! {
+                                        <p class="text-red-500 mt-8 text-center">
+                                            {format!("Failed to compare invocations: {e}")}
+                                        </p>
+                                    },
+                                )
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
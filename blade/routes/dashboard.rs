@@ -6,13 +6,14 @@ use components::{
     dashboard::{
         filters::FilterControls,
         graphs::HistoryGraphs,
+        metrics_trends::MetricsTrends,
         test_history_table::TestHistoryTable,
         test_search::TestSearchInput,
     },
 };
 use leptos::{either::Either, prelude::*};
 use leptos_router::{hooks::use_query, params::Params};
-use state::{TestFilter, TestHistory};
+use state::{BuildMetricsPoint, TestFilter, TestHistory};
 
 #[server]
 pub async fn get_test_history(
@@ -36,6 +37,24 @@ pub async fn get_test_history(
     .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
 }
 
+#[server]
+pub async fn get_build_metrics_history(
+    days: Option<u32>,
+    limit: Option<usize>,
+) -> Result<Vec<BuildMetricsPoint>, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    let since = std::time::SystemTime::now()
+        .checked_sub(std::time::Duration::from_secs(
+            days.unwrap_or(30) as u64 * 24 * 60 * 60,
+        ))
+        .unwrap_or(std::time::UNIX_EPOCH);
+    db::run(global.db_manager.clone(), move |db| {
+        db.get_build_metrics_history(&since, limit.unwrap_or(500))
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
 #[derive(PartialEq, Params)]
 pub(crate) struct DashboardParams {
     pub(crate) test_name: Option<String>,
@@ -72,6 +91,18 @@ pub fn Dashboard() -> impl IntoView {
         },
     );
 
+    let metrics_resource = Resource::new(
+        || (),
+        |_| async move {
+            get_build_metrics_history(None, None)
+                .await
+                .inspect_err(|e| {
+                    tracing::warn!("Failed to get build metrics history: {e:#?}");
+                })
+                .unwrap_or_default()
+        },
+    );
+
     view! {
         <div class="p-4 bg-white dark:bg-gray-800 text-gray-900 dark:text-white h-[calc(100vh-64px)] overflow-y-auto">
             <div class="container mx-auto">
@@ -111,6 +142,18 @@ pub fn Dashboard() -> impl IntoView {
                             })
                     }}
                 </Suspense>
+
+                <h2 class="text-2xl font-bold mt-12 mb-2">"Build Metrics Trends"</h2>
+                <p class="text-gray-500 text-sm">"Last 30 days"</p>
+                <Suspense fallback=|| {
+                    view! { <p class="text-gray-400">"Loading..."</p> }
+                }>
+                    {move || {
+                        metrics_resource
+                            .get()
+                            .map(|history| view! { <MetricsTrends history=history /> })
+                    }}
+                </Suspense>
             </div>
         </div>
     }
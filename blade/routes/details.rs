@@ -6,9 +6,11 @@ use components::{
     accordion::*,
     card::Card,
     list::{List, ListItem},
-    summaryheader::SummaryHeader,
+    statusicon::StatusIcon,
+    summaryheader::{SummaryHeader, format_time},
 };
 use leptos::prelude::*;
+use leptos_router::components::A;
 
 #[server]
 pub async fn get_options(uuid: String) -> Result<state::BuildOptions, ServerFnError> {
@@ -21,6 +23,23 @@ pub async fn get_options(uuid: String) -> Result<state::BuildOptions, ServerFnEr
         .map_err(crate::invocation::internal_err)
 }
 
+/// Returns the build of an invocation attempt along with all its attempts.
+#[server]
+pub async fn get_build(
+    build_id: String,
+) -> Result<Option<(state::Build, Vec<state::InvocationResults>)>, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run_group(global.db_manager.clone(), move |db| {
+        let Some(build) = db.get_build(&build_id)? else {
+            return Ok(None);
+        };
+        let attempts = db.get_build_invocations(&build_id)?;
+        Ok(Some((build, attempts)))
+    })
+    .await
+    .map_err(crate::invocation::internal_err)
+}
+
 #[allow(non_snake_case)]
 #[component]
 pub fn Details() -> impl IntoView {
@@ -29,6 +48,15 @@ pub fn Details() -> impl IntoView {
         move || invocation.with(|invocation| invocation.id.clone()),
         get_options,
     );
+    let build = Resource::new(
+        move || invocation.with(|invocation| invocation.build_id.clone()),
+        |build_id| async move {
+            match build_id {
+                Some(build_id) => get_build(build_id).await.ok().flatten(),
+                None => None,
+            }
+        },
+    );
 
     view! {
         <div class="flex flex-col m-1 p-1 dark:bg-gray-800">
@@ -36,6 +64,31 @@ pub fn Details() -> impl IntoView {
                 <SummaryHeader />
             </Card>
 
+            <Suspense>
+                {move || {
+                    build
+                        .get()
+                        .flatten()
+                        .map(|(build, attempts)| {
+                            view! {
+                                <Card class="p-3 m-2">
+                                    <BuildAttempts build=build attempts=attempts />
+                                </Card>
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || {
+                let info = build_info(&invocation.read());
+                (!info.is_empty())
+                    .then(|| {
+                        view! {
+                            <Card class="p-3 m-2">
+                                <BuildInfo info=info />
+                            </Card>
+                        }
+                    })
+            }}
             <div class="h-[73dvh] flex items-start justify-start justify-items-center overflow-auto">
                 <Card class="p-3 m-2 max-w-full w-full">
                     <Suspense fallback=move || {
@@ -62,6 +115,20 @@ pub fn Details() -> impl IntoView {
                                                         </AccordionItem>
                                                     }
                                                 })}
+                                            {(!opts.workspace_status.is_empty())
+                                                .then(move || {
+                                                    view! {
+                                                        <AccordionItem
+                                                            hide=false
+                                                            header=move || {
+                                                                view! { <h3>Workspace Status</h3> }
+                                                            }
+                                                        >
+
+                                                            <BuildMetadata md=opts.workspace_status />
+                                                        </AccordionItem>
+                                                    }
+                                                })}
                                             <AccordionItem
                                                 hide=false
                                                 header=move || {
@@ -123,6 +190,105 @@ pub fn Details() -> impl IntoView {
     }
 }
 
+// What BuildStarted and the workspace status reported about where and how the
+// build ran.
+fn build_info(i: &state::InvocationResults) -> Vec<(&'static str, String)> {
+    [
+        ("Bazel version", i.build_tool_version.clone()),
+        ("User", i.build_user.clone()),
+        ("Host", i.build_host.clone()),
+        ("Workspace directory", i.workspace_directory.clone()),
+        ("Working directory", i.working_directory.clone()),
+        ("Server PID", i.server_pid.map(|p| p.to_string())),
+        ("Options", i.options_description.clone()),
+    ]
+    .into_iter()
+    .filter_map(|(k, v)| v.map(|v| (k, v)))
+    .collect()
+}
+
+#[component]
+fn BuildInfo(info: Vec<(&'static str, String)>) -> impl IntoView {
+    view! {
+        <div class="grid grid-cols-[max-content_1fr] gap-x-4 gap-y-1 text-sm">
+            {info
+                .into_iter()
+                .map(|(k, v)| {
+                    view! {
+                        <span class="font-semibold">{k}</span>
+                        <span class="font-mono break-all">{v}</span>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+#[component]
+fn BuildAttempts(build: state::Build, attempts: Vec<state::InvocationResults>) -> impl IntoView {
+    let invocation = expect_context::<RwSignal<state::InvocationResults>>();
+    let current = invocation.with_untracked(|i| i.id.clone());
+    // Time spent waiting between the build being enqueued and this attempt
+    // starting.
+    let queued = build.enqueued.and_then(|enqueued| {
+        invocation
+            .with_untracked(|i| i.start.duration_since(enqueued).ok())
+            .map(|d| humantime::format_duration(std::time::Duration::from_secs(d.as_secs())))
+    });
+    view! {
+        <div class="flex flex-col gap-1 text-sm">
+            <div>
+                <span class="font-semibold">"Build "</span>
+                <span class="font-mono">{build.id.clone()}</span>
+                {(!build.project_id.is_empty())
+                    .then(|| format!(" in project {}", build.project_id))}
+                {build.result.clone().map(|r| format!(", finished with {r}"))}
+            </div>
+            {build
+                .enqueued
+                .map(|t| {
+                    view! {
+                        <div class="text-gray-500">
+                            {format!("Enqueued {}", format_time(&t))}
+                            {queued.map(|d| format!(", this attempt started {d} later"))}
+                        </div>
+                    }
+                })}
+            <List>
+                {attempts
+                    .into_iter()
+                    .map(|a| {
+                        let status = Signal::derive(move || a.status);
+                        let label = format!(
+                            "Attempt {} started {}",
+                            a.attempt_number.unwrap_or_default(),
+                            format_time(&a.start),
+                        );
+                        view! {
+                            <ListItem hide=Signal::derive(|| false)>
+                                <div class="flex gap-2 items-center">
+                                    <StatusIcon class="h-4 w-4" status=status />
+                                    {if a.id == current {
+                                        view! { <span class="font-semibold">{label}</span> }
+                                            .into_any()
+                                    } else {
+                                        view! {
+                                            <A href=format!("/invocation/{}", a.id)>
+                                                <span class="text-blue-500 underline">{label}</span>
+                                            </A>
+                                        }
+                                            .into_any()
+                                    }}
+                                </div>
+                            </ListItem>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </List>
+        </div>
+    }
+}
+
 #[component]
 fn OptionsList(opts: Vec<String>) -> impl IntoView {
     view! {
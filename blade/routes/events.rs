@@ -0,0 +1,290 @@
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+use components::{card::Card, summaryheader::SummaryHeader};
+use leptos::{either::Either, leptos_dom::helpers::event_target_value, prelude::*};
+use serde::{Deserialize, Serialize};
+
+// Rendering every event of a large build would hang the browser.
+#[cfg(feature = "ssr")]
+const MAX_EVENTS: usize = 500;
+// Payloads are stored compressed, so searching them means reading every event
+// of the kind. They are read in pages to bound the memory used.
+#[cfg(feature = "ssr")]
+const SEARCH_PAGE_SIZE: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RawEvents {
+    // Every event kind of the invocation and how often it occurs.
+    pub kinds: Vec<(String, usize)>,
+    // The matching events with pretty printed JSON, in sequence order.
+    pub events: Vec<state::RawEvent>,
+    // Number of matching events, including those that were cut off.
+    pub total: usize,
+}
+
+// Events are stored as compact JSON but shown pretty printed, so whitespace is
+// ignored to find text copied from either.
+#[cfg(feature = "ssr")]
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(feature = "ssr")]
+fn load_events(
+    db: &mut dyn state::DB,
+    uuid: &str,
+    kind: &str,
+    search: &str,
+) -> anyhow::Result<RawEvents> {
+    let kinds = db.get_raw_event_kinds(uuid)?;
+    let kind = Some(kind).filter(|k| !k.is_empty());
+    if search.is_empty() {
+        return Ok(RawEvents {
+            total: kinds
+                .iter()
+                .filter(|(k, _)| kind.is_none_or(|kind| k == kind))
+                .map(|(_, count)| count)
+                .sum(),
+            events: db.get_raw_events(uuid, kind, 0, MAX_EVENTS)?,
+            kinds,
+        });
+    }
+    let search = normalize(search);
+    let mut events = vec![];
+    let mut total = 0;
+    let mut after = 0;
+    loop {
+        let page = db.get_raw_events(uuid, kind, after, SEARCH_PAGE_SIZE)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = last.sequence;
+        for e in page {
+            if normalize(&e.json).contains(&search) {
+                total += 1;
+                if events.len() < MAX_EVENTS {
+                    events.push(e);
+                }
+            }
+        }
+    }
+    Ok(RawEvents {
+        kinds,
+        events,
+        total,
+    })
+}
+
+#[cfg(feature = "ssr")]
+fn pretty_print(events: &mut RawEvents) {
+    events.events.iter_mut().for_each(|e| {
+        if let Ok(v) = serde_json::from_str::<serde_json::Value>(&e.json)
+            && let Ok(pretty) = serde_json::to_string_pretty(&v)
+        {
+            e.json = pretty;
+        }
+    });
+}
+
+/// Returns the stored build events of an invocation, optionally restricted to
+/// a single event kind and to events whose JSON contains `search`.
+#[server]
+pub async fn get_raw_events(
+    uuid: String,
+    kind: String,
+    search: String,
+) -> Result<RawEvents, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    let mut events = db::run(global.db_manager.clone(), move |db| {
+        load_events(db, &uuid, &kind, &search)
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))?;
+    pretty_print(&mut events);
+    Ok(events)
+}
+
+#[allow(non_snake_case)]
+#[component]
+fn EventList(events: RawEvents) -> impl IntoView {
+    let shown = events.events.len();
+    let total = events.total;
+    view! {
+        <div class="text-sm text-gray-500 p-2">
+            {if shown < total {
+                format!("Showing the first {shown} of {total} matching events.")
+            } else {
+                format!("{total} matching events.")
+            }}
+        </div>
+        <div class="flex flex-col gap-1">
+            {events
+                .events
+                .into_iter()
+                .map(|e| {
+                    view! {
+                        <details class="border border-gray-200 dark:border-gray-600 rounded">
+                            <summary class="cursor-pointer p-2 hover:bg-gray-100 dark:hover:bg-gray-700">
+                                <span class="font-mono text-gray-500 pr-2">
+                                    {format!("#{}", e.sequence)}
+                                </span>
+                                <span class="font-semibold">{e.kind}</span>
+                            </summary>
+                            <pre class="p-2 text-xs overflow-x-auto bg-gray-50 dark:bg-gray-900">
+                                {e.json}
+                            </pre>
+                        </details>
+                    }
+                })
+                .collect::<Vec<_>>()}
+        </div>
+    }
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn Events() -> impl IntoView {
+    let invocation = expect_context::<RwSignal<state::InvocationResults>>();
+    let (kind, set_kind) = signal(String::new());
+    let (search, set_search) = signal(String::new());
+    let res = Resource::new(
+        move || {
+            (
+                invocation.with(|invocation| (invocation.id.clone(), invocation.end)),
+                kind.get(),
+                search.get(),
+            )
+        },
+        |((id, _), kind, search)| get_raw_events(id, kind, search),
+    );
+
+    view! {
+        <div class="flex flex-col m-1 p-1 dark:bg-gray-800">
+            <Card class="p-3 m-2">
+                <SummaryHeader />
+            </Card>
+            <Card class="p-3 m-2">
+                <div class="flex space-x-4 items-end">
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                            "Event kind"
+                        </label>
+                        <select
+                            class="p-2 bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md dark:text-white"
+                            prop:value=move || kind.get()
+                            on:change=move |ev| set_kind.set(event_target_value(&ev))
+                        >
+                            <option value="">"All"</option>
+                            {move || {
+                                res.get()
+                                    .and_then(|res| res.ok())
+                                    .map(|res| {
+                                        res.kinds
+                                            .into_iter()
+                                            .map(|(k, count)| {
+                                                view! {
+                                                    <option value=k.clone() selected=k == kind.get_untracked()>
+                                                        {format!("{k} ({count})")}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect::<Vec<_>>()
+                                    })
+                            }}
+                        </select>
+                    </div>
+                    <div class="grow">
+                        <label class="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                            "Search payloads"
+                        </label>
+                        <input
+                            type="search"
+                            class="p-2 w-full bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md dark:text-white"
+                            prop:value=move || search.get()
+                            on:change=move |ev| set_search.set(event_target_value(&ev))
+                        />
+                    </div>
+                </div>
+            </Card>
+            <div class="h-[60dvh] overflow-auto m-2">
+                <Suspense fallback=move || {
+                    view! { <div class="text-center py-8">"Loading build events..."</div> }
+                }>
+                    {move || {
+                        res.get()
+                            .map(|res| match res {
+                                Ok(events) if events.kinds.is_empty() => {
+                                    Either::Left(
+                                        view! {
+                                            <div class="text-center py-8 text-gray-500">
+                                                "No build events were stored for this invocation. Start Blade with --store_raw_events or select it with --raw_events_invocation or --raw_events_metadata to keep them."
+                                            </div>
+                                        },
+                                    )
+                                }
+                                Ok(events) => {
+                                    Either::Right(Either::Left(view! { <EventList events=events /> }))
+                                }
+                                Err(e) => {
+                                    Either::Right(
+                                        Either::Right(
+                                            view! {
+                                                <div class="text-center py-8 text-red-500">
+                                                    {format!("Failed to load build events: {e}")}
+                                                </div>
+                                            },
+                                        ),
+                                    )
+                                }
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_events() {
+        let tmp = tempdir::TempDir::new("test_load_events").unwrap();
+        let mut db = testonly::sqlite(&tmp).get().unwrap();
+        db.upsert_shallow_invocation(&state::InvocationResults {
+            id: "inv".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        for (sequence, name) in [(1, "a b"), (2, "x")] {
+            db.insert_raw_event(
+                "inv",
+                &state::RawEvent {
+                    sequence,
+                    kind: "targetComplete".to_string(),
+                    json: format!(r#"{{"name":"{name}"}}"#),
+                },
+            )
+            .unwrap();
+        }
+        let mut search = |search: &str| {
+            load_events(db.as_mut(), "inv", "", search)
+                .unwrap()
+                .events
+                .into_iter()
+                .map(|e| e.sequence)
+                .collect::<Vec<_>>()
+        };
+
+        // As copied from the pretty printed page.
+        assert_eq!(search(r#""name": "x""#), vec![2]);
+        assert_eq!(search(r#""NAME":"X""#), vec![2]);
+        assert_eq!(search("name"), vec![1, 2]);
+        assert_eq!(search("ab"), vec![1]);
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+use components::dashboard::flaky_tests_table::FlakyTestsTable;
+use leptos::{either::Either, leptos_dom::helpers::event_target_value, prelude::*};
+use state::FlakyTest;
+
+#[server]
+pub async fn get_flaky_tests(
+    days: Option<u32>,
+    min_invocations: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<FlakyTest>, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    let since = std::time::SystemTime::now()
+        .checked_sub(std::time::Duration::from_secs(
+            days.unwrap_or(7) as u64 * 24 * 60 * 60,
+        ))
+        .unwrap_or(std::time::UNIX_EPOCH);
+    db::run(global.db_manager.clone(), move |db| {
+        db.get_flaky_tests(&since, min_invocations.unwrap_or(3), limit.unwrap_or(100))
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn FlakyTests() -> impl IntoView {
+    let (days, set_days) = signal(7u32);
+    let (min_invocations, set_min_invocations) = signal(3usize);
+
+    let flaky = Resource::new(
+        move || (days.get(), min_invocations.get()),
+        |(days, min_invocations)| async move {
+            get_flaky_tests(Some(days), Some(min_invocations), None)
+                .await
+                .inspect_err(|e| tracing::warn!("Failed to get flaky tests: {e:#?}"))
+                .ok()
+        },
+    );
+
+    view! {
+        <div class="p-4 bg-white dark:bg-gray-800 text-gray-900 dark:text-white h-[calc(100vh-64px)] overflow-y-auto">
+            <div class="container mx-auto">
+                <h1 class="text-3xl font-bold mb-6">"Flaky Tests"</h1>
+                <div class="flex space-x-4 mb-6 items-end">
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                            "Window"
+                        </label>
+                        <select
+                            class="p-2 bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md dark:text-white"
+                            on:change=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<u32>() {
+                                    set_days.set(v);
+                                }
+                            }
+                        >
+                            <option value="1">"Last day"</option>
+                            <option value="7" selected>"Last 7 days"</option>
+                            <option value="30">"Last 30 days"</option>
+                            <option value="90">"Last 90 days"</option>
+                        </select>
+                    </div>
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                            "Minimum Invocations"
+                        </label>
+                        <input
+                            type="number"
+                            min="1"
+                            class="p-2 w-24 bg-white dark:bg-gray-700 border border-gray-300 dark:border-gray-500 rounded-md dark:text-white"
+                            prop:value=move || min_invocations.get().to_string()
+                            on:change=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<usize>() {
+                                    set_min_invocations.set(v.max(1));
+                                }
+                            }
+                        />
+                    </div>
+                </div>
+                <Suspense fallback=|| {
+                    view! { <p class="text-gray-400">"Loading..."</p> }
+                }>
+                    {move || {
+                        flaky
+                            .read()
+                            .as_ref()
+                            .map(|data| match data {
+                                Some(tests) => {
+                                    Either::Right(view! { <FlakyTestsTable tests=tests.clone() /> })
+                                }
+                                None => {
+                                    Either::Left(
+                                        view! {
+                                            <p class="text-red-500 mt-8 text-center">
+                                                "Failed to load flaky tests."
+                                            </p>
+                                        },
+                                    )
+                                }
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
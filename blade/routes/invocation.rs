@@ -65,6 +65,8 @@ pub fn Invocation() -> impl IntoView {
                     state::Status::Success
                         | state::Status::Fail
                         | state::Status::Skip
+                        | state::Status::Incomplete
+                        | state::Status::Interrupted
                         | state::Status::Unknown
                 ) || !inv.is_live;
                 invocation.set(inv.clone());
@@ -0,0 +1,71 @@
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+use components::invocationlist::{InvocationFilterControls, InvocationTable};
+use leptos::{either::Either, prelude::*};
+use state::{InvocationPage, InvocationQuery};
+
+#[server]
+pub async fn list_invocations(query: InvocationQuery) -> Result<InvocationPage, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run(global.db_manager.clone(), move |db| {
+        db.list_invocations(&query)
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn Invocations() -> impl IntoView {
+    let (query, set_query) = signal(InvocationQuery::default());
+
+    let page = Resource::new(
+        move || query.get(),
+        |query| async move {
+            list_invocations(query)
+                .await
+                .inspect_err(|e| tracing::warn!("Failed to list invocations: {e:#?}"))
+                .ok()
+        },
+    );
+
+    view! {
+        <div class="p-4 bg-white dark:bg-gray-800 text-gray-900 dark:text-white h-[calc(100vh-64px)] overflow-y-auto">
+            <div class="container mx-auto">
+                <h1 class="text-3xl font-bold mb-6">"Invocations"</h1>
+                <InvocationFilterControls set_query=set_query />
+                <Suspense fallback=|| {
+                    view! { <p class="text-gray-400">"Loading..."</p> }
+                }>
+                    {move || {
+                        page.read()
+                            .as_ref()
+                            .map(|data| match data {
+                                Some(page) => {
+                                    Either::Right(
+                                        view! {
+                                            <InvocationTable
+                                                page=page.clone()
+                                                query=query
+                                                set_query=set_query
+                                            />
+                                        },
+                                    )
+                                }
+                                None => {
+                                    Either::Left(
+                                        view! {
+                                            <p class="text-red-500 mt-8 text-center">
+                                                "Failed to load invocations."
+                                            </p>
+                                        },
+                                    )
+                                }
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
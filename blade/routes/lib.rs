@@ -1,9 +1,14 @@
 pub mod app;
 pub mod artifact;
+pub mod compare;
 pub mod dashboard;
 pub mod details;
 pub mod empty;
+pub mod events;
+pub mod flaky;
 pub mod invocation;
+pub mod invocations;
+pub mod metrics;
 pub mod profile;
 pub mod summary;
 pub mod test;
@@ -0,0 +1,105 @@
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+
+use components::{
+    buildmetrics::BuildMetricsView,
+    card::Card,
+    executionbreakdown::ExecutionBreakdown,
+    summaryheader::SummaryHeader,
+};
+use leptos::{either::Either, prelude::*};
+
+#[cfg(feature = "ssr")]
+fn execution_stats(
+    db: &mut dyn state::DB,
+    inv: state::InvocationResults,
+) -> anyhow::Result<state::ExecutionStats> {
+    Ok(state::ExecutionStats {
+        metrics: db.get_build_metrics(&inv.id)?,
+        strategies: db.get_action_strategies(&inv.id)?,
+        invocation_id: inv.id,
+        start: inv.start,
+    })
+}
+
+/// Returns the execution stats of the invocation and of the previous
+/// invocation with the same pattern, if any.
+#[server]
+pub async fn get_execution_stats(
+    uuid: String,
+) -> Result<(state::ExecutionStats, Option<state::ExecutionStats>), ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run_group(global.db_manager.clone(), move |db| {
+        let current = db.get_shallow_invocation(&uuid)?;
+        let previous = db.get_previous_invocation(&uuid)?;
+        Ok((
+            execution_stats(db, current)?,
+            previous.map(|p| execution_stats(db, p)).transpose()?,
+        ))
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
+#[allow(non_snake_case)]
+#[component]
+pub fn InvocationMetrics() -> impl IntoView {
+    let invocation = expect_context::<RwSignal<state::InvocationResults>>();
+    // BuildMetrics is one of the last events, so refetch once the build ends.
+    let res = Resource::new(
+        move || invocation.with(|invocation| (invocation.id.clone(), invocation.end)),
+        |(id, _)| get_execution_stats(id),
+    );
+
+    view! {
+        <div class="flex flex-col m-1 p-1 dark:bg-gray-800">
+            <Card class="p-3 m-2">
+                <SummaryHeader />
+            </Card>
+            <div class="h-[73dvh] overflow-auto">
+                <Suspense fallback=move || {
+                    view! { <div class="text-center py-8">"Loading build metrics..."</div> }
+                }>
+                    {move || {
+                        res.get()
+                            .map(|res| match res {
+                                Ok((current, previous)) => {
+                                    let metrics = current.metrics.clone();
+                                    Either::Left(
+                                        view! {
+                                            <ExecutionBreakdown current=current previous=previous />
+                                            {match metrics {
+                                                Some(metrics) => {
+                                                    Either::Left(
+                                                        view! { <BuildMetricsView metrics=metrics /> },
+                                                    )
+                                                }
+                                                None => {
+                                                    Either::Right(
+                                                        view! {
+                                                            <div class="text-center py-8 text-gray-500">
+                                                                "No build metrics reported for this invocation."
+                                                            </div>
+                                                        },
+                                                    )
+                                                }
+                                            }}
+                                        },
+                                    )
+                                }
+                                Err(e) => {
+                                    Either::Right(
+                                        view! {
+                                            <div class="text-center py-8 text-red-500">
+                                                {format!("Failed to load build metrics: {e}")}
+                                            </div>
+                                        },
+                                    )
+                                }
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
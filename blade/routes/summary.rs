@@ -2,6 +2,7 @@
 use std::sync::Arc;
 
 use components::{
+    actionfailures::ActionFailureList,
     card::Card,
     shellout::ShellOut,
     summaryheader::SummaryHeader,
@@ -26,6 +27,16 @@ pub async fn get_output(uuid: String) -> Result<String, ServerFnError> {
         .map_err(internal_err)
 }
 
+#[server]
+pub async fn get_action_failures(uuid: String) -> Result<Vec<state::ActionFailure>, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run(global.db_manager.clone(), move |db| {
+        db.get_action_failures(&uuid)
+    })
+    .await
+    .map_err(internal_err)
+}
+
 #[allow(non_snake_case)]
 #[component]
 pub fn Summary() -> impl IntoView {
@@ -46,6 +57,19 @@ pub fn Summary() -> impl IntoView {
             }
         }
     });
+    let failures = LocalResource::new(move || {
+        let id = params
+            .with(|p| p.as_ref().map(|p| p.id.clone()).unwrap_or_default())
+            .unwrap_or_default();
+        // Refetch as the invocation progresses since failures arrive mid-build.
+        let _ = invocation.with(|i| i.status);
+        async move {
+            if id.is_empty() {
+                return vec![];
+            }
+            get_action_failures(id).await.unwrap_or_default()
+        }
+    });
     Effect::new(move || {
         let out = output_res.read();
         match out.as_ref() {
@@ -61,6 +85,8 @@ pub fn Summary() -> impl IntoView {
             state::Status::Success
                 | state::Status::Fail
                 | state::Status::Skip
+                | state::Status::Incomplete
+                | state::Status::Interrupted
                 | state::Status::Unknown
         ) || !invocation.read_untracked().is_live;
         if !done {
@@ -81,9 +107,26 @@ pub fn Summary() -> impl IntoView {
                 <Card class="h-full w-1/4 max-w-1/4 md:max-w-xs p-1 m-1 flex-1 overflow-x-auto overflow-auto">
                     {TargetList()}
                 </Card>
-                <Card class="h-full max-w-full w-full p-1 m-1 flex-1 overflow-x-auto overflow-auto">
-                    <ShellOut text=output />
-                </Card>
+                <div class="h-full max-w-full w-full flex-1 flex flex-col overflow-hidden">
+                    <Suspense>
+                        {move || {
+                            failures
+                                .get()
+                                .filter(|f| !f.is_empty())
+                                .map(|f| {
+                                    view! {
+                                        <Card class="max-h-1/3 p-1 m-1 overflow-auto">
+                                            <h2 class="font-bold text-lg p-2">"Failed Actions"</h2>
+                                            <ActionFailureList failures=f />
+                                        </Card>
+                                    }
+                                })
+                        }}
+                    </Suspense>
+                    <Card class="h-full max-w-full w-full p-1 m-1 flex-1 overflow-x-auto overflow-auto">
+                        <ShellOut text=output />
+                    </Card>
+                </div>
             </div>
         </div>
     }
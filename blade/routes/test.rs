@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use components::{
     card::Card,
+    lastgreen::LastGreenView,
     shellout::ShellOut,
     testartifactlist::TestArtifactList,
     testinsights::TestInsights,
@@ -45,6 +46,23 @@ pub async fn get_artifact(uri: String) -> Result<Vec<u8>, ServerFnError<String>>
     }
 }
 
+// How many earlier results of the test to search for the last pass.
+#[cfg(feature = "ssr")]
+const LAST_GREEN_HISTORY: usize = 500;
+
+#[server]
+pub async fn get_last_green(
+    invocation_id: String,
+    test_name: String,
+) -> Result<state::LastGreen, ServerFnError> {
+    let global: Arc<state::Global> = use_context::<Arc<state::Global>>().unwrap();
+    db::run(global.db_manager.clone(), move |db| {
+        db.get_last_green(&invocation_id, &test_name, LAST_GREEN_HISTORY)
+    })
+    .await
+    .map_err(|e| ServerFnError::ServerError(format!("{e:#?}")))
+}
+
 fn get_run(
     run: &Option<i32>,
     shard: &Option<i32>,
@@ -229,6 +247,26 @@ pub fn Test() -> impl IntoView {
             }
         }
     });
+    // Only failing tests have a last green invocation worth looking up.
+    let failing = Memo::new(move |_| {
+        test.read()
+            .as_ref()
+            .ok()
+            .filter(|t| t.status == state::Status::Fail)
+            .map(|t| (invocation.read().id.clone(), t.name.clone()))
+    });
+    let last_green = Resource::new(
+        move || failing.get(),
+        |failing| async move {
+            match failing {
+                Some((id, name)) => get_last_green(id.clone(), name)
+                    .await
+                    .ok()
+                    .map(|lg| (lg, id)),
+                None => None,
+            }
+        },
+    );
     provide_context(test);
     provide_context(test_run);
     provide_context(test_xml);
@@ -245,6 +283,20 @@ pub fn Test() -> impl IntoView {
                     <Card class="flex p-3 m-2">
                         <TestSummary />
                     </Card>
+                    <Suspense>
+                        {move || {
+                            last_green
+                                .get()
+                                .flatten()
+                                .map(|(last_green, current)| {
+                                    view! {
+                                        <Card class="flex p-3 m-2">
+                                            <LastGreenView last_green=last_green current=current />
+                                        </Card>
+                                    }
+                                })
+                        }}
+                    </Suspense>
 
                     <div class="h-[73dvh] flex items-start justify-start justify-items-center">
                         <Card class="h-full w-1/4 max-w-1/4 md:max-w-xs p-1 m-1 flex-1 overflow-x-auto overflow-auto">
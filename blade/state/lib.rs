@@ -10,6 +10,12 @@ pub enum Status {
     Success,
     Fail,
     Skip,
+    // The build ended before it finished, e.g. a target that was announced
+    // but never built.
+    Incomplete,
+    // The invocation stopped sending events without finishing, e.g. because
+    // the Bazel client died.
+    Interrupted,
 }
 
 impl std::fmt::Display for Status {
@@ -23,11 +29,59 @@ impl Status {
             "Success" => Status::Success,
             "Fail" => Status::Fail,
             "Skip" => Status::Skip,
+            "Incomplete" => Status::Incomplete,
+            "Interrupted" => Status::Interrupted,
             _ => Status::Unknown,
         }
     }
 }
 
+/// Bazel's detailed test outcome. `Status` stays the coarse pass/fail used for
+/// filtering; this preserves the distinction between e.g. a flake and a
+/// timeout.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum TestStatus {
+    NoStatus,
+    Passed,
+    Flaky,
+    Timeout,
+    Failed,
+    Incomplete,
+    RemoteFailure,
+    FailedToBuild,
+    ToolHaltedBeforeTesting,
+}
+
+impl std::fmt::Display for TestStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { write!(f, "{self:?}") }
+}
+
+impl TestStatus {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "NoStatus" => Some(TestStatus::NoStatus),
+            "Passed" => Some(TestStatus::Passed),
+            "Flaky" => Some(TestStatus::Flaky),
+            "Timeout" => Some(TestStatus::Timeout),
+            "Failed" => Some(TestStatus::Failed),
+            "Incomplete" => Some(TestStatus::Incomplete),
+            "RemoteFailure" => Some(TestStatus::RemoteFailure),
+            "FailedToBuild" => Some(TestStatus::FailedToBuild),
+            "ToolHaltedBeforeTesting" => Some(TestStatus::ToolHaltedBeforeTesting),
+            _ => None,
+        }
+    }
+
+    /// Flaky tests eventually passed, so they count as a success.
+    pub fn status(&self) -> Status {
+        match self {
+            TestStatus::Passed | TestStatus::Flaky => Status::Success,
+            TestStatus::NoStatus => Status::Unknown,
+            _ => Status::Fail,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq)]
 pub struct Target {
     pub name: String,
@@ -35,6 +89,18 @@ pub struct Target {
     pub kind: String,
     pub start: std::time::SystemTime,
     pub end: Option<std::time::SystemTime>,
+    pub failure: Option<TargetFailure>,
+}
+
+/// Why a target failed, from `TargetComplete` or `Aborted`.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq, Default)]
+pub struct TargetFailure {
+    pub message: String,
+    // The failure detail category, e.g. `spawn`, or the abort reason, e.g.
+    // `analysis_failure`.
+    pub category: String,
+    // The targets that actually broke. Empty if it was this one.
+    pub root_causes: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -49,6 +115,7 @@ pub struct TestRun {
     pub shard: i32,
     pub attempt: i32,
     pub status: Status,
+    pub detailed_status: Option<TestStatus>,
     pub details: String,
     pub duration: std::time::Duration,
     pub files: HashMap<String, Artifact>,
@@ -58,12 +125,231 @@ pub struct TestRun {
 pub struct Test {
     pub name: String,
     pub status: Status,
+    pub detailed_status: Option<TestStatus>,
     pub duration: std::time::Duration,
     pub end: std::time::SystemTime,
     pub runs: Vec<TestRun>,
     pub num_runs: usize,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ActionFailure {
+    pub label: String,
+    pub mnemonic: String,
+    pub exit_code: i32,
+    pub command_line: Vec<String>,
+    pub primary_output: Option<String>,
+    pub stdout_uri: Option<String>,
+    pub stderr_uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct ActionMetrics {
+    pub mnemonic: String,
+    pub actions_executed: i64,
+    // Milliseconds since the epoch.
+    pub first_started_ms: i64,
+    pub last_ended_ms: i64,
+    pub system_time: std::time::Duration,
+    pub user_time: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct RunnerMetrics {
+    // Spawn runner name, e.g. "remote cache hit", "linux-sandbox".
+    pub name: String,
+    pub exec_kind: String,
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct BuildMetrics {
+    pub actions_created: i64,
+    pub actions_executed: i64,
+    pub action_cache_hits: i64,
+    pub action_cache_misses: i64,
+    pub used_heap_size_post_build: i64,
+    pub peak_post_gc_heap_size: i64,
+    pub targets_loaded: i64,
+    pub targets_configured: i64,
+    pub packages_loaded: i64,
+    pub cpu_time_ms: i64,
+    pub wall_time_ms: i64,
+    pub analysis_phase_time_ms: i64,
+    pub execution_phase_time_ms: i64,
+    pub source_artifacts_read_bytes: i64,
+    pub source_artifacts_read_count: i64,
+    pub output_artifacts_seen_bytes: i64,
+    pub output_artifacts_seen_count: i64,
+    pub output_artifacts_from_action_cache_bytes: i64,
+    pub output_artifacts_from_action_cache_count: i64,
+    pub top_level_artifacts_bytes: i64,
+    pub top_level_artifacts_count: i64,
+    // Only populated for a single invocation, not in metrics history.
+    pub actions: Vec<ActionMetrics>,
+    pub runners: Vec<RunnerMetrics>,
+}
+
+impl BuildMetrics {
+    pub fn action_cache_hit_rate(&self) -> Option<f64> {
+        let total = self.action_cache_hits + self.action_cache_misses;
+        if total == 0 {
+            return None;
+        }
+        Some(self.action_cache_hits as f64 / total as f64)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct BuildMetricsPoint {
+    pub invocation_id: String,
+    pub start: std::time::SystemTime,
+    pub metrics: BuildMetrics,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum ExecutionCategory {
+    Remote,
+    RemoteCache,
+    DiskCache,
+    ActionCache,
+    Sandbox,
+    Worker,
+    Local,
+    Other,
+}
+
+impl std::fmt::Display for ExecutionCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            ExecutionCategory::Remote => "Remote",
+            ExecutionCategory::RemoteCache => "Remote Cache",
+            ExecutionCategory::DiskCache => "Disk Cache",
+            ExecutionCategory::ActionCache => "Action Cache",
+            ExecutionCategory::Sandbox => "Sandbox",
+            ExecutionCategory::Worker => "Worker",
+            ExecutionCategory::Local => "Local",
+            ExecutionCategory::Other => "Other",
+        })
+    }
+}
+
+impl ExecutionCategory {
+    /// Classifies a Bazel spawn runner or strategy name such as
+    /// "remote cache hit" or "linux-sandbox". Returns None for the synthetic
+    /// "total" runner.
+    pub fn classify(runner: &str) -> Option<Self> {
+        let r = runner.to_lowercase();
+        Some(match r.as_str() {
+            "total" => return None,
+            _ if r.contains("remote cache") => ExecutionCategory::RemoteCache,
+            _ if r.contains("disk cache") => ExecutionCategory::DiskCache,
+            _ if r.contains("cached locally") || r.contains("action cache") => {
+                ExecutionCategory::ActionCache
+            },
+            _ if r.contains("sandbox") => ExecutionCategory::Sandbox,
+            _ if r.contains("worker") => ExecutionCategory::Worker,
+            _ if r.contains("remote") => ExecutionCategory::Remote,
+            "local" | "standalone" => ExecutionCategory::Local,
+            _ => ExecutionCategory::Other,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct StrategyCount {
+    pub mnemonic: String,
+    pub strategy: String,
+    pub count: i64,
+}
+
+/// A single build event as Bazel sent it, converted to proto3 JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct RawEvent {
+    pub sequence: i64,
+    // Name of the BuildEventId field that is set, e.g. "target_completed".
+    pub kind: String,
+    pub json: String,
+}
+
+/// A build as reported by the BES lifecycle events. Its invocation attempts
+/// are the invocations with the same `build_id`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct Build {
+    pub id: String,
+    pub project_id: String,
+    pub enqueued: Option<std::time::SystemTime>,
+    pub finished: Option<std::time::SystemTime>,
+    // Name of the BuildStatus result, e.g. "COMMAND_SUCCEEDED".
+    pub result: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ExecutionStats {
+    pub invocation_id: String,
+    pub start: std::time::SystemTime,
+    pub metrics: Option<BuildMetrics>,
+    // Per mnemonic strategies of individually reported actions.
+    pub strategies: Vec<StrategyCount>,
+}
+
+impl ExecutionStats {
+    /// Number of spawns per execution category, largest first. Uses the
+    /// invocation wide runner counts when Bazel reported them and falls back
+    /// to the individually reported actions otherwise.
+    pub fn category_counts(&self) -> Vec<(ExecutionCategory, i64)> {
+        let mut counts: HashMap<ExecutionCategory, i64> = HashMap::new();
+        match self.metrics.as_ref().filter(|m| !m.runners.is_empty()) {
+            Some(m) => m.runners.iter().for_each(|r| {
+                if let Some(c) = ExecutionCategory::classify(&r.name) {
+                    *counts.entry(c).or_default() += r.count as i64;
+                }
+            }),
+            None => self.strategies.iter().for_each(|s| {
+                if let Some(c) = ExecutionCategory::classify(&s.strategy) {
+                    *counts.entry(c).or_default() += s.count;
+                }
+            }),
+        }
+        let mut ret = counts
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ret
+    }
+
+    /// Share of spawns per execution category in [0, 1].
+    pub fn category_shares(&self) -> Vec<(ExecutionCategory, f64)> {
+        let counts = self.category_counts();
+        let total = counts.iter().map(|(_, n)| n).sum::<i64>();
+        if total == 0 {
+            return vec![];
+        }
+        counts
+            .into_iter()
+            .map(|(c, n)| (c, n as f64 / total as f64))
+            .collect()
+    }
+}
+
+/// How Bazel exited, e.g. `TESTS_FAILED` (3).
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct ExitCode {
+    pub name: String,
+    pub code: i32,
+}
+
+/// The decoded `failure_details.FailureDetail` of a failed build.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct FailureDetail {
+    pub message: String,
+    // The failure category, e.g. `remote_execution` or `test_command`.
+    pub category: String,
+    // The category specific code, e.g. `CACHE_NOT_FOUND`.
+    pub code: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct InvocationResults {
     pub id: String,
@@ -77,6 +363,27 @@ pub struct InvocationResults {
     pub last_heartbeat: Option<std::time::SystemTime>,
     pub is_live: bool,
     pub profile_uri: Option<String>,
+    // Set from the BES lifecycle events. A build is retried with new
+    // invocation attempts.
+    pub build_id: Option<String>,
+    pub attempt_number: Option<i64>,
+    // Set from the BES stream (`--project_id`, `--bes_keywords`) and from
+    // the `--tool_tag` option.
+    pub project_id: Option<String>,
+    pub notification_keywords: Vec<String>,
+    pub tool_tag: Option<String>,
+    // Set from `BuildFinished`.
+    pub exit_code: Option<ExitCode>,
+    pub failure_detail: Option<FailureDetail>,
+    // Set from `BuildStarted`.
+    pub build_tool_version: Option<String>,
+    pub workspace_directory: Option<String>,
+    pub working_directory: Option<String>,
+    pub server_pid: Option<i64>,
+    pub options_description: Option<String>,
+    // Set from the `BUILD_USER` and `BUILD_HOST` workspace status keys.
+    pub build_user: Option<String>,
+    pub build_host: Option<String>,
 }
 
 impl Default for InvocationResults {
@@ -93,6 +400,20 @@ impl Default for InvocationResults {
             last_heartbeat: None,
             is_live: false,
             profile_uri: None,
+            build_id: None,
+            attempt_number: None,
+            project_id: None,
+            notification_keywords: vec![],
+            tool_tag: None,
+            exit_code: None,
+            failure_detail: None,
+            build_tool_version: None,
+            workspace_directory: None,
+            working_directory: None,
+            server_pid: None,
+            options_description: None,
+            build_user: None,
+            build_host: None,
         }
     }
 }
@@ -137,6 +458,8 @@ pub struct BuildOptions {
     pub cmd_line: Vec<String>,
     pub explicit_cmd_line: Vec<String>,
     pub build_metadata: HashMap<String, String>,
+    // The `WorkspaceStatus` keys, e.g. `BUILD_SCM_REVISION`.
+    pub workspace_status: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -184,6 +507,24 @@ pub enum TestFilterItem {
         from: std::time::SystemTime,
         to: std::time::SystemTime,
     },
+    ProjectId(String),
+    NotificationKeyword(String),
+    ToolTag(String),
+}
+
+impl TestFilterItem {
+    /// The invocation listing filter for items that only match fields of the
+    /// invocation itself.
+    pub fn invocation_filter(&self) -> Option<InvocationFilter> {
+        let mut filter = InvocationFilter::default();
+        match self {
+            TestFilterItem::ProjectId(p) => filter.project_id = Some(p.clone()),
+            TestFilterItem::NotificationKeyword(k) => filter.keyword = Some(k.clone()),
+            TestFilterItem::ToolTag(t) => filter.tool_tag = Some(t.clone()),
+            _ => return None,
+        }
+        Some(filter)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -193,25 +534,337 @@ pub struct TestFilter {
     pub filter: TestFilterItem,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum InvocationSortField {
+    #[default]
+    Start,
+    End,
+    Command,
+    Status,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct InvocationFilter {
+    pub status: Option<Status>,
+    pub command: Option<String>,
+    pub pattern: Option<String>,
+    pub start_from: Option<std::time::SystemTime>,
+    pub start_to: Option<std::time::SystemTime>,
+    // Build metadata key/value pairs that must all match exactly.
+    pub metadata: Vec<(String, String)>,
+    pub project_id: Option<String>,
+    // A single notification keyword the invocation must have.
+    pub keyword: Option<String>,
+    pub tool_tag: Option<String>,
+    // The exit code name, e.g. `BUILD_FAILURE`.
+    pub exit_code: Option<String>,
+    // The failure detail category, e.g. `remote_execution`.
+    pub failure_category: Option<String>,
+    pub build_tool_version: Option<String>,
+    // Workspace status key/value pairs that must all match exactly.
+    pub workspace_status: Vec<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct InvocationQuery {
+    pub filter: InvocationFilter,
+    pub sort_by: InvocationSortField,
+    pub ascending: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for InvocationQuery {
+    fn default() -> Self {
+        Self {
+            filter: InvocationFilter::default(),
+            sort_by: InvocationSortField::Start,
+            ascending: false,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct InvocationPage {
+    // Shallow invocations: targets and tests are not populated.
+    pub invocations: Vec<InvocationResults>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct FlakyTest {
+    pub name: String,
+    // Number of invocations in the window that ran this test.
+    pub total_invocations: usize,
+    // Invocations where the test had mixed attempt results or flipped status
+    // relative to the previous invocation with identical build metadata.
+    pub flaky_invocations: usize,
+    pub last_flaky_invocation: Option<String>,
+    pub last_flaky_start: Option<std::time::SystemTime>,
+}
+
+impl FlakyTest {
+    pub fn flake_rate(&self) -> f64 {
+        if self.total_invocations == 0 {
+            return 0.0;
+        }
+        self.flaky_invocations as f64 / self.total_invocations as f64
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct InvocationRef {
+    pub invocation_id: String,
+    pub start: std::time::SystemTime,
+    // Commit from the build metadata, if reported.
+    pub commit: Option<String>,
+}
+
+/// Where a failing test started failing, among earlier invocations with
+/// matching flags.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct LastGreen {
+    // Oldest invocation of the current failure streak.
+    pub first_failed: Option<InvocationRef>,
+    // Most recent invocation before the streak where the test passed.
+    pub last_passed: Option<InvocationRef>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ChangeKind {
+    NewlyFailing,
+    NewlyPassing,
+    Added,
+    Removed,
+    // Any other status transition, e.g. passing to skipped.
+    Changed,
+}
+
+impl ChangeKind {
+    fn classify(before: Option<Status>, after: Option<Status>) -> Option<Self> {
+        Some(match (before, after) {
+            (None, None) => return None,
+            (Some(b), Some(a)) if a == b => return None,
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (_, Some(Status::Fail)) => ChangeKind::NewlyFailing,
+            (Some(Status::Fail), Some(Status::Success)) => ChangeKind::NewlyPassing,
+            _ => ChangeKind::Changed,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct StatusChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub before: Option<Status>,
+    pub after: Option<Status>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct DurationDelta {
+    pub name: String,
+    pub before: std::time::Duration,
+    pub after: std::time::Duration,
+}
+
+impl DurationDelta {
+    /// Change in seconds, positive when the test got slower.
+    pub fn delta_secs(&self) -> f64 { self.after.as_secs_f64() - self.before.as_secs_f64() }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct ListDiff {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl ListDiff {
+    // Order insensitive, but repeated entries are compared by count.
+    fn new(before: &[String], after: &[String]) -> Self {
+        let mut counts: HashMap<&str, i64> = HashMap::new();
+        before
+            .iter()
+            .for_each(|b| *counts.entry(b).or_default() -= 1);
+        after
+            .iter()
+            .for_each(|a| *counts.entry(a).or_default() += 1);
+        let mut ret = Self::default();
+        for (k, n) in counts {
+            let v = if n < 0 {
+                &mut ret.removed
+            } else {
+                &mut ret.added
+            };
+            (0..n.abs()).for_each(|_| v.push(k.to_string()));
+        }
+        ret.removed.sort();
+        ret.added.sort();
+        ret
+    }
+
+    pub fn is_empty(&self) -> bool { self.removed.is_empty() && self.added.is_empty() }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct MetadataChange {
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Differences between two invocations, from `before` to `after`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct InvocationDiff {
+    // Shallow invocations: targets and tests are not populated.
+    pub before: InvocationResults,
+    pub after: InvocationResults,
+    pub targets: Vec<StatusChange>,
+    pub tests: Vec<StatusChange>,
+    // Tests present in both invocations, largest change first.
+    pub test_durations: Vec<DurationDelta>,
+    pub explicit_cmd_line: ListDiff,
+    pub explicit_startup: ListDiff,
+    pub build_metadata: Vec<MetadataChange>,
+}
+
+fn status_changes<T>(
+    before: &HashMap<String, T>,
+    after: &HashMap<String, T>,
+    status: fn(&T) -> Status,
+) -> Vec<StatusChange> {
+    let mut names = before.keys().chain(after.keys()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let b = before.get(name).map(status);
+            let a = after.get(name).map(status);
+            ChangeKind::classify(b, a).map(|kind| StatusChange {
+                name: name.clone(),
+                kind,
+                before: b,
+                after: a,
+            })
+        })
+        .collect()
+}
+
+impl InvocationDiff {
+    pub fn new(
+        before: &InvocationResults,
+        before_options: &BuildOptions,
+        after: &InvocationResults,
+        after_options: &BuildOptions,
+    ) -> Self {
+        let mut test_durations = after
+            .tests
+            .values()
+            .filter_map(|a| {
+                before.tests.get(&a.name).map(|b| DurationDelta {
+                    name: a.name.clone(),
+                    before: b.duration,
+                    after: a.duration,
+                })
+            })
+            .filter(|d| d.before != d.after)
+            .collect::<Vec<_>>();
+        test_durations.sort_by(|a, b| {
+            b.delta_secs()
+                .abs()
+                .total_cmp(&a.delta_secs().abs())
+                .then(a.name.cmp(&b.name))
+        });
+
+        let mut keys = before_options
+            .build_metadata
+            .keys()
+            .chain(after_options.build_metadata.keys())
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        let build_metadata = keys
+            .into_iter()
+            .filter_map(|k| {
+                let b = before_options.build_metadata.get(k);
+                let a = after_options.build_metadata.get(k);
+                (a != b).then(|| MetadataChange {
+                    key: k.clone(),
+                    before: b.cloned(),
+                    after: a.cloned(),
+                })
+            })
+            .collect();
+
+        let shallow = |i: &InvocationResults| InvocationResults {
+            targets: HashMap::new(),
+            tests: HashMap::new(),
+            ..i.clone()
+        };
+        Self {
+            before: shallow(before),
+            after: shallow(after),
+            targets: status_changes(&before.targets, &after.targets, |t| t.status),
+            tests: status_changes(&before.tests, &after.tests, |t| t.status),
+            test_durations,
+            explicit_cmd_line: ListDiff::new(
+                &before_options.explicit_cmd_line,
+                &after_options.explicit_cmd_line,
+            ),
+            explicit_startup: ListDiff::new(
+                &before_options.explicit_startup,
+                &after_options.explicit_startup,
+            ),
+            build_metadata,
+        }
+    }
+}
+
+/// Console output stored after a known line, see `DB::get_output_since`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Default)]
+pub struct OutputSince {
+    // Set when lines up to the known line were deleted, e.g. to redraw
+    // progress: how many of them are still stored.
+    pub kept: Option<usize>,
+    // Lines after the known line with their ids, oldest first.
+    pub lines: Vec<(i64, String)>,
+}
+
 cfg_if! {
 if #[cfg(feature = "ssr")] {
 use derivative::Derivative;
 
 pub trait DB {
+    /// Runs `f` in a transaction that is rolled back if it fails. Nested
+    /// calls use savepoints.
+    fn transaction(&mut self, f: Box<dyn FnOnce(&mut dyn DB) -> anyhow::Result<()> + '_>) -> anyhow::Result<()>;
     fn upsert_shallow_invocation(&mut self, invocation: &InvocationResults) -> anyhow::Result<()>;
     #[allow(clippy::type_complexity)]
     fn update_shallow_invocation(&mut self, invocation_id: &str, upd: Box<dyn FnOnce(&mut InvocationResults) -> anyhow::Result<()>>) -> anyhow::Result<()>;
     fn get_progress(&mut self, invocation_id: &str) -> anyhow::Result<String>;
+    /// Returns the output lines after the line with id `after_id`, 0 for all of them. Lines are only ever deleted from the end.
+    fn get_output_since(&mut self, invocation_id: &str, after_id: i64) -> anyhow::Result<OutputSince>;
     fn upsert_target(&mut self, id: &str, target: &Target) -> anyhow::Result<()>;
     fn update_target_result(&mut self, invocation_id: &str, name: &str, status: Status, end: std::time::SystemTime) -> anyhow::Result<()>;
+    fn update_target_failure(&mut self, invocation_id: &str, name: &str, failure: &TargetFailure) -> anyhow::Result<()>;
     fn upsert_test(&mut self, id: &str, test: &Test) -> anyhow::Result<String>;
     fn get_test(&mut self, id: &str, name: &str) -> anyhow::Result<Test>;
     fn update_test_result(&mut self, invocation_id: &str, name: &str, status: Status, duration: std::time::Duration, num_runs: usize) -> anyhow::Result<()>;
+    /// Marks the targets and tests that are still in progress as incomplete and returns how many there were.
+    fn mark_incomplete(&mut self, invocation_id: &str, end: std::time::SystemTime) -> anyhow::Result<usize>;
     fn upsert_test_run(&mut self, id: &str, test_id: &str, run: &TestRun) -> anyhow::Result<()>;
     fn get_invocation(&mut self, id: &str) -> anyhow::Result<InvocationResults>;
     fn get_shallow_invocation(&mut self, id: &str) -> anyhow::Result<InvocationResults>;
     fn delete_invocation(&mut self, id: &str) -> anyhow::Result<()>;
     fn delete_invocations_since(&mut self, ts: &std::time::SystemTime) -> anyhow::Result<usize>;
+    /// Returns the invocations that are still in progress without a heartbeat since `heartbeat_before`.
+    fn get_abandoned_invocations(&mut self, heartbeat_before: &std::time::SystemTime) -> anyhow::Result<Vec<InvocationResults>>;
     fn update_invocation_heartbeat(&mut self, invocation_id: &str) -> anyhow::Result<()>;
     fn insert_options(&mut self, id: &str, options: &BuildOptions) -> anyhow::Result<()>;
     fn get_options(&mut self, id: &str) -> anyhow::Result<BuildOptions>;
@@ -219,12 +872,56 @@ pub trait DB {
     fn insert_output_lines(&mut self, id: &str, lines: Vec<String>) -> anyhow::Result<()>;
     fn get_test_history(&mut self, test_name: &str, filters: &[TestFilter], max_results: usize, default_days: Option<u32>) -> anyhow::Result<TestHistory>;
     fn search_test_names(&mut self, pattern: &str, limit: usize) -> anyhow::Result<Vec<String>>;
+    fn list_invocations(&mut self, query: &InvocationQuery) -> anyhow::Result<InvocationPage>;
+    fn insert_action_failure(&mut self, invocation_id: &str, failure: &ActionFailure) -> anyhow::Result<()>;
+    fn get_action_failures(&mut self, invocation_id: &str) -> anyhow::Result<Vec<ActionFailure>>;
+    fn upsert_build_metrics(&mut self, invocation_id: &str, metrics: &BuildMetrics) -> anyhow::Result<()>;
+    fn get_build_metrics(&mut self, invocation_id: &str) -> anyhow::Result<Option<BuildMetrics>>;
+    fn get_build_metrics_history(&mut self, since: &std::time::SystemTime, limit: usize) -> anyhow::Result<Vec<BuildMetricsPoint>>;
+    fn insert_action_strategy(&mut self, invocation_id: &str, key: &str, mnemonic: &str, strategy: &str) -> anyhow::Result<()>;
+    fn get_action_strategies(&mut self, invocation_id: &str) -> anyhow::Result<Vec<StrategyCount>>;
+    fn get_previous_invocation(&mut self, invocation_id: &str) -> anyhow::Result<Option<InvocationResults>>;
+    fn insert_raw_event(&mut self, invocation_id: &str, event: &RawEvent) -> anyhow::Result<()>;
+    fn get_raw_event_kinds(&mut self, invocation_id: &str) -> anyhow::Result<Vec<(String, usize)>>;
+    fn get_raw_events(&mut self, invocation_id: &str, kind: Option<&str>, after: i64, limit: usize) -> anyhow::Result<Vec<RawEvent>>;
+    fn get_stream_sequence_number(&mut self, invocation_id: &str, stream: &str) -> anyhow::Result<Option<i64>>;
+    fn update_stream_sequence_number(&mut self, invocation_id: &str, stream: &str, sequence_number: i64) -> anyhow::Result<()>;
+    fn upsert_build(&mut self, build: &Build) -> anyhow::Result<()>;
+    fn get_build(&mut self, build_id: &str) -> anyhow::Result<Option<Build>>;
+    fn get_build_invocations(&mut self, build_id: &str) -> anyhow::Result<Vec<InvocationResults>>;
+    fn get_last_green(&mut self, invocation_id: &str, test_name: &str, max_history: usize) -> anyhow::Result<LastGreen>;
+    fn get_flaky_tests(&mut self, since: &std::time::SystemTime, min_invocations: usize, limit: usize) -> anyhow::Result<Vec<FlakyTest>>;
 }
 
 pub trait DBManager: std::marker::Send + std::marker::Sync {
     fn get(&self) -> anyhow::Result<Box<dyn DB>>;
 }
 
+/// Selects the invocations whose build events are stored for the events
+/// page, like the recorder selects the streams it records.
+#[derive(Debug, Clone, Default)]
+pub struct RawEventSelection {
+    pub all: bool,
+    pub invocation_ids: std::collections::HashSet<String>,
+    // Build metadata that must all match for an invocation to be selected.
+    pub metadata: Vec<(String, String)>,
+}
+
+impl RawEventSelection {
+    /// Whether the invocation is selected before its build metadata is known.
+    pub fn selects(&self, invocation_id: &str) -> bool {
+        self.all || self.invocation_ids.contains(invocation_id)
+    }
+
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        !self.metadata.is_empty()
+            && self
+                .metadata
+                .iter()
+                .all(|(k, v)| metadata.get(k).is_some_and(|m| m == v))
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Global {
@@ -235,7 +932,212 @@ pub struct Global {
     pub allow_local: bool,
     pub retention: Option<std::time::Duration>,
     pub session_lock_time: std::time::Duration,
+    pub raw_events: RawEventSelection,
 }
 
 }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn invocation(
+        id: &str,
+        targets: &[(&str, Status)],
+        tests: &[(&str, Status, u64)],
+    ) -> InvocationResults {
+        InvocationResults {
+            id: id.to_string(),
+            targets: targets
+                .iter()
+                .map(|(name, status)| {
+                    (
+                        name.to_string(),
+                        Target {
+                            name: name.to_string(),
+                            status: *status,
+                            kind: "rule".to_string(),
+                            start: UNIX_EPOCH,
+                            end: None,
+                            failure: None,
+                        },
+                    )
+                })
+                .collect(),
+            tests: tests
+                .iter()
+                .map(|(name, status, secs)| {
+                    (
+                        name.to_string(),
+                        Test {
+                            name: name.to_string(),
+                            status: *status,
+                            detailed_status: None,
+                            duration: Duration::from_secs(*secs),
+                            end: UNIX_EPOCH,
+                            runs: vec![],
+                            num_runs: 1,
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn strings(v: &[&str]) -> Vec<String> { v.iter().map(|s| s.to_string()).collect() }
+
+    #[test]
+    fn test_classify() {
+        use Status::*;
+        assert_eq!(ChangeKind::classify(None, None), None);
+        assert_eq!(ChangeKind::classify(Some(Fail), Some(Fail)), None);
+        assert_eq!(
+            ChangeKind::classify(None, Some(Success)),
+            Some(ChangeKind::Added)
+        );
+        assert_eq!(
+            ChangeKind::classify(Some(Fail), None),
+            Some(ChangeKind::Removed)
+        );
+        assert_eq!(
+            ChangeKind::classify(Some(Success), Some(Fail)),
+            Some(ChangeKind::NewlyFailing)
+        );
+        assert_eq!(
+            ChangeKind::classify(Some(Skip), Some(Fail)),
+            Some(ChangeKind::NewlyFailing)
+        );
+        assert_eq!(
+            ChangeKind::classify(Some(Fail), Some(Success)),
+            Some(ChangeKind::NewlyPassing)
+        );
+        assert_eq!(
+            ChangeKind::classify(Some(Success), Some(Skip)),
+            Some(ChangeKind::Changed)
+        );
+    }
+
+    #[test]
+    fn test_list_diff() {
+        let diff = ListDiff::new(
+            &strings(&["--config=ci", "-c", "opt", "--keep_going"]),
+            &strings(&["--keep_going", "--config=ci", "--config=ci", "-c", "dbg"]),
+        );
+        assert_eq!(diff.removed, strings(&["opt"]));
+        assert_eq!(diff.added, strings(&["--config=ci", "dbg"]));
+        assert!(!diff.is_empty());
+
+        // Reordering is not a change.
+        assert!(ListDiff::new(&strings(&["a", "b"]), &strings(&["b", "a"])).is_empty());
+    }
+
+    #[test]
+    fn test_invocation_diff() {
+        use Status::*;
+        let before = invocation(
+            "before",
+            &[
+                ("//:same", Success),
+                ("//:breaks", Success),
+                ("//:gone", Success),
+            ],
+            &[("//:fixed_test", Fail, 10), ("//:slower_test", Success, 5)],
+        );
+        let after = invocation(
+            "after",
+            &[("//:same", Success), ("//:breaks", Fail), ("//:new", Skip)],
+            &[
+                ("//:fixed_test", Success, 11),
+                ("//:slower_test", Success, 25),
+                ("//:new_test", Success, 1),
+            ],
+        );
+        let before_options = BuildOptions {
+            explicit_cmd_line: strings(&["--config=ci"]),
+            explicit_startup: strings(&["--host_jvm_args=-Xmx2g"]),
+            build_metadata: HashMap::from([
+                ("COMMIT_SHA".to_string(), "abc".to_string()),
+                ("ROLE".to_string(), "CI".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let after_options = BuildOptions {
+            explicit_cmd_line: strings(&["--config=ci", "--keep_going"]),
+            explicit_startup: strings(&["--host_jvm_args=-Xmx2g"]),
+            build_metadata: HashMap::from([
+                ("COMMIT_SHA".to_string(), "def".to_string()),
+                ("PR".to_string(), "42".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let diff = InvocationDiff::new(&before, &before_options, &after, &after_options);
+        assert!(diff.before.targets.is_empty() && diff.after.tests.is_empty());
+        assert_eq!(
+            diff.targets
+                .iter()
+                .map(|c| (c.name.as_str(), c.kind, c.before, c.after))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "//:breaks",
+                    ChangeKind::NewlyFailing,
+                    Some(Success),
+                    Some(Fail)
+                ),
+                ("//:gone", ChangeKind::Removed, Some(Success), None),
+                ("//:new", ChangeKind::Added, None, Some(Skip)),
+            ]
+        );
+        assert_eq!(
+            diff.tests
+                .iter()
+                .map(|c| (c.name.as_str(), c.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("//:fixed_test", ChangeKind::NewlyPassing),
+                ("//:new_test", ChangeKind::Added),
+            ]
+        );
+        // Only tests in both invocations, largest change first.
+        assert_eq!(
+            diff.test_durations
+                .iter()
+                .map(|d| (d.name.as_str(), d.delta_secs()))
+                .collect::<Vec<_>>(),
+            vec![("//:slower_test", 20.0), ("//:fixed_test", 1.0)]
+        );
+        assert_eq!(
+            diff.explicit_cmd_line,
+            ListDiff {
+                removed: vec![],
+                added: strings(&["--keep_going"]),
+            }
+        );
+        assert!(diff.explicit_startup.is_empty());
+        assert_eq!(
+            diff.build_metadata,
+            vec![
+                MetadataChange {
+                    key: "COMMIT_SHA".to_string(),
+                    before: Some("abc".to_string()),
+                    after: Some("def".to_string()),
+                },
+                MetadataChange {
+                    key: "PR".to_string(),
+                    before: None,
+                    after: Some("42".to_string()),
+                },
+                MetadataChange {
+                    key: "ROLE".to_string(),
+                    before: Some("CI".to_string()),
+                    after: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Helpers shared by the tests of several crates.
+
+use std::sync::Arc;
+
+/// A database that can't be connected to.
+pub struct Unavailable;
+
+impl state::DBManager for Unavailable {
+    fn get(&self) -> anyhow::Result<Box<dyn state::DB>> {
+        Err(anyhow::anyhow!("connection refused"))
+    }
+}
+
+/// A new SQLite database in `tmp`.
+pub fn sqlite(tmp: &tempdir::TempDir) -> Arc<dyn state::DBManager> {
+    db::new(&format!(
+        "sqlite://{}",
+        tmp.path().join("test.db").display()
+    ))
+    .unwrap()
+}
+
+/// Server state with the defaults of the command line flags.
+pub fn global(db_manager: Arc<dyn state::DBManager>) -> state::Global {
+    state::Global {
+        db_manager,
+        bytestream_client: bytestream::Client::new(),
+        allow_local: false,
+        retention: None,
+        session_lock_time: std::time::Duration::from_secs(3600),
+        raw_events: Default::default(),
+    }
+}
@@ -42,6 +42,20 @@ struct Args {
     /// Max concurrent requests in the stream send loop
     #[arg(long = "concurrency", default_value_t = 100u32)]
     concurrency: u32,
+
+    /// Replay a stream recorded with `blade --record_dir` instead of
+    /// generating synthetic streams
+    #[arg(long = "replay")]
+    replay: Option<String>,
+
+    /// Replay speed relative to the recorded timing; 0 sends events without
+    /// delay
+    #[arg(long = "speed", default_value_t = 1.0)]
+    speed: f64,
+
+    /// Keep the recorded invocation id instead of replaying under a new one
+    #[arg(long = "keep_invocation_id", default_value_t = false)]
+    keep_invocation_id: bool,
 }
 
 #[tokio::main]
@@ -55,6 +69,10 @@ async fn main() -> Result<()> {
     let args = Args::parse();
     info!(?args, "starting streamstress");
 
+    if let Some(path) = args.replay.clone() {
+        return replay(&args, &path).await;
+    }
+
     let payload = vec![b'X'; args.payload_size as usize];
 
     // Launch streams with bounded concurrency to avoid overwhelming the local
@@ -154,6 +172,98 @@ async fn run_single_stream(
     Ok(())
 }
 
+fn event_time(obe: &bes::OrderedBuildEvent) -> Option<Duration> {
+    let t = obe.event.as_ref()?.event_time.as_ref()?;
+    Some(Duration::new(
+        t.seconds.try_into().ok()?,
+        t.nanos.try_into().ok()?,
+    ))
+}
+
+async fn replay(args: &Args, path: &str) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("read {path}"))?;
+    let mut buf = &data[..];
+    let mut events = vec![];
+    while !buf.is_empty() {
+        events.push(
+            bes::OrderedBuildEvent::decode_length_delimited(&mut buf)
+                .context("decode recording")?,
+        );
+    }
+    // Replaying under the recorded id is rejected once the original session
+    // has ended, so default to a fresh one.
+    let invocation_id = (!args.keep_invocation_id).then(|| Uuid::new_v4().to_string());
+    info!(events = events.len(), ?invocation_id, "replaying {path}");
+
+    let channel = build_channel(args).await?;
+    let mut client = bes::publish_build_event_client::PublishBuildEventClient::new(channel);
+    let total = events.len();
+    let mut acked = 0;
+    let mut prev: Option<Duration> = None;
+    // A recording holds the original stream followed by its retries, each of
+    // which starts over at an earlier sequence number.
+    for stream in events.chunk_by(|a, b| b.sequence_number > a.sequence_number) {
+        acked += replay_stream(
+            args,
+            &mut client,
+            stream,
+            invocation_id.as_deref(),
+            &mut prev,
+        )
+        .await?;
+    }
+    info!(events = total, acked, "replay completed");
+    Ok(())
+}
+
+async fn replay_stream(
+    args: &Args,
+    client: &mut bes::publish_build_event_client::PublishBuildEventClient<Channel>,
+    events: &[bes::OrderedBuildEvent],
+    invocation_id: Option<&str>,
+    prev: &mut Option<Duration>,
+) -> Result<usize> {
+    let (tx, rx) = tokio::sync::mpsc::channel::<bes::PublishBuildToolEventStreamRequest>(128);
+    let response_stream = client
+        .publish_build_tool_event_stream(Request::new(ReceiverStream::new(rx)))
+        .await
+        .context("open stream")?
+        .into_inner();
+    let acks = tokio::spawn(async move {
+        tokio::pin!(response_stream);
+        let mut acked = 0usize;
+        while let Some(res) = response_stream.next().await {
+            res?;
+            acked += 1;
+        }
+        Ok::<usize, tonic::Status>(acked)
+    });
+
+    for obe in events {
+        let mut obe = obe.clone();
+        if let Some(id) = invocation_id
+            && let Some(stream_id) = obe.stream_id.as_mut()
+        {
+            stream_id.invocation_id = id.to_string();
+        }
+        let t = event_time(&obe);
+        if args.speed > 0.0
+            && let (Some(prev), Some(t)) = (*prev, t)
+            && let Some(d) = t.checked_sub(prev)
+        {
+            sleep(d.div_f64(args.speed)).await;
+        }
+        *prev = t.or(*prev);
+        let request = bes::PublishBuildToolEventStreamRequest {
+            ordered_build_event: Some(obe),
+            ..Default::default()
+        };
+        tx.send(request).await.context("send event")?;
+    }
+    drop(tx);
+    acks.await?.context("stream failed")
+}
+
 async fn send_bazel_event(
     tx: &tokio::sync::mpsc::Sender<bes::PublishBuildToolEventStreamRequest>,
     stream_id: &bes::StreamId,